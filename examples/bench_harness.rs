@@ -0,0 +1,50 @@
+//! Run a benchmark workload and print the timings as JSON.
+//!
+//! Usage: cargo run --release --features bench --example bench_harness -- [static|churn|streaming] [frames] [output]
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, WindowResolution},
+};
+
+use bevy_simple_tilemap::{
+    bench::{BenchWorkload, TileMapBenchHarness},
+    prelude::*,
+};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let workload = args.next().unwrap_or_else(|| "churn".to_owned());
+    let Some(workload) = BenchWorkload::from_name(&workload) else {
+        eprintln!("Unknown workload '{workload}'. Expected one of: static, churn, streaming");
+        std::process::exit(1);
+    };
+
+    let mut harness = TileMapBenchHarness::new(workload);
+
+    if let Some(frames) = args.next() {
+        harness = harness.with_frames(frames.parse().expect("frames should be a number"));
+    }
+
+    if let Some(output) = args.next() {
+        harness = harness.with_output(output);
+    }
+
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: WindowResolution::new(1280.0, 720.0).with_scale_factor_override(1.0),
+                        present_mode: PresentMode::AutoNoVsync,
+                        ..Default::default()
+                    }),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .add_plugins(SimpleTileMapPlugin)
+        .add_plugins(harness)
+        .run();
+}
@@ -84,13 +84,7 @@ fn update_tiles_system(mut query: Query<&mut TileMap>, mut count: Local<u32>) {
 
             for x in X_RANGE {
                 // Add tile change to list
-                tiles.push((
-                    IVec3::new(x, y, 0),
-                    Some(Tile {
-                        sprite_index,
-                        ..Default::default()
-                    }),
-                ));
+                tiles.push((IVec3::new(x, y, 0), Some(Tile::new(sprite_index))));
             }
 
             i += 1;
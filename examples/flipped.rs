@@ -77,130 +77,34 @@ fn setup(
     let atlas_handle = texture_atlases.add(atlas);
 
     let tiles = vec![
-        (
-            ivec3(-4, 0, 0),
-            Some(Tile {
-                sprite_index: 0,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(-3, 0, 0),
-            Some(Tile {
-                sprite_index: 0,
-                flags: TileFlags::FLIP_X,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(-2, 0, 0),
-            Some(Tile {
-                sprite_index: 1,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(-1, 0, 0),
-            Some(Tile {
-                sprite_index: 1,
-                flags: TileFlags::FLIP_X,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(0, 0, 0),
-            Some(Tile {
-                sprite_index: 2,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(1, 0, 0),
-            Some(Tile {
-                sprite_index: 2,
-                flags: TileFlags::FLIP_X,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(2, 0, 0),
-            Some(Tile {
-                sprite_index: 3,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(3, 0, 0),
-            Some(Tile {
-                sprite_index: 3,
-                flags: TileFlags::FLIP_X,
-                ..Default::default()
-            }),
-        ),
+        (ivec3(-4, 0, 0), Some(Tile::new(0))),
+        (ivec3(-3, 0, 0), Some(Tile::new(0).with_flags(TileFlags::FLIP_X))),
+        (ivec3(-2, 0, 0), Some(Tile::new(1))),
+        (ivec3(-1, 0, 0), Some(Tile::new(1).with_flags(TileFlags::FLIP_X))),
+        (ivec3(0, 0, 0), Some(Tile::new(2))),
+        (ivec3(1, 0, 0), Some(Tile::new(2).with_flags(TileFlags::FLIP_X))),
+        (ivec3(2, 0, 0), Some(Tile::new(3))),
+        (ivec3(3, 0, 0), Some(Tile::new(3).with_flags(TileFlags::FLIP_X))),
         // Y-flipped row
-        (
-            ivec3(-4, 1, 0),
-            Some(Tile {
-                sprite_index: 0,
-                flags: TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
-        ),
+        (ivec3(-4, 1, 0), Some(Tile::new(0).with_flags(TileFlags::FLIP_Y))),
         (
             ivec3(-3, 1, 0),
-            Some(Tile {
-                sprite_index: 0,
-                flags: TileFlags::FLIP_X | TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(-2, 1, 0),
-            Some(Tile {
-                sprite_index: 1,
-                flags: TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
+            Some(Tile::new(0).with_flags(TileFlags::FLIP_X | TileFlags::FLIP_Y)),
         ),
+        (ivec3(-2, 1, 0), Some(Tile::new(1).with_flags(TileFlags::FLIP_Y))),
         (
             ivec3(-1, 1, 0),
-            Some(Tile {
-                sprite_index: 1,
-                flags: TileFlags::FLIP_X | TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(0, 1, 0),
-            Some(Tile {
-                sprite_index: 2,
-                flags: TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
+            Some(Tile::new(1).with_flags(TileFlags::FLIP_X | TileFlags::FLIP_Y)),
         ),
+        (ivec3(0, 1, 0), Some(Tile::new(2).with_flags(TileFlags::FLIP_Y))),
         (
             ivec3(1, 1, 0),
-            Some(Tile {
-                sprite_index: 2,
-                flags: TileFlags::FLIP_X | TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(2, 1, 0),
-            Some(Tile {
-                sprite_index: 3,
-                flags: TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
+            Some(Tile::new(2).with_flags(TileFlags::FLIP_X | TileFlags::FLIP_Y)),
         ),
+        (ivec3(2, 1, 0), Some(Tile::new(3).with_flags(TileFlags::FLIP_Y))),
         (
             ivec3(3, 1, 0),
-            Some(Tile {
-                sprite_index: 3,
-                flags: TileFlags::FLIP_X | TileFlags::FLIP_Y,
-                ..Default::default()
-            }),
+            Some(Tile::new(3).with_flags(TileFlags::FLIP_X | TileFlags::FLIP_Y)),
         ),
     ];
 
@@ -76,13 +76,7 @@ fn update_tiles_system(mut query: Query<&mut TileMap>, mut count: Local<i32>) {
 
         for x in 0..10 {
             // Add tile change
-            tiles.push((
-                IVec3::new(x, line_y, 1),
-                Some(Tile {
-                    sprite_index: 0,
-                    ..Default::default()
-                }),
-            ));
+            tiles.push((IVec3::new(x, line_y, 1), Some(Tile::new(0))));
         }
 
         // Perform tile update
@@ -107,13 +101,7 @@ fn setup(
     // Background (layer 0) tiles
     for y in 0..10 {
         for x in 0..10 {
-            tiles.push((
-                IVec3::new(x, y, 0),
-                Some(Tile {
-                    sprite_index: 2,
-                    ..Default::default()
-                }),
-            ));
+            tiles.push((IVec3::new(x, y, 0), Some(Tile::new(2))));
         }
     }
 
@@ -0,0 +1,168 @@
+//! Renders a tilemap without depth testing into a custom pass that has no depth attachment.
+
+use bevy::{
+    core_pipeline::{
+        core_2d::{extract_core_2d_camera_phases, Transparent2d},
+        upscaling::UpscalingNode,
+    },
+    ecs::query::QueryItem,
+    math::{ivec3, uvec2},
+    prelude::*,
+    render::{
+        camera::{CameraRenderGraph, ExtractedCamera},
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, RenderSubGraph, ViewNode, ViewNodeRunner,
+        },
+        render_phase::ViewSortedRenderPhases,
+        render_resource::RenderPassDescriptor,
+        renderer::RenderContext,
+        sync_world::RenderEntity,
+        view::ViewTarget,
+        Extract, ExtractSchedule, RenderApp,
+    },
+    window::WindowResolution,
+};
+
+use bevy_simple_tilemap::prelude::*;
+
+fn main() {
+    let mut app = App::new();
+
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: WindowResolution::new(1280.0, 720.0).with_scale_factor_override(1.0),
+                    ..Default::default()
+                }),
+                ..default()
+            })
+            .set(ImagePlugin::default_nearest()),
+    )
+    .add_plugins(SimpleTileMapPlugin)
+    .add_systems(Startup, setup);
+
+    app.sub_app_mut(RenderApp)
+        .add_systems(
+            ExtractSchedule,
+            // Core 2D phase extraction removes the phases of views that are not `Camera2d`s
+            extract_no_depth_camera_phases.after(extract_core_2d_camera_phases),
+        )
+        .add_render_sub_graph(NoDepthGraph)
+        .add_render_graph_node::<ViewNodeRunner<NoDepthPassNode>>(NoDepthGraph, NoDepthNode::Pass)
+        .add_render_graph_node::<ViewNodeRunner<UpscalingNode>>(NoDepthGraph, NoDepthNode::Upscaling)
+        .add_render_graph_edges(NoDepthGraph, (NoDepthNode::Pass, NoDepthNode::Upscaling));
+
+    app.run();
+}
+
+/// Camera rendered by [`NoDepthGraph`] instead of the core 2D graph
+#[derive(Component, Default)]
+#[require(
+    Camera,
+    CameraRenderGraph(|| CameraRenderGraph::new(NoDepthGraph)),
+    OrthographicProjection(OrthographicProjection::default_2d),
+    Msaa(|| Msaa::Off)
+)]
+struct NoDepthCamera;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderSubGraph)]
+struct NoDepthGraph;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+enum NoDepthNode {
+    Pass,
+    Upscaling,
+}
+
+/// Give views of [`NoDepthCamera`]s a transparent phase, which tilemaps without depth testing are queued into
+fn extract_no_depth_camera_phases(
+    mut transparent_2d_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    cameras: Extract<Query<(RenderEntity, &Camera), With<NoDepthCamera>>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        if camera.is_active {
+            transparent_2d_phases.insert_or_clear(entity);
+        }
+    }
+}
+
+/// Draws the transparent phase of the view, with only a color attachment
+#[derive(Default)]
+struct NoDepthPassNode;
+
+impl ViewNode for NoDepthPassNode {
+    type ViewQuery = (&'static ExtractedCamera, &'static ViewTarget);
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, target): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.view_entity();
+
+        let Some(transparent_phase) = world
+            .get_resource::<ViewSortedRenderPhases<Transparent2d>>()
+            .and_then(|phases| phases.get(&view_entity))
+        else {
+            return Ok(());
+        };
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("no_depth_pass"),
+            color_attachments: &[Some(target.get_color_attachment())],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        if let Some(viewport) = camera.viewport.as_ref() {
+            render_pass.set_camera_viewport(viewport);
+        }
+
+        if let Err(err) = transparent_phase.render(&mut render_pass, world, view_entity) {
+            error!("Error rendering the no-depth phase: {err:?}");
+        }
+
+        Ok(())
+    }
+}
+
+fn setup(
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    // Load tilesheet texture and make a texture atlas from it
+    let image = asset_server.load("textures/tilesheet.png");
+    let atlas = TextureAtlasLayout::from_grid(uvec2(16, 16), 4, 1, Some(uvec2(1, 1)), None);
+    let atlas_handle = texture_atlases.add(atlas);
+
+    let tiles = vec![
+        (ivec3(-1, 0, 0), Some(Tile::new(0))),
+        (ivec3(1, 0, 0), Some(Tile::new(1))),
+        (ivec3(0, -1, 0), Some(Tile::new(2))),
+        (ivec3(0, 1, 0), Some(Tile::new(3))),
+        // Drawn over the tiles of layer 0, as the transparent phase is sorted by z
+        (ivec3(0, 0, 1), Some(Tile::new(0))),
+    ];
+
+    // Set up tilemap without depth testing, as the pass has no depth attachment
+    let mut tilemap = TileMap::new(image, atlas_handle);
+    tilemap.depth_test = false;
+    tilemap.set_tiles(tiles);
+
+    // Spawn camera with the custom pass
+    commands.spawn(NoDepthCamera);
+
+    // Spawn tilemap
+    commands.spawn((
+        tilemap,
+        Transform {
+            scale: Vec3::splat(3.0),
+            ..Default::default()
+        },
+    ));
+}
@@ -77,34 +77,10 @@ fn setup(
     let atlas_handle = texture_atlases.add(atlas);
 
     let tiles = vec![
-        (
-            ivec3(-1, 0, 0),
-            Some(Tile {
-                sprite_index: 0,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(1, 0, 0),
-            Some(Tile {
-                sprite_index: 1,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(0, -1, 0),
-            Some(Tile {
-                sprite_index: 2,
-                ..Default::default()
-            }),
-        ),
-        (
-            ivec3(0, 1, 0),
-            Some(Tile {
-                sprite_index: 3,
-                ..Default::default()
-            }),
-        ),
+        (ivec3(-1, 0, 0), Some(Tile::new(0))),
+        (ivec3(1, 0, 0), Some(Tile::new(1))),
+        (ivec3(0, -1, 0), Some(Tile::new(2))),
+        (ivec3(0, 1, 0), Some(Tile::new(3))),
     ];
 
     // Set up tilemap
@@ -76,13 +76,7 @@ fn update_tiles1_system(mut query: Query<(&mut TileMap, &mut TileMap1)>) {
         let sprite_index = &mut state.sprite_index;
 
         // Perform tile update
-        tilemap.set_tile(
-            pos.extend(1),
-            Some(Tile {
-                sprite_index: *sprite_index,
-                ..Default::default()
-            }),
-        );
+        tilemap.set_tile(pos.extend(1), Some(Tile::new(*sprite_index)));
 
         pos.x += 1;
         if pos.x > 9 {
@@ -116,13 +110,7 @@ fn update_tiles2_system(mut query: Query<(&mut TileMap, &mut TileMap2)>) {
         }
 
         // Perform tile update
-        tilemap.set_tile(
-            pos.extend(1),
-            Some(Tile {
-                sprite_index: 3,
-                ..Default::default()
-            }),
-        );
+        tilemap.set_tile(pos.extend(1), Some(Tile::new(3)));
 
         match *direction {
             0 => {
@@ -187,13 +175,7 @@ fn setup(
     // Background (layer 0) tiles
     for y in 0..10 {
         for x in 0..10 {
-            tiles.push((
-                IVec3::new(x, y, 0),
-                Some(Tile {
-                    sprite_index: 2,
-                    ..Default::default()
-                }),
-            ));
+            tiles.push((IVec3::new(x, y, 0), Some(Tile::new(2))));
         }
     }
 
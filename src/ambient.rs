@@ -0,0 +1,71 @@
+use bevy::{color::LinearRgba, prelude::*, render::extract_resource::ExtractResource};
+
+/// Ambient light multiplied into the color of all tiles in the shader.
+///
+/// Changing this does not require any tiles to be re-meshed, which makes it suitable for
+/// tinting entire maps every frame, such as for a day/night cycle.
+#[derive(Resource, Clone, Debug, ExtractResource, Reflect)]
+#[reflect(Resource, Debug, Default)]
+pub struct TileMapAmbient {
+    pub color: Color,
+    pub intensity: f32,
+    /// Overrides for groups of layers. The first group containing a layer is used for it.
+    pub groups: Vec<TileMapAmbientGroup>,
+}
+
+/// Ambient light override for a range of layers
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug)]
+pub struct TileMapAmbientGroup {
+    /// First layer in the group (inclusive)
+    pub min_layer: i32,
+    /// Last layer in the group (inclusive)
+    pub max_layer: i32,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Default for TileMapAmbient {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 1.0,
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl TileMapAmbient {
+    /// Get the linear ambient color to apply to the specified layer, with intensity applied
+    pub fn layer_color(&self, layer: i32) -> LinearRgba {
+        let (color, intensity) = self
+            .groups
+            .iter()
+            .find(|group| (group.min_layer..=group.max_layer).contains(&layer))
+            .map(|group| (group.color, group.intensity))
+            .unwrap_or((self.color, self.intensity));
+
+        let color = color.to_linear();
+
+        LinearRgba::new(
+            color.red * intensity,
+            color.green * intensity,
+            color.blue * intensity,
+            1.0,
+        )
+    }
+}
+
+/// Tint multiplied into the color of all tiles of a tilemap in the shader, including alpha.
+///
+/// Like [`TileMapAmbient`], changing this does not require any tiles to be re-meshed,
+/// so it can be used to fade an entire tilemap in or out.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component, Debug, Default)]
+pub struct TileMapColor(pub Color);
+
+impl Default for TileMapColor {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{Tile, TileMap};
+
+/// Animation for a tile, cycling through a sequence of sprite indices
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct TileAnimation {
+    /// Sprite indices of the frames, in the order they will be shown
+    pub frames: Vec<u32>,
+    /// How long each frame is shown
+    pub frame_duration: Duration,
+}
+
+impl TileAnimation {
+    pub fn new(frames: impl Into<Vec<u32>>, frame_duration: Duration) -> Self {
+        Self {
+            frames: frames.into(),
+            frame_duration,
+        }
+    }
+
+    /// Get the sprite index of the frame that should be shown at the specified time
+    pub fn frame_at(&self, elapsed: Duration) -> Option<u32> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let frame_nanos = self.frame_duration.as_nanos();
+        if frame_nanos == 0 {
+            return self.frames.first().copied();
+        }
+
+        let frame = (elapsed.as_nanos() / frame_nanos) % self.frames.len() as u128;
+
+        Some(self.frames[frame as usize])
+    }
+}
+
+/// A tile that is fading out before being removed
+#[derive(Clone, Debug)]
+pub(crate) struct TileFade {
+    pub pos: IVec3,
+    pub duration: Duration,
+    pub elapsed: Duration,
+    /// The tile as it was when the fade started
+    pub original: Option<Tile>,
+}
+
+impl TileFade {
+    pub fn new(pos: IVec3, duration: Duration) -> Self {
+        Self {
+            pos,
+            duration,
+            elapsed: Duration::ZERO,
+            original: None,
+        }
+    }
+}
+
+/// Advance the animation clock of tilemaps that have animations
+pub(crate) fn advance_tile_animations_system(time: Res<Time>, mut tilemap_query: Query<&mut TileMap>) {
+    for mut tilemap in tilemap_query.iter_mut() {
+        if !tilemap.has_animations() {
+            continue;
+        }
+
+        tilemap.animation_time += time.delta();
+    }
+}
+
+/// Fade out tiles that are being removed, and remove them once the fade is complete
+pub(crate) fn fade_tiles_system(time: Res<Time>, mut tilemap_query: Query<&mut TileMap>) {
+    for mut tilemap in tilemap_query.iter_mut() {
+        if tilemap.tile_fades.is_empty() {
+            continue;
+        }
+
+        let mut fades = std::mem::take(&mut tilemap.tile_fades);
+
+        fades.retain_mut(|fade| {
+            fade.elapsed += time.delta();
+
+            if fade.elapsed >= fade.duration {
+                tilemap.set_tile(fade.pos, None);
+                return false;
+            }
+
+            if fade.original.is_none() {
+                fade.original = tilemap.get_tile(fade.pos).cloned();
+            }
+
+            // If there is no tile to fade, there is nothing to remove either
+            let Some(original) = &fade.original else {
+                return false;
+            };
+
+            let remaining = 1.0 - fade.elapsed.as_secs_f32() / fade.duration.as_secs_f32();
+
+            let mut tile = original.clone();
+            tile.color.set_alpha(original.color.alpha() * remaining);
+
+            tilemap.tile_changes.push((fade.pos, Some(tile)));
+
+            true
+        });
+
+        // Fades may have been started by the user in the meantime
+        fades.append(&mut tilemap.tile_fades);
+        tilemap.tile_fades = fades;
+    }
+}
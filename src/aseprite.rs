@@ -0,0 +1,271 @@
+use std::{fmt, io};
+
+use asefile::{AsepriteFile, LayerType};
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+    utils::{HashMap, Instant},
+};
+
+use crate::{Tile, TileMap, TileMapLayer};
+
+/// Tilemap loaded from an Aseprite `.aseprite` or `.ase` file using its tilemap feature.
+///
+/// Each tilemap layer becomes a layer of the tilemap, numbered from 0 with the bottom layer,
+/// and each tileset becomes an image and texture atlas layout. Aseprite's y axis points down,
+/// so the tile at (x, y) in Aseprite is placed at (x, -y), and the top left of the tilemap is at the origin.
+///
+/// Only the first frame is imported. Layer names, opacity and visibility are carried over.
+/// Regular image layers are ignored.
+///
+/// Spawn an [`AsepriteTileMapRoot`] to instantiate the tilemap.
+#[derive(Asset, TypePath, Debug)]
+pub struct AsepriteTileMap {
+    /// Tilesets by their id
+    pub tilesets: HashMap<u32, AsepriteTileset>,
+    pub layers: Vec<AsepriteLayer>,
+}
+
+/// Tileset of an [`AsepriteTileMap`]
+#[derive(Clone, Debug)]
+pub struct AsepriteTileset {
+    pub image: Handle<Image>,
+    pub texture_atlas_layout: Handle<TextureAtlasLayout>,
+}
+
+/// Tilemap layer of an [`AsepriteTileMap`]
+#[derive(Clone, Debug)]
+pub struct AsepriteLayer {
+    /// Tilemap layer the tiles are placed on
+    pub z: i32,
+    /// Id of the tileset used by the layer
+    pub tileset: u32,
+    pub settings: TileMapLayer,
+    pub tiles: Vec<(IVec2, Tile)>,
+}
+
+impl AsepriteTileMap {
+    /// Build the tilemap from the file, queuing its tiles.
+    /// Existing tiles and layer settings are replaced.
+    fn build(&self, tilemap: &mut TileMap) {
+        // The tilemap uses the tileset of the bottom layer, and layers using other tilesets override it
+        let base_tileset = self.layers.first().map(|layer| layer.tileset);
+
+        if let Some(tileset) = base_tileset.and_then(|id| self.tilesets.get(&id)) {
+            tilemap.image = tileset.image.clone();
+            tilemap.texture_atlas_layout = tileset.texture_atlas_layout.clone();
+        }
+
+        tilemap.clear();
+        tilemap.layers.clear();
+        tilemap.layers_changed_at = Instant::now();
+
+        for layer in self.layers.iter() {
+            let mut settings = layer.settings.clone();
+
+            if Some(layer.tileset) != base_tileset {
+                if let Some(tileset) = self.tilesets.get(&layer.tileset) {
+                    settings.image = Some(tileset.image.clone());
+                    settings.texture_atlas_layout = Some(tileset.texture_atlas_layout.clone());
+                }
+            }
+
+            tilemap.set_layer(layer.z, settings);
+            tilemap.set_tiles(
+                layer
+                    .tiles
+                    .iter()
+                    .map(|(pos, tile)| (pos.extend(layer.z), Some(tile.clone()))),
+            );
+        }
+    }
+}
+
+/// Error loading an [`AsepriteTileMap`]
+#[derive(Debug)]
+pub enum AsepriteTileMapLoaderError {
+    Io(io::Error),
+    Aseprite(asefile::AsepriteParseError),
+}
+
+impl fmt::Display for AsepriteTileMapLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read file: {err}"),
+            Self::Aseprite(err) => write!(f, "could not parse file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AsepriteTileMapLoaderError {}
+
+impl From<io::Error> for AsepriteTileMapLoaderError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<asefile::AsepriteParseError> for AsepriteTileMapLoaderError {
+    fn from(err: asefile::AsepriteParseError) -> Self {
+        Self::Aseprite(err)
+    }
+}
+
+/// Loads [`AsepriteTileMap`]s from `.aseprite` and `.ase` files
+#[derive(Default)]
+pub struct AsepriteTileMapLoader;
+
+impl AssetLoader for AsepriteTileMapLoader {
+    type Asset = AsepriteTileMap;
+    type Settings = ();
+    type Error = AsepriteTileMapLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let ase = AsepriteFile::read(bytes.as_slice())?;
+
+        let mut tilesets = HashMap::default();
+
+        for (id, tileset) in ase.tilesets().iter() {
+            let id = id.value();
+
+            // Tileset images are a vertical strip of tiles
+            let Some(tileset_image) = ase.tileset_image(&tileset.id()) else {
+                continue;
+            };
+
+            let tile_size = UVec2::new(tileset.tile_size().width() as u32, tileset.tile_size().height() as u32);
+
+            let image = Image::new(
+                Extent3d {
+                    width: tileset_image.width(),
+                    height: tileset_image.height(),
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                tileset_image.into_raw(),
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::default(),
+            );
+
+            let texture_atlas_layout = TextureAtlasLayout::from_grid(tile_size, 1, tileset.tile_count(), None, None);
+
+            tilesets.insert(
+                id,
+                AsepriteTileset {
+                    image: load_context.add_labeled_asset(format!("tileset{id}"), image),
+                    texture_atlas_layout: load_context
+                        .add_labeled_asset(format!("tileset{id}_layout"), texture_atlas_layout),
+                },
+            );
+        }
+
+        let mut layers = Vec::new();
+
+        // Layers are listed from the bottom up
+        for layer in ase.layers() {
+            let LayerType::Tilemap(tileset_id) = layer.layer_type() else {
+                continue;
+            };
+
+            let Some(tilemap) = ase.tilemap(layer.id(), 0) else {
+                continue;
+            };
+
+            let visible = layer.is_visible();
+            let color = Color::srgba(1.0, 1.0, 1.0, layer.opacity() as f32 / 255.0);
+
+            let mut tiles = Vec::new();
+
+            for y in 0..tilemap.height() {
+                for x in 0..tilemap.width() {
+                    let tile_id = tilemap.tile(x, y).id();
+
+                    // Tile 0 is the empty tile
+                    if tile_id == 0 {
+                        continue;
+                    }
+
+                    tiles.push((
+                        IVec2::new(x as i32, -(y as i32)),
+                        Tile {
+                            sprite_index: tile_id,
+                            color,
+                            ..default()
+                        },
+                    ));
+                }
+            }
+
+            layers.push(AsepriteLayer {
+                z: layers.len() as i32,
+                tileset: tileset_id.value(),
+                settings: TileMapLayer {
+                    name: Some(layer.name().to_string()),
+                    render_layers: (!visible).then(RenderLayers::none),
+                    ..default()
+                },
+                tiles,
+            });
+        }
+
+        Ok(AsepriteTileMap { tilesets, layers })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite", "ase"]
+    }
+}
+
+/// Instantiates an [`AsepriteTileMap`] as a [`TileMap`] on the same entity, once it is loaded.
+/// The tilemap is rebuilt when the file changes, discarding any changes made to it.
+#[derive(Component, Clone, Debug, Deref)]
+pub struct AsepriteTileMapRoot(pub Handle<AsepriteTileMap>);
+
+/// Build the tilemaps of [`AsepriteTileMapRoot`]s when their file is loaded or changes
+pub(crate) fn instantiate_aseprite_tilemaps_system(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<AsepriteTileMap>>,
+    aseprite_tilemaps: Res<Assets<AsepriteTileMap>>,
+    mut root_query: Query<(Entity, Ref<AsepriteTileMapRoot>, Option<&mut TileMap>)>,
+) {
+    let changed_tilemaps: Vec<AssetId<AsepriteTileMap>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, root, tilemap) in root_query.iter_mut() {
+        if !root.is_changed() && !changed_tilemaps.contains(&root.id()) {
+            continue;
+        }
+
+        // Not loaded yet. The tilemap is built once it is.
+        let Some(aseprite_tilemap) = aseprite_tilemaps.get(&root.0) else {
+            continue;
+        };
+
+        match tilemap {
+            Some(mut tilemap) => aseprite_tilemap.build(&mut tilemap),
+            None => {
+                let mut tilemap = TileMap::new(Handle::default(), Handle::default());
+                aseprite_tilemap.build(&mut tilemap);
+                commands.entity(entity).insert(tilemap);
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{Tile, TileFlags, TileMap};
+
+/// Tilemap loaded from a `.tilemap.ron` file.
+///
+/// The file describes the tileset as a grid of sprites, and the tiles of each layer as a sparse list:
+/// ```ron
+/// (
+///     image: "textures/tilesheet.png",
+///     tile_size: (16, 16),
+///     columns: 4,
+///     rows: 1,
+///     // Optional, in pixels
+///     padding: Some((1, 1)),
+///     offset: None,
+///     layers: [
+///         (
+///             z: 0,
+///             name: Some("ground"),
+///             tiles: [
+///                 (pos: (0, 0), sprite_index: 0),
+///                 // Optional flip flags and color
+///                 (pos: (1, 0), sprite_index: 1, flip_x: true),
+///                 (pos: (2, 0), sprite_index: 2, color: Some(Srgba((red: 1.0, green: 0.5, blue: 0.5, alpha: 1.0)))),
+///             ],
+///         ),
+///     ],
+/// )
+/// ```
+///
+/// The image path is relative to the assets folder. Spawn a [`TileMapAssetRoot`] to instantiate the tilemap,
+/// which is rebuilt whenever the file changes if asset hot reloading is enabled.
+#[derive(Asset, TypePath, Debug)]
+pub struct TileMapAsset {
+    #[dependency]
+    pub image: Handle<Image>,
+    pub texture_atlas_layout: Handle<TextureAtlasLayout>,
+    pub layers: Vec<TileMapAssetLayer>,
+}
+
+impl TileMapAsset {
+    /// Queue setting the tiles and layer names of the asset on a tilemap
+    fn set_tiles(&self, tilemap: &mut TileMap) {
+        tilemap.set_tiles(self.layers.iter().flat_map(|layer| {
+            layer
+                .tiles
+                .iter()
+                .map(|tile| (tile.pos.extend(layer.z), Some(tile.to_tile())))
+        }));
+
+        for layer in self.layers.iter() {
+            tilemap.layer_mut(layer.z).name = layer.name.clone();
+        }
+    }
+}
+
+/// Layer of a [`TileMapAsset`]
+#[derive(Clone, Debug, Deserialize)]
+pub struct TileMapAssetLayer {
+    /// Layer index, used as the z coordinate of its tiles
+    pub z: i32,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub tiles: Vec<TileMapAssetTile>,
+}
+
+/// Tile of a [`TileMapAssetLayer`]
+#[derive(Clone, Debug, Deserialize)]
+pub struct TileMapAssetTile {
+    pub pos: IVec2,
+    pub sprite_index: u32,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    /// Tint of the tile. Defaults to white.
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+impl TileMapAssetTile {
+    fn to_tile(&self) -> Tile {
+        let mut flags = TileFlags::empty();
+        flags.set(TileFlags::FLIP_X, self.flip_x);
+        flags.set(TileFlags::FLIP_Y, self.flip_y);
+
+        Tile {
+            sprite_index: self.sprite_index,
+            color: self.color.unwrap_or(Color::WHITE),
+            flags,
+            ..default()
+        }
+    }
+}
+
+/// Contents of a `.tilemap.ron` file, see [`TileMapAsset`]
+#[derive(Deserialize)]
+struct TileMapFile {
+    image: String,
+    tile_size: UVec2,
+    columns: u32,
+    rows: u32,
+    #[serde(default)]
+    padding: Option<UVec2>,
+    #[serde(default)]
+    offset: Option<UVec2>,
+    layers: Vec<TileMapAssetLayer>,
+}
+
+/// Error loading a [`TileMapAsset`]
+#[derive(Debug)]
+pub enum TileMapAssetLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for TileMapAssetLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read tilemap: {err}"),
+            Self::Ron(err) => write!(f, "could not parse tilemap: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TileMapAssetLoaderError {}
+
+impl From<std::io::Error> for TileMapAssetLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for TileMapAssetLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+/// Loads [`TileMapAsset`]s from `.tilemap.ron` files
+#[derive(Default)]
+pub struct TileMapAssetLoader;
+
+impl AssetLoader for TileMapAssetLoader {
+    type Asset = TileMapAsset;
+    type Settings = ();
+    type Error = TileMapAssetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let file: TileMapFile = ron::de::from_bytes(&bytes)?;
+
+        let image = load_context.load(file.image);
+        let texture_atlas_layout = load_context.add_labeled_asset(
+            "texture_atlas_layout".into(),
+            TextureAtlasLayout::from_grid(file.tile_size, file.columns, file.rows, file.padding, file.offset),
+        );
+
+        Ok(TileMapAsset {
+            image,
+            texture_atlas_layout,
+            layers: file.layers,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tilemap.ron"]
+    }
+}
+
+/// Instantiates a [`TileMapAsset`] as a [`TileMap`] on the same entity, once it is loaded.
+/// The tilemap is rebuilt when the asset changes, discarding any changes made to it.
+#[derive(Component, Clone, Debug, Deref)]
+pub struct TileMapAssetRoot(pub Handle<TileMapAsset>);
+
+/// Build the tilemaps of [`TileMapAssetRoot`]s when their asset is loaded or changes
+pub(crate) fn instantiate_tilemap_assets_system(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<TileMapAsset>>,
+    tilemap_assets: Res<Assets<TileMapAsset>>,
+    mut root_query: Query<(Entity, Ref<TileMapAssetRoot>, Option<&mut TileMap>)>,
+) {
+    let changed_assets: Vec<AssetId<TileMapAsset>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, root, tilemap) in root_query.iter_mut() {
+        if !root.is_changed() && !changed_assets.contains(&root.id()) {
+            continue;
+        }
+
+        // Not loaded yet. The tilemap is built once it is.
+        let Some(asset) = tilemap_assets.get(&root.0) else {
+            continue;
+        };
+
+        match tilemap {
+            Some(mut tilemap) => {
+                tilemap.clear();
+                tilemap.image = asset.image.clone();
+                tilemap.texture_atlas_layout = asset.texture_atlas_layout.clone();
+                asset.set_tiles(&mut tilemap);
+            }
+            None => {
+                let mut tilemap = TileMap::new(asset.image.clone(), asset.texture_atlas_layout.clone());
+                asset.set_tiles(&mut tilemap);
+                commands.entity(entity).insert(tilemap);
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::Indices,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, PrimitiveTopology, TextureDimension, TextureFormat},
+    },
+    utils::HashMap,
+};
+
+use crate::{TileFlags, TileMap};
+
+const QUAD_INDICES: [u32; 6] = [0, 2, 3, 0, 1, 2];
+
+const QUAD_VERTEX_POSITIONS: [Vec2; 4] = [
+    Vec2::from_array([-0.5, -0.5]),
+    Vec2::from_array([0.5, -0.5]),
+    Vec2::from_array([0.5, 0.5]),
+    Vec2::from_array([-0.5, 0.5]),
+];
+
+const QUAD_UVS: [Vec2; 4] = [
+    Vec2::from_array([0., 1.]),
+    Vec2::from_array([1., 1.]),
+    Vec2::from_array([1., 0.]),
+    Vec2::from_array([0., 0.]),
+];
+
+impl TileMap {
+    /// Bake the tilemap into static meshes, one per layer, rendered through Bevy's regular 2D mesh path.
+    ///
+    /// Each returned bundle can be spawned as a child of the tilemap entity, after which the tilemap itself
+    /// can be removed or hidden. Tiles are baked with their current animation frame, and layers are positioned
+    /// at their [`TileMap::layer_z`]. Layer fills and parallax are not baked.
+    ///
+    /// Layers whose image or texture atlas layout is not loaded are skipped.
+    pub fn bake_to_mesh(
+        &self,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+        images: &Assets<Image>,
+        texture_atlases: &Assets<TextureAtlasLayout>,
+    ) -> Vec<(Mesh2d, MeshMaterial2d<ColorMaterial>, Transform)> {
+        struct LayerMesh {
+            positions: Vec<[f32; 3]>,
+            uvs: Vec<[f32; 2]>,
+            colors: Vec<[f32; 4]>,
+            indices: Vec<u32>,
+        }
+
+        let mut layer_meshes: HashMap<i32, LayerMesh> = HashMap::default();
+
+        for (pos, tile) in self.iter_tiles() {
+            let (image, texture_atlas_layout) = self.layer_tileset(pos.z);
+
+            let (Some(image), Some(texture_atlas)) = (images.get(image), texture_atlases.get(texture_atlas_layout))
+            else {
+                continue;
+            };
+
+            let sprite_index = self.animated_sprite_index(pos, tile.sprite_index);
+            let Some(rect) = texture_atlas.textures.get(sprite_index as usize) else {
+                continue;
+            };
+
+            // It is assumed and mandated that all sprites in the sprite sheet are the same size
+            let Some(tile_size) = texture_atlas.textures.first().map(|rect| rect.size().as_vec2()) else {
+                continue;
+            };
+
+            let image_size = image.size().as_vec2();
+
+            let mut uvs = QUAD_UVS;
+
+            if tile.flags.contains(TileFlags::FLIP_X) {
+                uvs = [uvs[1], uvs[0], uvs[3], uvs[2]];
+            }
+
+            if tile.flags.contains(TileFlags::FLIP_Y) {
+                uvs = [uvs[3], uvs[2], uvs[1], uvs[0]];
+            }
+
+            let rect = rect.as_rect();
+            let rect_size = rect.size();
+            let quad_size = tile.custom_size.unwrap_or(rect_size);
+            let tile_pos = pos.truncate().as_vec2() * tile_size;
+            let z = tile.flags.depth_bias() as f32 * TileFlags::DEPTH_BIAS_STEP * self.layer_z_scale;
+            let color = tile.render_color(self.color_mode).to_f32_array();
+
+            let layer_mesh = layer_meshes.entry(pos.z).or_insert_with(|| LayerMesh {
+                positions: Vec::new(),
+                uvs: Vec::new(),
+                colors: Vec::new(),
+                indices: Vec::new(),
+            });
+
+            let first_vertex = layer_mesh.positions.len() as u32;
+
+            for (quad_pos, uv) in QUAD_VERTEX_POSITIONS.iter().zip(uvs) {
+                layer_mesh
+                    .positions
+                    .push((tile_pos + *quad_pos * quad_size).extend(z).to_array());
+                layer_mesh
+                    .uvs
+                    .push(((rect.min + uv * rect_size) / image_size).to_array());
+                layer_mesh.colors.push(color);
+            }
+
+            layer_mesh.indices.extend(QUAD_INDICES.iter().map(|i| first_vertex + i));
+        }
+
+        layer_meshes
+            .into_iter()
+            .map(|(layer, layer_mesh)| {
+                let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+                    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, layer_mesh.positions)
+                    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, layer_mesh.uvs)
+                    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, layer_mesh.colors)
+                    .with_inserted_indices(Indices::U32(layer_mesh.indices));
+
+                let (image, _) = self.layer_tileset(layer);
+
+                let material = ColorMaterial {
+                    texture: Some(image.clone()),
+                    alpha_mode: self.alpha_mode,
+                    ..default()
+                };
+
+                (
+                    Mesh2d(meshes.add(mesh)),
+                    MeshMaterial2d(materials.add(material)),
+                    Transform::from_xyz(0.0, 0.0, self.layer_z(layer)),
+                )
+            })
+            .collect()
+    }
+
+    /// Bake the tilemap into an image on the CPU, such as for exporting thumbnails, minimap textures
+    /// or comparing against reference images in tests.
+    ///
+    /// Each tile covers one cell of the size of the first sprite in the texture atlas, with the top left tile
+    /// of the tilemap's bounds at the top left of the image. Layers are drawn from the bottom up,
+    /// blending each tile's tinted sprite over what is below it. Tiles are baked with their current animation frame.
+    ///
+    /// All layers are baked with the specified tileset, and layer fills, offsets and parallax are not baked.
+    /// Returns `None` if the tilemap has no tiles, or the texture atlas has no sprites.
+    pub fn bake_to_image(&self, texture_atlas: &TextureAtlasLayout, image: &Image) -> Option<Image> {
+        let bounds = self.bounds()?;
+        let tile_size = texture_atlas.textures.first()?.size().as_vec2();
+
+        let size = ((bounds.size() + 1).as_vec2() * tile_size).as_uvec2();
+
+        let mut baked = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+
+        let mut tiles: Vec<_> = self.iter_tiles().collect();
+        tiles.sort_by_key(|(pos, tile)| (pos.z, tile.flags.depth_bias()));
+
+        for (pos, tile) in tiles {
+            let sprite_index = self.animated_sprite_index(pos, tile.sprite_index);
+            let Some(rect) = texture_atlas.textures.get(sprite_index as usize) else {
+                continue;
+            };
+
+            let rect_size = rect.size();
+            let quad_size = tile.custom_size.unwrap_or(rect_size.as_vec2());
+            let color = tile.render_color(self.color_mode);
+
+            // Center of the tile in the baked image, whose y axis points down
+            let center = (IVec2::new(pos.x - bounds.min.x, bounds.max.y - pos.y).as_vec2() + 0.5) * tile_size;
+            let min = (center - quad_size / 2.0).round().as_ivec2().max(IVec2::ZERO);
+            let max = (center + quad_size / 2.0).round().as_ivec2().min(size.as_ivec2());
+
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    // Sample the nearest sprite pixel
+                    let mut uv = (IVec2::new(x, y).as_vec2() + 0.5 - (center - quad_size / 2.0)) / quad_size;
+
+                    if tile.flags.contains(TileFlags::FLIP_X) {
+                        uv.x = 1.0 - uv.x;
+                    }
+
+                    if tile.flags.contains(TileFlags::FLIP_Y) {
+                        uv.y = 1.0 - uv.y;
+                    }
+
+                    let source = rect.min + (uv * rect_size.as_vec2()).as_uvec2().min(rect_size - 1);
+                    let Ok(source) = image.get_color_at(source.x, source.y) else {
+                        continue;
+                    };
+
+                    let source = LinearRgba::from_vec4(source.to_linear().to_vec4() * color.to_vec4());
+                    if source.alpha <= 0.0 {
+                        continue;
+                    }
+
+                    let (x, y) = (x as u32, y as u32);
+                    let destination = baked
+                        .get_color_at(x, y)
+                        .map_or(LinearRgba::NONE, |color| color.to_linear());
+
+                    let _ = baked.set_color_at(x, y, blend_over(source, destination).into());
+                }
+            }
+        }
+
+        Some(baked)
+    }
+}
+
+/// Blend a color over another, as with regular alpha blending
+fn blend_over(source: LinearRgba, destination: LinearRgba) -> LinearRgba {
+    let alpha = source.alpha + destination.alpha * (1.0 - source.alpha);
+    if alpha <= 0.0 {
+        return LinearRgba::NONE;
+    }
+
+    let blend = |source_component: f32, destination_component: f32| {
+        (source_component * source.alpha + destination_component * destination.alpha * (1.0 - source.alpha)) / alpha
+    };
+
+    LinearRgba::new(
+        blend(source.red, destination.red),
+        blend(source.green, destination.green),
+        blend(source.blue, destination.blue),
+        alpha,
+    )
+}
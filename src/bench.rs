@@ -0,0 +1,317 @@
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use bevy::{
+    app::AppExit,
+    asset::RenderAssetUsages,
+    math::uvec2,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    render::stats::TileMapRenderStats,
+    streaming::{TileMapStreaming, TileMapStreamingPlugin},
+    tilemap::{row_major_pos, TILES_PER_CHUNK},
+    Chunk, Tile, TileMap,
+};
+
+/// Scripted workload run by [`TileMapBenchHarness`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BenchWorkload {
+    /// A map that is filled once and then left unchanged
+    Static,
+    /// Every tile of the map is changed every frame
+    Churn,
+    /// The camera moves continuously over an infinite map, with chunks generated and unloaded as it goes
+    Streaming,
+}
+
+impl BenchWorkload {
+    /// Name of the workload, as used in the output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Static => "static",
+            Self::Churn => "churn",
+            Self::Streaming => "streaming",
+        }
+    }
+
+    /// Look up a workload by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "static" => Some(Self::Static),
+            "churn" => Some(Self::Churn),
+            "streaming" => Some(Self::Streaming),
+            _ => None,
+        }
+    }
+}
+
+/// Plugin that runs a scripted workload for a number of frames, writes timings as a single line of JSON
+/// and then exits the app. Intended for tracking performance across crate and Bevy upgrades, such as in CI.
+///
+/// Requires [`SimpleTileMapPlugin`](crate::plugin::SimpleTileMapPlugin), as well as asset and image support.
+/// [`TileMapStreamingPlugin`] is added if it isn't already.
+/// The harness spawns its own camera and tilemap, using a generated texture.
+/// Tilemaps are only extracted for rendering while a window exists, so without one only
+/// the main world systems are measured.
+#[derive(Clone, Debug)]
+pub struct TileMapBenchHarness {
+    pub workload: BenchWorkload,
+    /// Number of frames to measure
+    pub frames: u32,
+    /// Number of frames to run before measuring, to let assets load and caches fill
+    pub warmup_frames: u32,
+    /// Size of the map in tiles, for the static and churn workloads
+    pub map_size: UVec2,
+    /// File to write the results to. If `None`, they are printed to stdout.
+    pub output: Option<PathBuf>,
+}
+
+impl TileMapBenchHarness {
+    pub fn new(workload: BenchWorkload) -> Self {
+        Self {
+            workload,
+            frames: 600,
+            warmup_frames: 60,
+            map_size: uvec2(1024, 1024),
+            output: None,
+        }
+    }
+
+    pub fn with_frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    pub fn with_warmup_frames(mut self, warmup_frames: u32) -> Self {
+        self.warmup_frames = warmup_frames;
+        self
+    }
+
+    pub fn with_map_size(mut self, map_size: UVec2) -> Self {
+        self.map_size = map_size;
+        self
+    }
+
+    pub fn with_output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+}
+
+impl Plugin for TileMapBenchHarness {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<TileMapStreamingPlugin>() {
+            app.add_plugins(TileMapStreamingPlugin);
+        }
+
+        app.insert_resource(BenchState {
+            settings: self.clone(),
+            frame: 0,
+            frame_times: Vec::with_capacity(self.frames as usize),
+            tile_counts: Vec::with_capacity(self.frames as usize),
+        })
+        .add_systems(Startup, bench_setup_system)
+        .add_systems(Update, bench_workload_system)
+        .add_systems(Last, bench_record_system);
+    }
+}
+
+#[derive(Resource)]
+struct BenchState {
+    settings: TileMapBenchHarness,
+    frame: u32,
+    frame_times: Vec<Duration>,
+    tile_counts: Vec<usize>,
+}
+
+/// Size of the generated tiles, in pixels
+const BENCH_TILE_SIZE: u32 = 16;
+
+/// Number of sprites in the generated texture
+const BENCH_SPRITE_COUNT: u32 = 4;
+
+/// Distance the camera moves per frame in the streaming workload, in tiles
+const BENCH_STREAMING_SPEED: f32 = 8.0;
+
+/// Sprite index of a tile in a generated chunk, varying by position so that chunks are not uniform
+fn bench_sprite_index(pos: IVec3, frame: u32) -> u32 {
+    (pos.x + pos.y).rem_euclid(BENCH_SPRITE_COUNT as i32) as u32 ^ (frame % BENCH_SPRITE_COUNT)
+}
+
+fn bench_setup_system(
+    mut commands: Commands,
+    state: Res<BenchState>,
+    mut images: ResMut<Assets<Image>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: BENCH_TILE_SIZE * BENCH_SPRITE_COUNT,
+            height: BENCH_TILE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255, 255, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    ));
+
+    let atlas = TextureAtlasLayout::from_grid(
+        uvec2(BENCH_TILE_SIZE, BENCH_TILE_SIZE),
+        BENCH_SPRITE_COUNT,
+        1,
+        None,
+        None,
+    );
+    let atlas_handle = texture_atlases.add(atlas);
+
+    commands.spawn(Camera2d);
+
+    let mut tilemap = TileMap::new(image, atlas_handle);
+
+    match state.settings.workload {
+        BenchWorkload::Static => {
+            set_bench_tiles(&mut tilemap, state.settings.map_size, 0);
+
+            commands.spawn(tilemap);
+        }
+        BenchWorkload::Churn => {
+            commands.spawn(tilemap);
+        }
+        BenchWorkload::Streaming => {
+            let streaming = TileMapStreaming::new(
+                |chunk_pos: IVec3| {
+                    let mut chunk = Chunk::from_chunk_pos(chunk_pos);
+                    let origin = chunk.origin;
+
+                    let tiles: Vec<(IVec3, Option<Tile>)> = (0..TILES_PER_CHUNK)
+                        .map(|i| {
+                            let pos = origin + row_major_pos(i).extend(0);
+
+                            (pos, Some(Tile::new(bench_sprite_index(pos, 0))))
+                        })
+                        .collect();
+
+                    chunk.set_tiles(tiles);
+
+                    Some(chunk)
+                },
+                [0],
+            )
+            .with_max_pending(usize::MAX);
+
+            commands.spawn((tilemap, streaming));
+        }
+    }
+}
+
+/// Set every tile of a map centered on the origin
+fn set_bench_tiles(tilemap: &mut TileMap, map_size: UVec2, frame: u32) {
+    let half_size = (map_size / 2).as_ivec2();
+
+    let tiles = (-half_size.y..map_size.y as i32 - half_size.y).flat_map(|y| {
+        (-half_size.x..map_size.x as i32 - half_size.x).map(move |x| {
+            let pos = IVec3::new(x, y, 0);
+
+            (pos, Some(Tile::new(bench_sprite_index(pos, frame))))
+        })
+    });
+
+    tilemap.set_tiles(tiles);
+}
+
+fn bench_workload_system(
+    state: Res<BenchState>,
+    mut tilemap_query: Query<&mut TileMap>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    match state.settings.workload {
+        BenchWorkload::Static => {}
+        BenchWorkload::Churn => {
+            for mut tilemap in tilemap_query.iter_mut() {
+                set_bench_tiles(&mut tilemap, state.settings.map_size, state.frame);
+            }
+        }
+        BenchWorkload::Streaming => {
+            for mut transform in camera_query.iter_mut() {
+                transform.translation.x += BENCH_STREAMING_SPEED * BENCH_TILE_SIZE as f32;
+            }
+        }
+    }
+}
+
+fn bench_record_system(
+    mut state: ResMut<BenchState>,
+    time: Res<Time<Real>>,
+    render_stats: Res<TileMapRenderStats>,
+    tilemap_query: Query<&TileMap>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let state = &mut *state;
+
+    state.frame += 1;
+
+    if state.frame <= state.settings.warmup_frames {
+        return;
+    }
+
+    state.frame_times.push(time.delta());
+    state.tile_counts.push(render_stats.tile_count);
+
+    if state.frame_times.len() < state.settings.frames as usize {
+        return;
+    }
+
+    let chunk_count = tilemap_query.iter().map(|tilemap| tilemap.chunks.len()).sum();
+    let report = bench_report(state, chunk_count);
+
+    let result = match &state.settings.output {
+        Some(path) => fs::write(path, format!("{report}\n")),
+        None => {
+            println!("{report}");
+            Ok::<(), io::Error>(())
+        }
+    };
+
+    if let Err(err) = result {
+        error!("Failed to write tilemap benchmark results: {err}");
+        app_exit_events.send(AppExit::error());
+        return;
+    }
+
+    app_exit_events.send(AppExit::Success);
+}
+
+/// Format the results as a single line of JSON
+fn bench_report(state: &BenchState, chunk_count: usize) -> String {
+    let mut frame_times: Vec<f64> = state
+        .frame_times
+        .iter()
+        .map(|frame_time| frame_time.as_secs_f64() * 1000.0)
+        .collect();
+
+    frame_times.sort_by(f64::total_cmp);
+
+    let percentile = |p: f64| frame_times[((frame_times.len() - 1) as f64 * p).round() as usize];
+    let mean = frame_times.iter().sum::<f64>() / frame_times.len() as f64;
+    let mean_tiles = state.tile_counts.iter().sum::<usize>() as f64 / state.tile_counts.len() as f64;
+
+    format!(
+        concat!(
+            "{{\"workload\":\"{}\",\"frames\":{},\"mean_ms\":{:.4},\"min_ms\":{:.4},\"p50_ms\":{:.4},",
+            "\"p95_ms\":{:.4},\"p99_ms\":{:.4},\"max_ms\":{:.4},\"chunks\":{},\"mean_rendered_tiles\":{:.1}}}"
+        ),
+        state.settings.workload.name(),
+        frame_times.len(),
+        mean,
+        frame_times[0],
+        percentile(0.5),
+        percentile(0.95),
+        percentile(0.99),
+        frame_times[frame_times.len() - 1],
+        chunk_count,
+        mean_tiles,
+    )
+}
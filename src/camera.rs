@@ -0,0 +1,256 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::TileMap;
+
+/// Optional plugin providing pan, zoom and bounds clamping for 2D cameras viewing tilemaps.
+///
+/// Add a [`TileCamera`] component to a [`Camera2d`] entity to control it.
+/// The camera is driven by the [`TileCameraInput`] resource, which is populated from the
+/// keyboard and mouse according to [`TileCameraKeyBindings`] unless `default_input` is disabled,
+/// in which case it is up to the user to fill it from whatever input handling they use.
+pub struct TileCameraPlugin {
+    /// Populate [`TileCameraInput`] from keyboard and mouse input
+    pub default_input: bool,
+}
+
+impl Default for TileCameraPlugin {
+    fn default() -> Self {
+        Self { default_input: true }
+    }
+}
+
+/// System sets for the tile camera systems
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub enum TileCameraSystem {
+    /// Populates [`TileCameraInput`] from keyboard and mouse input.
+    Input,
+    /// Moves and zooms cameras according to [`TileCameraInput`].
+    Control,
+}
+
+/// Camera controller for viewing tilemaps
+#[derive(Component, Clone, Debug)]
+#[require(Camera2d)]
+pub struct TileCamera {
+    /// Pan speed in pixels per second, at a scale of 1.0
+    pub pan_speed: f32,
+    /// Zoom speed, in scale factor per second
+    pub zoom_speed: f32,
+    /// Minimum camera scale (maximum zoom in)
+    pub min_scale: f32,
+    /// Maximum camera scale (maximum zoom out)
+    pub max_scale: f32,
+    /// Pan the camera when the cursor is within this many logical pixels from the edge of the window
+    pub edge_scroll_margin: Option<f32>,
+    /// Keep the point under the cursor fixed while zooming
+    pub zoom_to_cursor: bool,
+    /// Tilemap entity whose bounds the camera view is clamped to
+    pub bounds: Option<Entity>,
+}
+
+impl Default for TileCamera {
+    fn default() -> Self {
+        Self {
+            pan_speed: 1000.0,
+            zoom_speed: 2.0,
+            min_scale: 0.1,
+            max_scale: 10.0,
+            edge_scroll_margin: None,
+            zoom_to_cursor: true,
+            bounds: None,
+        }
+    }
+}
+
+/// Input driving all [`TileCamera`]s. Reset every frame after being applied.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TileCameraInput {
+    /// Pan direction. Each axis is expected to be in the range -1.0 to 1.0.
+    pub pan: Vec2,
+    /// Zoom amount. Positive values zoom in, and negative values zoom out.
+    pub zoom: f32,
+}
+
+/// Key bindings used to populate [`TileCameraInput`] when default input is enabled
+#[derive(Resource, Clone, Debug)]
+pub struct TileCameraKeyBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub zoom_in: KeyCode,
+    pub zoom_out: KeyCode,
+    /// Zoom using the mouse wheel
+    pub mouse_wheel_zoom: bool,
+}
+
+impl Default for TileCameraKeyBindings {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::KeyW,
+            down: KeyCode::KeyS,
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            zoom_in: KeyCode::KeyX,
+            zoom_out: KeyCode::KeyZ,
+            mouse_wheel_zoom: true,
+        }
+    }
+}
+
+impl Plugin for TileCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileCameraInput>()
+            .init_resource::<TileCameraKeyBindings>()
+            .configure_sets(Update, TileCameraSystem::Input.before(TileCameraSystem::Control))
+            .add_systems(Update, tile_camera_control_system.in_set(TileCameraSystem::Control));
+
+        if self.default_input {
+            app.add_systems(Update, tile_camera_input_system.in_set(TileCameraSystem::Input));
+        }
+    }
+}
+
+/// Populate camera input from keyboard and mouse
+fn tile_camera_input_system(
+    mut input: ResMut<TileCameraInput>,
+    key_bindings: Res<TileCameraKeyBindings>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+) {
+    let axis = |negative: KeyCode, positive: KeyCode| -> f32 {
+        keyboard_input.pressed(positive) as i32 as f32 - keyboard_input.pressed(negative) as i32 as f32
+    };
+
+    input.pan += Vec2::new(
+        axis(key_bindings.left, key_bindings.right),
+        axis(key_bindings.down, key_bindings.up),
+    );
+
+    input.zoom += axis(key_bindings.zoom_out, key_bindings.zoom_in);
+
+    for event in mouse_wheel_events.read() {
+        if !key_bindings.mouse_wheel_zoom {
+            continue;
+        }
+
+        // Normalize so that one notch of a typical mouse wheel zooms about as much as
+        // holding the zoom key for a short while.
+        input.zoom += match event.unit {
+            MouseScrollUnit::Line => event.y * 10.0,
+            MouseScrollUnit::Pixel => event.y * 0.5,
+        };
+    }
+}
+
+/// Move and zoom cameras according to input, and clamp them to the tilemap bounds
+fn tile_camera_control_system(
+    mut input: ResMut<TileCameraInput>,
+    time: Res<Time>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&TileCamera, &mut Transform)>,
+    tilemap_query: Query<(&TileMap, &GlobalTransform)>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let window_size = window.size();
+    let cursor = window.cursor_position();
+    let delta = time.delta_secs();
+
+    for (tile_camera, mut tf) in camera_query.iter_mut() {
+        let mut pan = input.pan.clamp(Vec2::NEG_ONE, Vec2::ONE);
+
+        // Edge scrolling
+        if let (Some(margin), Some(cursor)) = (tile_camera.edge_scroll_margin, cursor) {
+            if cursor.x < margin {
+                pan.x = -1.0;
+            } else if cursor.x > window_size.x - margin {
+                pan.x = 1.0;
+            }
+
+            // Window coordinates have Y pointing down
+            if cursor.y < margin {
+                pan.y = 1.0;
+            } else if cursor.y > window_size.y - margin {
+                pan.y = -1.0;
+            }
+        }
+
+        let scale = tf.scale.x;
+        tf.translation += (pan * tile_camera.pan_speed * scale * delta).extend(0.0);
+
+        // Zoom
+        if input.zoom != 0.0 {
+            let new_scale = (scale * (1.0 - input.zoom * tile_camera.zoom_speed * delta).max(0.01))
+                .clamp(tile_camera.min_scale, tile_camera.max_scale);
+
+            if tile_camera.zoom_to_cursor {
+                if let Some(cursor) = cursor {
+                    // Offset of the cursor from the center of the view, in logical pixels
+                    let cursor_offset = (cursor - window_size / 2.0) * Vec2::new(1.0, -1.0);
+
+                    // Keep the world position under the cursor the same before and after zooming
+                    tf.translation += (cursor_offset * (scale - new_scale)).extend(0.0);
+                }
+            }
+
+            tf.scale = Vec3::new(new_scale, new_scale, tf.scale.z);
+        }
+
+        // Clamp to tilemap bounds
+        let Some(bounds_entity) = tile_camera.bounds else {
+            continue;
+        };
+
+        let Ok((tilemap, tilemap_transform)) = tilemap_query.get(bounds_entity) else {
+            continue;
+        };
+
+        let (Some(bounds), Some(tile_size)) = (tilemap.bounds(), tilemap.tile_size(&texture_atlases)) else {
+            continue;
+        };
+
+        let grid_matrix = tilemap.projection.grid_matrix(tile_size.as_vec2());
+
+        // Tiles are centered on their position, so extend the bounds by half a tile in each direction
+        let min = bounds.min.as_vec2() - 0.5;
+        let max = bounds.max.as_vec2() + 0.5;
+
+        let corners = [
+            Vec2::new(min.x, min.y),
+            Vec2::new(max.x, min.y),
+            Vec2::new(max.x, max.y),
+            Vec2::new(min.x, max.y),
+        ]
+        .map(|corner| {
+            tilemap_transform
+                .transform_point((grid_matrix * corner).extend(0.0))
+                .truncate()
+        });
+
+        let world_min = corners.iter().copied().reduce(Vec2::min).unwrap();
+        let world_max = corners.iter().copied().reduce(Vec2::max).unwrap();
+
+        let half_view = window_size * tf.scale.truncate() / 2.0;
+
+        for axis in 0..2 {
+            let (lo, hi) = (world_min[axis] + half_view[axis], world_max[axis] - half_view[axis]);
+
+            tf.translation[axis] = if lo > hi {
+                // View is larger than the map, so keep it centered
+                (world_min[axis] + world_max[axis]) / 2.0
+            } else {
+                tf.translation[axis].clamp(lo, hi)
+            };
+        }
+    }
+
+    *input = TileCameraInput::default();
+}
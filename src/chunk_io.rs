@@ -0,0 +1,495 @@
+use std::{fmt, io};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    tilemap::{calc_chunk_origin, calc_chunk_pos, row_major_pos, CHUNK_HEIGHT, CHUNK_WIDTH, TILES_PER_CHUNK},
+    Chunk, Tile, TileFlags, TileMap,
+};
+
+/// Identifies serialized chunk data
+const MAGIC: [u8; 4] = *b"BSTC";
+
+/// Identifies serialized tilemap data
+const TILEMAP_MAGIC: [u8; 4] = *b"BSTM";
+
+/// Current version of the tilemap format
+const TILEMAP_VERSION: u8 = 1;
+
+/// Current version of the chunk format. Increment when the format changes, and keep decoding older versions.
+const VERSION: u8 = 2;
+
+/// Chunk size of data written by version 1, which did not store it
+const V1_CHUNK_SIZE: (u16, u16) = (64, 64);
+
+/// Tile has a custom size
+const TILE_HAS_CUSTOM_SIZE: u8 = 1 << 0;
+
+/// Error decoding a chunk from bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkDecodeError {
+    /// The data does not start with the chunk or tilemap magic bytes
+    InvalidMagic,
+    /// The data was written by a newer, unsupported version of the format
+    UnsupportedVersion(u8),
+    /// The data ended unexpectedly
+    UnexpectedEnd,
+    /// The data was written with a different chunk size than this build uses
+    ChunkSizeMismatch(u16, u16),
+    /// A tile index was outside the chunk
+    InvalidTileIndex(usize),
+    /// A tile referred to a tile outside the palette
+    InvalidPaletteIndex(u32),
+    /// A chunk origin was not the bottom left corner of a chunk
+    MisalignedOrigin(IVec3),
+    /// A tilemap contained more than one chunk at the same chunk position
+    DuplicateChunk(IVec3),
+}
+
+impl fmt::Display for ChunkDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMagic => write!(f, "not chunk or tilemap data"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported chunk format version {version}"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of chunk data"),
+            Self::ChunkSizeMismatch(width, height) => write!(
+                f,
+                "chunk data has a chunk size of {width}x{height}, but {CHUNK_WIDTH}x{CHUNK_HEIGHT} is used"
+            ),
+            Self::InvalidTileIndex(index) => write!(f, "invalid tile index {index}"),
+            Self::InvalidPaletteIndex(index) => write!(f, "invalid palette index {index}"),
+            Self::MisalignedOrigin(origin) => write!(f, "chunk origin {origin} is not aligned to the chunk size"),
+            Self::DuplicateChunk(chunk_pos) => write!(f, "duplicate chunk at chunk position {chunk_pos}"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkDecodeError {}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ChunkDecodeError> {
+        let (head, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(ChunkDecodeError::UnexpectedEnd)?;
+
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> Result<u8, ChunkDecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ChunkDecodeError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, ChunkDecodeError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, ChunkDecodeError> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, ChunkDecodeError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+}
+
+/// Write a tile in the format shared by chunks and tilemaps
+fn write_tile(bytes: &mut Vec<u8>, tile: &Tile) {
+    bytes.extend_from_slice(&tile.sprite_index.to_le_bytes());
+
+    for component in tile.color.to_srgba().to_f32_array() {
+        bytes.extend_from_slice(&component.to_le_bytes());
+    }
+
+    bytes.extend_from_slice(&tile.flags.bits().to_le_bytes());
+
+    match tile.custom_size {
+        Some(size) => {
+            bytes.push(TILE_HAS_CUSTOM_SIZE);
+            bytes.extend_from_slice(&size.x.to_le_bytes());
+            bytes.extend_from_slice(&size.y.to_le_bytes());
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn read_tile(reader: &mut Reader<'_>) -> Result<Tile, ChunkDecodeError> {
+    let sprite_index = reader.u32()?;
+    let color = Color::srgba(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+    let flags = TileFlags::from_bits_retain(reader.u32()?);
+
+    let custom_size = if reader.u8()? & TILE_HAS_CUSTOM_SIZE != 0 {
+        Some(Vec2::new(reader.f32()?, reader.f32()?))
+    } else {
+        None
+    };
+
+    Ok(Tile {
+        sprite_index,
+        color,
+        flags,
+        custom_size,
+    })
+}
+
+impl Chunk {
+    /// Serialize the chunk to a compact, versioned binary format.
+    ///
+    /// Only occupied tiles are stored, so sparse chunks are small.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tiles: Vec<(usize, &Tile)> = self
+            .occupied_indices()
+            .filter_map(|i| Some((i, self.tile_at_index(i)?)))
+            .collect();
+
+        let mut bytes = Vec::with_capacity(25 + tiles.len() * 27);
+
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(CHUNK_WIDTH as u16).to_le_bytes());
+        bytes.extend_from_slice(&(CHUNK_HEIGHT as u16).to_le_bytes());
+
+        for value in self.origin.to_array() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(tiles.len() as u32).to_le_bytes());
+
+        for (index, tile) in tiles {
+            bytes.extend_from_slice(&(index as u16).to_le_bytes());
+            write_tile(&mut bytes, tile);
+        }
+
+        bytes
+    }
+
+    /// Deserialize a chunk previously serialized with [`Chunk::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChunkDecodeError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take::<4>()? != MAGIC {
+            return Err(ChunkDecodeError::InvalidMagic);
+        }
+
+        let version = reader.u8()?;
+        if version > VERSION {
+            return Err(ChunkDecodeError::UnsupportedVersion(version));
+        }
+
+        let (width, height) = if version >= 2 {
+            (reader.u16()?, reader.u16()?)
+        } else {
+            V1_CHUNK_SIZE
+        };
+
+        if (width as u32, height as u32) != (CHUNK_WIDTH, CHUNK_HEIGHT) {
+            return Err(ChunkDecodeError::ChunkSizeMismatch(width, height));
+        }
+
+        let origin = read_origin(&mut reader)?;
+        let tile_count = reader.u32()?;
+
+        let mut chunk = Chunk::new(origin);
+        let mut tiles = Vec::with_capacity((tile_count as usize).min(TILES_PER_CHUNK));
+
+        for _ in 0..tile_count {
+            let index = reader.u16()? as usize;
+            if index >= TILES_PER_CHUNK {
+                return Err(ChunkDecodeError::InvalidTileIndex(index));
+            }
+
+            let tile = read_tile(&mut reader)?;
+            tiles.push((origin + row_major_pos(index).extend(0), Some(tile)));
+        }
+
+        chunk.set_tiles(tiles);
+
+        Ok(chunk)
+    }
+}
+
+impl TileMap {
+    /// Serialize the chunk at the specified chunk position with [`Chunk::to_bytes`], if it exists
+    pub fn save_chunk(&self, chunk_pos: IVec3) -> Option<Vec<u8>> {
+        Some(self.chunks.get(&chunk_pos)?.to_bytes())
+    }
+
+    /// Deserialize a chunk and queue it for insertion with [`TileMap::insert_chunk`],
+    /// returning the chunk position it will be inserted at
+    pub fn load_chunk(&mut self, bytes: &[u8]) -> Result<IVec3, ChunkDecodeError> {
+        let chunk = Chunk::from_bytes(bytes)?;
+        let chunk_pos = calc_chunk_pos(chunk.origin);
+
+        self.insert_chunk(chunk);
+
+        Ok(chunk_pos)
+    }
+
+    /// Serialize the tiles of the tilemap to a compact, versioned binary format,
+    /// suited to saving large worlds frequently.
+    ///
+    /// Each distinct tile is stored once in a palette, and chunks store runs of palette indices,
+    /// skipping over empty tiles. Settings, layers, animations and handles are not stored,
+    /// and pending tile changes are not included, so changes made since the tilemap was last updated are lost.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_bytes(&mut bytes).expect("writing to a Vec should not fail");
+        bytes
+    }
+
+    /// Serialize the tiles of the tilemap to a writer, in the same format as [`TileMap::to_bytes`].
+    ///
+    /// Chunks are written one at a time, so the whole output is never held in memory.
+    pub fn write_bytes(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let mut chunks: Vec<&Chunk> = self.chunks.values().filter(|chunk| !chunk.is_empty()).collect();
+        chunks.sort_unstable_by_key(|chunk| (chunk.origin.z, chunk.origin.y, chunk.origin.x));
+
+        let encode_tile = |tile: &Tile| {
+            let mut tile_bytes = Vec::new();
+            write_tile(&mut tile_bytes, tile);
+            tile_bytes
+        };
+
+        // Build the palette of distinct tiles up front, as it is written before the chunks
+        let mut palette: Vec<u8> = Vec::new();
+        let mut palette_indices: HashMap<Vec<u8>, u32> = HashMap::default();
+
+        for chunk in chunks.iter() {
+            for index in chunk.occupied_indices() {
+                let Some(tile) = chunk.tile_at_index(index) else {
+                    continue;
+                };
+
+                let tile_bytes = encode_tile(tile);
+                if !palette_indices.contains_key(&tile_bytes) {
+                    let palette_index = palette_indices.len() as u32;
+                    palette.extend_from_slice(&tile_bytes);
+                    palette_indices.insert(tile_bytes, palette_index);
+                }
+            }
+        }
+
+        let index_size = palette_index_size(palette_indices.len());
+
+        let mut bytes = Vec::with_capacity(17 + palette.len());
+
+        bytes.extend_from_slice(&TILEMAP_MAGIC);
+        bytes.push(TILEMAP_VERSION);
+        bytes.extend_from_slice(&(CHUNK_WIDTH as u16).to_le_bytes());
+        bytes.extend_from_slice(&(CHUNK_HEIGHT as u16).to_le_bytes());
+
+        bytes.extend_from_slice(&(palette_indices.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&palette);
+
+        bytes.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+
+        writer.write_all(&bytes)?;
+
+        for chunk in chunks {
+            // Runs of consecutive occupied tiles, as the number of empty tiles before the run
+            // and the palette indices of its tiles
+            let mut runs: Vec<(u16, Vec<u32>)> = Vec::new();
+            let mut next_index = 0;
+
+            for index in chunk.occupied_indices() {
+                let Some(tile) = chunk.tile_at_index(index) else {
+                    continue;
+                };
+
+                let palette_index = palette_indices[&encode_tile(tile)];
+
+                match runs.last_mut() {
+                    Some((_, run)) if index == next_index => run.push(palette_index),
+                    _ => runs.push(((index - next_index) as u16, vec![palette_index])),
+                }
+
+                next_index = index + 1;
+            }
+
+            bytes.clear();
+
+            for value in chunk.origin.to_array() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+
+            bytes.extend_from_slice(&(runs.len() as u16).to_le_bytes());
+
+            for (skip, run) in runs {
+                bytes.extend_from_slice(&skip.to_le_bytes());
+                bytes.extend_from_slice(&(run.len() as u16).to_le_bytes());
+
+                for palette_index in run {
+                    bytes.extend_from_slice(&palette_index.to_le_bytes()[..index_size]);
+                }
+            }
+
+            writer.write_all(&bytes)?;
+        }
+
+        Ok(())
+    }
+
+    /// Deserialize the tiles of a tilemap previously serialized with [`TileMap::to_bytes`].
+    ///
+    /// As handles are not stored, the tilemap's image and texture atlas layout must be set afterwards.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChunkDecodeError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take::<4>()? != TILEMAP_MAGIC {
+            return Err(ChunkDecodeError::InvalidMagic);
+        }
+
+        let version = reader.u8()?;
+        if version > TILEMAP_VERSION {
+            return Err(ChunkDecodeError::UnsupportedVersion(version));
+        }
+
+        let (width, height) = (reader.u16()?, reader.u16()?);
+        if (width as u32, height as u32) != (CHUNK_WIDTH, CHUNK_HEIGHT) {
+            return Err(ChunkDecodeError::ChunkSizeMismatch(width, height));
+        }
+
+        let palette_len = reader.u32()?;
+        let palette = (0..palette_len)
+            .map(|_| read_tile(&mut reader))
+            .collect::<Result<Vec<Tile>, _>>()?;
+
+        let index_size = palette_index_size(palette.len());
+
+        let mut tilemap = TileMap::new(Handle::default(), Handle::default());
+
+        let chunk_count = reader.u32()?;
+
+        for _ in 0..chunk_count {
+            let origin = read_origin(&mut reader)?;
+            let chunk_pos = calc_chunk_pos(origin);
+
+            if tilemap.chunks.contains_key(&chunk_pos) {
+                return Err(ChunkDecodeError::DuplicateChunk(chunk_pos));
+            }
+
+            let run_count = reader.u16()?;
+
+            let mut tiles = Vec::new();
+            let mut index = 0;
+
+            for _ in 0..run_count {
+                index += reader.u16()? as usize;
+                let run_len = reader.u16()?;
+
+                for _ in 0..run_len {
+                    if index >= TILES_PER_CHUNK {
+                        return Err(ChunkDecodeError::InvalidTileIndex(index));
+                    }
+
+                    let mut palette_index = [0; 4];
+                    for byte in palette_index.iter_mut().take(index_size) {
+                        *byte = reader.u8()?;
+                    }
+
+                    let palette_index = u32::from_le_bytes(palette_index);
+                    let tile = palette
+                        .get(palette_index as usize)
+                        .ok_or(ChunkDecodeError::InvalidPaletteIndex(palette_index))?;
+
+                    tiles.push((origin + row_major_pos(index).extend(0), Some(tile.clone())));
+                    index += 1;
+                }
+            }
+
+            let mut chunk = Chunk::new(origin);
+            chunk.set_tiles(tiles);
+
+            tilemap.chunks.insert(chunk_pos, chunk);
+        }
+
+        Ok(tilemap)
+    }
+}
+
+/// Read a chunk origin, which must be the bottom left corner of a chunk so that its tiles lie within the chunk
+fn read_origin(reader: &mut Reader<'_>) -> Result<IVec3, ChunkDecodeError> {
+    let origin = IVec3::new(reader.i32()?, reader.i32()?, reader.i32()?);
+
+    if origin != calc_chunk_origin(calc_chunk_pos(origin)) {
+        return Err(ChunkDecodeError::MisalignedOrigin(origin));
+    }
+
+    Ok(origin)
+}
+
+/// Number of bytes used to store palette indices, for a palette of the specified size
+fn palette_index_size(palette_len: usize) -> usize {
+    match palette_len {
+        0..=0x100 => 1,
+        0x101..=0x1_0000 => 2,
+        _ => 4,
+    }
+}
+
+/// Error reading a compressed tilemap
+#[cfg(feature = "zstd")]
+#[derive(Debug)]
+pub enum CompressedTileMapError {
+    Io(io::Error),
+    Decode(ChunkDecodeError),
+}
+
+#[cfg(feature = "zstd")]
+impl fmt::Display for CompressedTileMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not decompress tilemap: {err}"),
+            Self::Decode(err) => write!(f, "could not decode tilemap: {err}"),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl std::error::Error for CompressedTileMapError {}
+
+#[cfg(feature = "zstd")]
+impl From<io::Error> for CompressedTileMapError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl From<ChunkDecodeError> for CompressedTileMapError {
+    fn from(err: ChunkDecodeError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl TileMap {
+    /// Serialize the tiles of the tilemap as with [`TileMap::to_bytes`], compressed with zstd at the specified level
+    /// (1 to 22, or 0 for the default). The output is compressed as it is written.
+    pub fn write_compressed(&self, writer: impl io::Write, level: i32) -> io::Result<()> {
+        let mut encoder = zstd::Encoder::new(writer, level)?;
+        self.write_bytes(&mut encoder)?;
+        encoder.finish()?;
+
+        Ok(())
+    }
+
+    /// Deserialize a tilemap previously serialized with [`TileMap::write_compressed`].
+    ///
+    /// As handles are not stored, the tilemap's image and texture atlas layout must be set afterwards.
+    pub fn read_compressed(reader: impl io::Read) -> Result<Self, CompressedTileMapError> {
+        let mut bytes = Vec::new();
+        io::Read::read_to_end(&mut zstd::Decoder::new(reader)?, &mut bytes)?;
+
+        Ok(Self::from_bytes(&bytes)?)
+    }
+}
@@ -0,0 +1,300 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    collision::{TileCollision, TileCollisionMap, TileCollisionShape, TileCollisionShapes},
+    dirty::DirtyChunks,
+    tilemap::{calc_chunk_origin, calc_chunk_pos, row_major_index, CHUNK_HEIGHT, CHUNK_WIDTH, TILES_PER_CHUNK},
+    TileMap,
+};
+
+/// Generates physics colliders from the solid tiles of the [`TileCollisionMap`] on the same entity.
+///
+/// Adjacent solid tiles are merged into rectangles, each spawned as a child entity of the tilemap
+/// with a [`TileCollider`], as one collider per tile would slow down physics on large tilemaps.
+/// With the `rapier` feature, these also get a `bevy_rapier2d` `Collider`, and with the `avian` feature
+/// an `avian2d` static `Collider`, so physics stays in sync with the tilemap.
+/// One-way tiles are not solid from all sides, and don't get colliders.
+/// Tiles with [`TileCollisionShapes`] get a collider for each of their shapes instead.
+///
+/// Colliders are regenerated for chunks changed in [`DirtyChunks`], so set collision data together
+/// with the tiles it belongs to. When only collision data changes, use [`TileMapColliders::mark_dirty`].
+/// Only orthogonal tilemaps are supported.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TileMapColliders {
+    /// Layers to generate colliders for, or `None` for all layers
+    pub layers: Option<Vec<i32>>,
+    /// Collider entities of each chunk, by chunk position
+    chunk_colliders: HashMap<IVec3, Vec<Entity>>,
+    /// Chunks to regenerate colliders for, in addition to the dirty chunks of the tilemap
+    dirty: HashSet<IVec3>,
+    /// Whether colliders have been generated for all chunks, which happens once the tile size is known
+    initialized: bool,
+}
+
+impl TileMapColliders {
+    /// Generate colliders for the specified layers only
+    pub fn with_layers(layers: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            layers: Some(layers.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    /// Regenerate colliders for the chunk containing the tile at the specified position
+    pub fn mark_dirty(&mut self, pos: IVec3) {
+        self.dirty.insert(calc_chunk_pos(pos));
+    }
+
+    /// Regenerate colliders for all chunks
+    pub fn refresh(&mut self) {
+        self.initialized = false;
+    }
+}
+
+/// Rectangular or polygon collider, generated from solid tiles by [`TileMapColliders`].
+/// Positioned at the center of the rectangle, or of the tile for polygons, relative to the tilemap.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Debug)]
+#[require(Transform, Visibility)]
+pub struct TileCollider {
+    /// Half the size of the rectangle, or of the tile for polygons, in the tilemap's local units
+    pub half_size: Vec2,
+    /// Rectangle of solid tiles covered, in tile coordinates (inclusive)
+    pub tiles: IRect,
+    /// Convex polygon of a tile's [`TileCollisionShape`], relative to the collider's position
+    /// in the tilemap's local units. The collider is a rectangle if this is `None`.
+    pub polygon: Option<Vec<Vec2>>,
+}
+
+pub(crate) fn update_colliders_system(
+    mut commands: Commands,
+    mut tilemap_query: Query<(
+        Entity,
+        &TileMap,
+        Ref<TileCollisionMap>,
+        Option<Ref<TileCollisionShapes>>,
+        &mut TileMapColliders,
+    )>,
+    dirty_chunks: Res<DirtyChunks>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, tilemap, collision_map, shapes, mut colliders) in tilemap_query.iter_mut() {
+        let Some(tile_size) = tilemap.tile_size(&texture_atlases) else {
+            continue;
+        };
+
+        let tile_size = tile_size.as_vec2();
+
+        // A replaced collision map or changed shapes may differ anywhere
+        if collision_map.is_added() || shapes.as_ref().is_some_and(|shapes| shapes.is_changed()) {
+            colliders.initialized = false;
+        }
+
+        let mut chunk_positions: HashSet<IVec3> = colliders.dirty.drain().collect();
+
+        if colliders.initialized {
+            chunk_positions.extend(
+                dirty_chunks
+                    .iter_tilemap(entity)
+                    .map(|dirty_chunk| dirty_chunk.chunk_pos),
+            );
+        } else {
+            colliders.initialized = true;
+
+            // Include chunks with previous colliders, so that ones no longer solid are removed
+            chunk_positions.extend(colliders.chunk_colliders.keys().copied());
+            chunk_positions.extend(collision_map.chunk_positions());
+        }
+
+        for chunk_pos in chunk_positions {
+            // Despawning recursively also removes the colliders from the tilemap's children.
+            // Colliders may have been despawned already by the user.
+            if let Some(previous) = colliders.chunk_colliders.remove(&chunk_pos) {
+                for collider in previous {
+                    if let Some(collider) = commands.get_entity(collider) {
+                        collider.despawn_recursive();
+                    }
+                }
+            }
+
+            if colliders
+                .layers
+                .as_ref()
+                .is_some_and(|layers| !layers.contains(&chunk_pos.z))
+            {
+                continue;
+            }
+
+            let z = tilemap.layer_z(chunk_pos.z);
+
+            // Tiles with shapes get colliders of their own, and the remaining solid tiles are merged
+            let mut shaped_tiles: Vec<(IVec3, Vec<TileCollisionShape>)> = Vec::new();
+
+            let rects = collider_rects(&collision_map, chunk_pos, |pos| {
+                match shapes.as_ref().and_then(|shapes| shapes.shapes_at(tilemap, pos)) {
+                    Some(tile_shapes) => {
+                        shaped_tiles.push((pos, tile_shapes));
+                        false
+                    }
+                    None => true,
+                }
+            });
+
+            let mut chunk_colliders: Vec<Entity> = rects
+                .into_iter()
+                .map(|rect| {
+                    let center = (rect.min + rect.max).as_vec2() / 2.0 * tile_size;
+                    let half_size = (rect.size() + 1).as_vec2() * tile_size / 2.0;
+
+                    let collider = TileCollider {
+                        half_size,
+                        tiles: rect,
+                        polygon: None,
+                    };
+
+                    spawn_collider(&mut commands, entity, collider, center.extend(z))
+                })
+                .collect();
+
+            for (pos, tile_shapes) in shaped_tiles {
+                let tile_center = pos.truncate().as_vec2();
+                let tiles = IRect::from_corners(pos.truncate(), pos.truncate());
+
+                for shape in tile_shapes {
+                    let (center, collider) = match shape {
+                        TileCollisionShape::Rect(rect) => (
+                            (tile_center + rect.center()) * tile_size,
+                            TileCollider {
+                                half_size: rect.half_size() * tile_size,
+                                tiles,
+                                polygon: None,
+                            },
+                        ),
+                        TileCollisionShape::Polygon(points) => (
+                            tile_center * tile_size,
+                            TileCollider {
+                                half_size: tile_size / 2.0,
+                                tiles,
+                                polygon: Some(points.iter().map(|point| *point * tile_size).collect()),
+                            },
+                        ),
+                    };
+
+                    chunk_colliders.push(spawn_collider(&mut commands, entity, collider, center.extend(z)));
+                }
+            }
+
+            if !chunk_colliders.is_empty() {
+                colliders.chunk_colliders.insert(chunk_pos, chunk_colliders);
+            }
+        }
+    }
+}
+
+/// Spawn a collider as a child entity of the tilemap,
+/// along with the colliders of the physics engines whose features are enabled
+fn spawn_collider(commands: &mut Commands, tilemap: Entity, collider: TileCollider, translation: Vec3) -> Entity {
+    let mut entity = commands.spawn(Transform::from_translation(translation));
+
+    #[cfg(feature = "rapier")]
+    {
+        use bevy_rapier2d::prelude::Collider;
+
+        let physics_collider = match &collider.polygon {
+            Some(points) => Collider::convex_hull(points),
+            None => Some(Collider::cuboid(collider.half_size.x, collider.half_size.y)),
+        };
+
+        if let Some(physics_collider) = physics_collider {
+            entity.insert(physics_collider);
+        }
+    }
+
+    #[cfg(feature = "avian")]
+    {
+        use avian2d::prelude::{Collider, RigidBody};
+
+        let physics_collider = match &collider.polygon {
+            Some(points) => Collider::convex_hull(points.clone()),
+            None => Some(Collider::rectangle(
+                collider.half_size.x * 2.0,
+                collider.half_size.y * 2.0,
+            )),
+        };
+
+        if let Some(physics_collider) = physics_collider {
+            entity.insert((RigidBody::Static, physics_collider));
+        }
+    }
+
+    entity.insert(collider).set_parent(tilemap).id()
+}
+
+/// Merge the solid tiles of a chunk that collide as a whole into rectangles, in tile coordinates (inclusive).
+///
+/// Starting from the bottom left solid tile not covered yet, each rectangle is extended right as far as possible,
+/// then up for as long as the whole row is solid and not covered yet.
+pub(crate) fn collider_rects(
+    collision_map: &TileCollisionMap,
+    chunk_pos: IVec3,
+    mut is_whole: impl FnMut(IVec3) -> bool,
+) -> Vec<IRect> {
+    let origin = calc_chunk_origin(chunk_pos);
+
+    let mut solid = vec![false; TILES_PER_CHUNK];
+    let mut any_solid = false;
+
+    for (pos, collision) in collision_map.iter_chunk(chunk_pos) {
+        if collision.contains(TileCollision::SOLID) && is_whole(pos) {
+            solid[row_major_index((pos - origin).truncate())] = true;
+            any_solid = true;
+        }
+    }
+
+    if !any_solid {
+        return Vec::new();
+    }
+
+    let width = CHUNK_WIDTH as i32;
+    let height = CHUNK_HEIGHT as i32;
+    let index = |x: i32, y: i32| row_major_index(IVec2::new(x, y));
+
+    let mut rects = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if !solid[index(x, y)] {
+                continue;
+            }
+
+            let mut max_x = x;
+            while max_x + 1 < width && solid[index(max_x + 1, y)] {
+                max_x += 1;
+            }
+
+            let mut max_y = y;
+            while max_y + 1 < height && (x..=max_x).all(|x| solid[index(x, max_y + 1)]) {
+                max_y += 1;
+            }
+
+            // Covered tiles are cleared, so that they are not part of another rectangle
+            for covered_y in y..=max_y {
+                for covered_x in x..=max_x {
+                    solid[index(covered_x, covered_y)] = false;
+                }
+            }
+
+            rects.push(IRect::new(
+                origin.x + x,
+                origin.y + y,
+                origin.x + max_x,
+                origin.y + max_y,
+            ));
+        }
+    }
+
+    rects
+}
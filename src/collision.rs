@@ -0,0 +1,369 @@
+use bevy::{prelude::*, utils::HashMap};
+use bitflags::bitflags;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{TileData, TileFlags, TileMap};
+
+bitflags! {
+    /// Gameplay collision flags for a tile, separate from the render-related [`TileFlags`](crate::TileFlags).
+    ///
+    /// Each side of a tile can be solid independently, which allows one-way platforms
+    /// that can be landed on from above but jumped through from below.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+    #[reflect(opaque, Debug, Default, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+    pub struct TileCollision: u8 {
+        const SOLID_TOP = 1 << 0;
+        const SOLID_BOTTOM = 1 << 1;
+        const SOLID_LEFT = 1 << 2;
+        const SOLID_RIGHT = 1 << 3;
+        /// Solid from all sides
+        const SOLID = Self::SOLID_TOP.bits() | Self::SOLID_BOTTOM.bits() | Self::SOLID_LEFT.bits() | Self::SOLID_RIGHT.bits();
+        /// Can only be landed on from above
+        const ONE_WAY = Self::SOLID_TOP.bits();
+        /// Movers that request it can drop through the tile, even though it is solid from above
+        const DROP_THROUGH = 1 << 4;
+    }
+}
+
+/// The direction a mover approaches a tile from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum CollisionDirection {
+    /// Moving down onto the top side of the tile
+    FromAbove,
+    /// Moving up into the bottom side of the tile
+    FromBelow,
+    /// Moving right into the left side of the tile
+    FromLeft,
+    /// Moving left into the right side of the tile
+    FromRight,
+}
+
+impl CollisionDirection {
+    /// The side of the tile that is hit when approaching from this direction
+    #[inline]
+    pub fn side(&self) -> TileCollision {
+        match self {
+            Self::FromAbove => TileCollision::SOLID_TOP,
+            Self::FromBelow => TileCollision::SOLID_BOTTOM,
+            Self::FromLeft => TileCollision::SOLID_LEFT,
+            Self::FromRight => TileCollision::SOLID_RIGHT,
+        }
+    }
+}
+
+/// Per-tile collision data, stored per layer alongside a [`TileMap`](crate::TileMap)
+pub type TileCollisionMap = TileData<TileCollision>;
+
+/// Collision shape of a tile that is not fully solid, such as a slope or half tile.
+///
+/// Shapes are in tile units relative to the center of the tile, where the tile covers -0.5 to 0.5 on each axis.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TileCollisionShape {
+    Rect(Rect),
+    /// Convex polygon
+    Polygon(Vec<Vec2>),
+}
+
+impl TileCollisionShape {
+    /// Shape covering the whole tile
+    pub const FULL: Self = Self::Rect(Rect {
+        min: Vec2::splat(-0.5),
+        max: Vec2::splat(0.5),
+    });
+
+    /// Mirror the shape horizontally and/or vertically, such as for flipped tiles
+    pub fn flipped(&self, flip_x: bool, flip_y: bool) -> Self {
+        let scale = Vec2::new(if flip_x { -1.0 } else { 1.0 }, if flip_y { -1.0 } else { 1.0 });
+
+        match self {
+            Self::Rect(rect) => Self::Rect(Rect::from_corners(rect.min * scale, rect.max * scale)),
+            Self::Polygon(points) => Self::Polygon(points.iter().map(|point| *point * scale).collect()),
+        }
+    }
+
+    /// Extent along an axis (0 for x, 1 for y) of the part of the shape between `min` and `max` on the other axis
+    fn extent_in_band(&self, axis: usize, min: f32, max: f32) -> Option<(f32, f32)> {
+        let other = 1 - axis;
+
+        match self {
+            Self::Rect(rect) => {
+                (rect.max[other] > min && rect.min[other] < max).then_some((rect.min[axis], rect.max[axis]))
+            }
+            Self::Polygon(points) => {
+                let mut extent: Option<(f32, f32)> = None;
+                let mut include = |value: f32| {
+                    extent = Some(extent.map_or((value, value), |(lo, hi)| (lo.min(value), hi.max(value))));
+                };
+
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+
+                    if (min..=max).contains(&a[other]) {
+                        include(a[axis]);
+                    }
+
+                    // Where the edge crosses the sides of the band
+                    for side in [min, max] {
+                        if (a[other] - side) * (b[other] - side) < 0.0 {
+                            let t = (side - a[other]) / (b[other] - a[other]);
+                            include(a[axis] + (b[axis] - a[axis]) * t);
+                        }
+                    }
+                }
+
+                extent
+            }
+        }
+    }
+}
+
+/// Collision shapes of tiles by sprite index, for tiles that are not fully solid, such as slopes and half tiles.
+///
+/// Add to a tilemap entity along with a [`TileCollisionMap`], which decides which tiles collide.
+/// Solid tiles whose sprite has shapes collide with their shapes rather than the whole tile,
+/// in [`TileCollisionMap::sweep_aabb_with_shapes`] and [`TileMapColliders`](crate::colliders::TileMapColliders).
+/// Shapes are flipped along with the tile.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TileCollisionShapes {
+    /// Shapes by sprite index
+    shapes: HashMap<u32, Vec<TileCollisionShape>>,
+    /// Shapes by sprite index of layers using their own tileset, by layer
+    layer_shapes: HashMap<i32, HashMap<u32, Vec<TileCollisionShape>>>,
+}
+
+impl TileCollisionShapes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the shapes of tiles with the specified sprite index
+    pub fn with(mut self, sprite_index: u32, shapes: impl IntoIterator<Item = TileCollisionShape>) -> Self {
+        self.insert(sprite_index, shapes);
+        self
+    }
+
+    /// Set the shapes of tiles with the specified sprite index, replacing any previous shapes
+    pub fn insert(&mut self, sprite_index: u32, shapes: impl IntoIterator<Item = TileCollisionShape>) {
+        self.shapes.insert(sprite_index, shapes.into_iter().collect());
+    }
+
+    /// Set the shapes of tiles with the specified sprite index on a layer that uses its own tileset.
+    /// Once a layer has shapes of its own, shapes set with [`insert`](Self::insert) no longer apply to it.
+    pub fn insert_layer(
+        &mut self,
+        layer: i32,
+        sprite_index: u32,
+        shapes: impl IntoIterator<Item = TileCollisionShape>,
+    ) {
+        self.layer_shapes
+            .entry(layer)
+            .or_default()
+            .insert(sprite_index, shapes.into_iter().collect());
+    }
+
+    /// Get the shapes of tiles with the specified sprite index on a layer
+    pub fn get(&self, layer: i32, sprite_index: u32) -> Option<&[TileCollisionShape]> {
+        let shapes = self.layer_shapes.get(&layer).unwrap_or(&self.shapes);
+
+        shapes.get(&sprite_index).map(Vec::as_slice)
+    }
+
+    /// Get the shapes of the tile at the specified position of a tilemap, flipped along with the tile.
+    /// Returns `None` if the tile collides as a whole, because its sprite has no shapes or its shape covers the tile.
+    pub fn shapes_at(&self, tilemap: &TileMap, pos: IVec3) -> Option<Vec<TileCollisionShape>> {
+        let tile = tilemap.get_tile(pos)?;
+        let shapes = self.get(pos.z, tile.sprite_index)?;
+
+        if shapes == [TileCollisionShape::FULL] {
+            return None;
+        }
+
+        let flip_x = tile.flags.contains(TileFlags::FLIP_X);
+        let flip_y = tile.flags.contains(TileFlags::FLIP_Y);
+
+        Some(shapes.iter().map(|shape| shape.flipped(flip_x, flip_y)).collect())
+    }
+}
+
+/// Tolerance for rectangles touching the edge of a tile, so that resting against a tile counts as a collision
+const SWEEP_EPSILON: f32 = 1e-4;
+
+/// Result of sweeping a rectangle with [`TileCollisionMap::sweep_aabb`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionSweep {
+    /// Movement the rectangle can make before colliding
+    pub delta: Vec2,
+    /// Tile hit while moving horizontally, if any
+    pub hit_x: Option<IVec3>,
+    /// Tile hit while moving vertically, if any
+    pub hit_y: Option<IVec3>,
+}
+
+impl TileData<TileCollision> {
+    /// Get the collision flags of the tile at the specified position
+    pub fn collision(&self, pos: IVec3) -> TileCollision {
+        self.get(pos).copied().unwrap_or_default()
+    }
+
+    /// Returns true if a mover approaching the tile from the specified direction collides with it
+    pub fn collision_at(&self, pos: IVec3, from_direction: CollisionDirection) -> bool {
+        self.collision(pos).intersects(from_direction.side())
+    }
+
+    /// Like [`collision_at`](Self::collision_at), but movers that are dropping down pass through
+    /// tiles flagged with [`TileCollision::DROP_THROUGH`].
+    pub fn collision_at_dropping(&self, pos: IVec3, from_direction: CollisionDirection, dropping: bool) -> bool {
+        let collision = self.collision(pos);
+
+        if dropping
+            && from_direction == CollisionDirection::FromAbove
+            && collision.contains(TileCollision::DROP_THROUGH)
+        {
+            return false;
+        }
+
+        collision.intersects(from_direction.side())
+    }
+
+    /// Returns true if the tile at the specified position is solid from all sides
+    pub fn is_solid(&self, pos: IVec3) -> bool {
+        self.collision(pos).contains(TileCollision::SOLID)
+    }
+
+    /// Move a rectangle by `delta` on a layer, stopping at the first tile it collides with on each axis,
+    /// such as for moving platformer characters without a physics engine.
+    ///
+    /// The rectangle and movement are in tile units, where the tile at (x, y) covers x - 0.5 to x + 0.5
+    /// and y - 0.5 to y + 0.5, matching how tiles are centered on their position. Horizontal movement
+    /// is resolved first, then vertical movement from the resulting position. One-way tiles only stop
+    /// rectangles that are above them before moving.
+    pub fn sweep_aabb(&self, rect: Rect, delta: Vec2, layer: i32) -> CollisionSweep {
+        self.sweep(rect, delta, layer, |_| None)
+    }
+
+    /// Move a rectangle like [`sweep_aabb`](Self::sweep_aabb), colliding with the [`TileCollisionShapes`]
+    /// of tiles rather than the whole tile, so that slopes and half tiles collide accurately
+    pub fn sweep_aabb_with_shapes(
+        &self,
+        rect: Rect,
+        delta: Vec2,
+        layer: i32,
+        tilemap: &TileMap,
+        shapes: &TileCollisionShapes,
+    ) -> CollisionSweep {
+        self.sweep(rect, delta, layer, |pos| shapes.shapes_at(tilemap, pos))
+    }
+
+    fn sweep(
+        &self,
+        rect: Rect,
+        delta: Vec2,
+        layer: i32,
+        shapes_at: impl Fn(IVec3) -> Option<Vec<TileCollisionShape>>,
+    ) -> CollisionSweep {
+        let (delta_x, hit_x) = self.sweep_axis(rect, delta.x, 0, layer, &shapes_at);
+
+        let rect = Rect::from_corners(rect.min + Vec2::new(delta_x, 0.0), rect.max + Vec2::new(delta_x, 0.0));
+        let (delta_y, hit_y) = self.sweep_axis(rect, delta.y, 1, layer, &shapes_at);
+
+        CollisionSweep {
+            delta: Vec2::new(delta_x, delta_y),
+            hit_x,
+            hit_y,
+        }
+    }
+
+    /// Move a rectangle along one axis (0 for x, 1 for y), returning the allowed movement and the tile hit, if any.
+    /// Tiles without shapes collide as a whole.
+    fn sweep_axis(
+        &self,
+        rect: Rect,
+        delta: f32,
+        axis: usize,
+        layer: i32,
+        shapes_at: &impl Fn(IVec3) -> Option<Vec<TileCollisionShape>>,
+    ) -> (f32, Option<IVec3>) {
+        if delta == 0.0 {
+            return (0.0, None);
+        }
+
+        let other = 1 - axis;
+        let sign = delta.signum();
+
+        // Span of the rectangle on the other axis, not counting tiles it only touches
+        let band_min = rect.min[other] + SWEEP_EPSILON;
+        let band_max = rect.max[other] - SWEEP_EPSILON;
+
+        let first = (band_min + 0.5).floor() as i32;
+        let last = (band_max + 0.5).floor() as i32;
+
+        let direction = match (axis, delta > 0.0) {
+            (0, true) => CollisionDirection::FromLeft,
+            (0, false) => CollisionDirection::FromRight,
+            (_, true) => CollisionDirection::FromBelow,
+            (_, false) => CollisionDirection::FromAbove,
+        };
+
+        let tile_pos = |along: i32, across: i32| match axis {
+            0 => IVec3::new(along, across, layer),
+            _ => IVec3::new(across, along, layer),
+        };
+
+        let edge = if delta > 0.0 { rect.max[axis] } else { rect.min[axis] };
+
+        // Start from the tile containing the leading edge of the rectangle, as shaped tiles may not fill it
+        let mut along = (edge + 0.5).floor() as i32;
+
+        // Until the near side of the tiles is beyond where the rectangle moves to
+        while (along as f32 - 0.5 * sign - edge) * sign < delta.abs() {
+            let mut nearest: Option<(f32, IVec3)> = None;
+
+            for across in first..=last {
+                let pos = tile_pos(along, across);
+
+                if !self.collision_at(pos, direction) {
+                    continue;
+                }
+
+                // Side of the tile or its shapes facing the rectangle, relative to the center of the tile
+                let face = match shapes_at(pos) {
+                    None => Some(-0.5 * sign),
+                    Some(shapes) => shapes
+                        .iter()
+                        .filter_map(|shape| {
+                            shape.extent_in_band(axis, band_min - across as f32, band_max - across as f32)
+                        })
+                        .map(|(min, max)| if delta > 0.0 { min } else { max })
+                        .min_by(|a, b| (a * sign).total_cmp(&(b * sign))),
+                };
+
+                let Some(face) = face else {
+                    continue;
+                };
+
+                let distance = (along as f32 + face - edge) * sign;
+
+                // Sides behind the leading edge are already overlapped, such as within the tile of a slope
+                if distance < -SWEEP_EPSILON || distance >= delta.abs() {
+                    continue;
+                }
+
+                if !nearest.is_some_and(|(nearest, _)| nearest <= distance) {
+                    nearest = Some((distance, pos));
+                }
+            }
+
+            if let Some((distance, pos)) = nearest {
+                return (distance.max(0.0) * sign, Some(pos));
+            }
+
+            along += sign as i32;
+        }
+
+        (delta, None)
+    }
+}
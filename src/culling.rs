@@ -0,0 +1,219 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{math::DVec3, prelude::*, render::primitives::Aabb};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    tilemap::{calc_chunk_origin, calc_chunk_pos},
+    TileMap,
+};
+
+/// Settings for prefetching chunks ahead of moving cameras.
+///
+/// Chunks that will become visible within the look-ahead time, given the current velocity of a camera,
+/// are extracted and meshed before they actually come into view. This avoids pop-in during fast scrolling
+/// without enlarging the culling area in all directions.
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource, Debug, Default)]
+pub struct ChunkPrefetchSettings {
+    /// How far ahead, in seconds, to prefetch chunks based on camera velocity. Zero disables prefetching.
+    pub look_ahead: f32,
+}
+
+impl Default for ChunkPrefetchSettings {
+    fn default() -> Self {
+        Self { look_ahead: 0.25 }
+    }
+}
+
+/// Whether the chunks of a tilemap are culled against the cameras
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum TileMapCulling {
+    /// Only chunks within view of a camera are drawn
+    #[default]
+    Enabled,
+    /// All chunks are drawn by every camera, and the tilemap is never frustum culled.
+    /// Use this for tilemaps rendered to offscreen targets or with unusual cameras that culling gets wrong.
+    Disabled,
+}
+
+/// Result of culling a chunk against the cameras
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkCullState {
+    /// The chunk is within view of a camera
+    Visible,
+    /// The chunk is not in view, but was extracted because a moving camera is about to reach it
+    Prefetched,
+    /// The chunk was skipped
+    Culled,
+}
+
+/// Cull rect of a chunk, and how it was culled when tilemaps were last extracted
+#[derive(Clone, Debug)]
+pub struct ChunkCullInfo {
+    /// Tilemap entity
+    pub tilemap: Entity,
+    /// Origin of the chunk, in tile coordinates
+    pub origin: IVec3,
+    /// Corners of the world-space area tested against the cameras, including parallax relative to the first camera
+    pub corners: [Vec2; 4],
+    pub state: ChunkCullState,
+}
+
+/// Shared between the main world and render world, for passing the [`ChunkCullInfo`] of every chunk considered
+/// during extraction back to the main world. Only inserted by [`TileMapDebugPlugin`](crate::debug::TileMapDebugPlugin),
+/// so culling is not recorded otherwise.
+#[derive(Resource, Clone, Default)]
+pub(crate) struct ChunkCullingDebugChannel(pub(crate) Arc<Mutex<Vec<ChunkCullInfo>>>);
+
+/// Update the world-space AABBs of tilemap chunks when the tilemap or its transform changes
+pub(crate) fn update_chunk_aabbs_system(
+    mut tilemap_query: Query<(&mut TileMap, &GlobalTransform), Or<(Changed<TileMap>, Changed<GlobalTransform>)>>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (mut tilemap, transform) in tilemap_query.iter_mut() {
+        let Some(tile_size) = tilemap.tile_size(&texture_atlases) else {
+            continue;
+        };
+
+        let tile_size = tile_size.as_vec2();
+
+        // Use double precision, to remain accurate far from the origin
+        let transform_matrix = transform.compute_matrix().as_dmat4();
+
+        let chunk_aabbs = tilemap
+            .chunks
+            .iter()
+            .map(|(chunk_pos, chunk)| {
+                let (min, max) = tilemap.projection.chunk_bounds(chunk.origin.truncate(), tile_size);
+                let z = tilemap.layer_z(chunk.origin.z) as f64;
+
+                let corners = [
+                    DVec3::new(min.x, min.y, z),
+                    DVec3::new(max.x, min.y, z),
+                    DVec3::new(max.x, max.y, z),
+                    DVec3::new(min.x, max.y, z),
+                ]
+                .map(|corner| transform_matrix.transform_point3(corner).as_vec3());
+
+                let world_min = corners.into_iter().reduce(Vec3::min).unwrap();
+                let world_max = corners.into_iter().reduce(Vec3::max).unwrap();
+
+                (*chunk_pos, Aabb::from_min_max(world_min, world_max))
+            })
+            .collect();
+
+        // Bypass change detection, as this is derived data and should not cause the tilemap to be considered changed
+        tilemap.bypass_change_detection().chunk_aabbs = chunk_aabbs;
+    }
+}
+
+/// Update the local-space [`Aabb`] of tilemaps, covering all of their chunks and layer fills.
+///
+/// This lets Bevy's visibility system frustum cull entire tilemaps, respecting `NoFrustumCulling` and
+/// visibility ranges. Tilemaps with parallax or offset layers have no AABB, as those offsets are in world space,
+/// and neither do tilemaps with [`TileMapCulling::Disabled`].
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_tilemap_aabbs_system(
+    mut commands: Commands,
+    tilemap_query: Query<(Entity, &TileMap, Option<&Aabb>), Or<(Changed<TileMap>, Without<Aabb>)>>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, tilemap, previous_aabb) in tilemap_query.iter() {
+        let has_layer_offsets = tilemap
+            .layers()
+            .any(|(_, settings)| settings.parallax != Vec2::ONE || settings.offset != Vec2::ZERO);
+        let is_culled = tilemap.culling == TileMapCulling::Enabled;
+
+        let aabb = tilemap
+            .tile_size(&texture_atlases)
+            .filter(|_| is_culled && !has_layer_offsets)
+            .and_then(|tile_size| {
+                let tile_size = tile_size.as_vec2();
+                let margin = tile_size * tilemap.cull_margin;
+
+                let chunk_origins = tilemap.chunks.values().map(|chunk| chunk.origin);
+                let fill_origins = tilemap.layers().filter_map(|(layer, settings)| {
+                    let (_, fill_bounds) = settings.fill()?;
+
+                    // Only the chunks at the corners of the fill are needed, as the bounds are a union
+                    let corners = [
+                        fill_bounds.min,
+                        ivec2(fill_bounds.max.x, fill_bounds.min.y),
+                        fill_bounds.max,
+                        ivec2(fill_bounds.min.x, fill_bounds.max.y),
+                    ];
+
+                    Some(corners.map(|pos| calc_chunk_origin(calc_chunk_pos(pos.extend(layer)))))
+                });
+
+                chunk_origins
+                    .chain(fill_origins.flatten())
+                    .map(|origin| {
+                        let (min, max) = tilemap.projection.chunk_bounds(origin.truncate(), tile_size);
+                        let z = tilemap.layer_z(origin.z);
+
+                        ((min.as_vec2() - margin).extend(z), (max.as_vec2() + margin).extend(z))
+                    })
+                    .reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max)))
+                    .map(|(min, max)| Aabb::from_min_max(min, max))
+            });
+
+        match aabb {
+            Some(aabb) => {
+                if previous_aabb != Some(&aabb) {
+                    commands.entity(entity).insert(aabb);
+                }
+            }
+            None => {
+                if previous_aabb.is_some() {
+                    commands.entity(entity).remove::<Aabb>();
+                }
+            }
+        }
+    }
+}
+
+/// Get the chunk positions (ignoring layer) containing each 2D camera, in the tilemap's coordinates.
+/// Returns `None` if the tilemap's tile size is not known yet.
+pub(crate) fn camera_chunk_positions(
+    tilemap: &TileMap,
+    transform: &GlobalTransform,
+    camera_query: &Query<&GlobalTransform, With<Camera2d>>,
+    texture_atlases: &Assets<TextureAtlasLayout>,
+) -> Option<Vec<IVec2>> {
+    world_chunk_positions(
+        tilemap,
+        transform,
+        camera_query.iter().map(GlobalTransform::translation),
+        texture_atlases,
+    )
+}
+
+/// Get the chunk positions (ignoring layer) containing each world position, in the tilemap's coordinates.
+/// Returns `None` if the tilemap's tile size is not known yet.
+pub(crate) fn world_chunk_positions(
+    tilemap: &TileMap,
+    transform: &GlobalTransform,
+    positions: impl IntoIterator<Item = Vec3>,
+    texture_atlases: &Assets<TextureAtlasLayout>,
+) -> Option<Vec<IVec2>> {
+    let tile_size = tilemap.tile_size(texture_atlases)?.as_vec2();
+    let inverse_transform = transform.affine().inverse();
+
+    Some(
+        positions
+            .into_iter()
+            .map(|position| {
+                let local_pos = inverse_transform.transform_point3(position);
+                let tile_pos = tilemap.projection.local_to_tile(local_pos.truncate(), tile_size);
+
+                calc_chunk_pos(tile_pos.extend(0)).truncate()
+            })
+            .collect(),
+    )
+}
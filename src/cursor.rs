@@ -0,0 +1,86 @@
+use bevy::{prelude::*, render::camera::NormalizedRenderTarget, utils::HashMap, window::PrimaryWindow};
+
+use crate::TileMap;
+
+/// Tracks the tile under the mouse cursor. Add to a tilemap entity, and it is updated every frame after
+/// [`TileMapSystem::UpdateChunks`](crate::plugin::TileMapSystem::UpdateChunks), reflecting that frame's tile changes.
+///
+/// The cursor is converted to a world position through each camera whose viewport contains it,
+/// taking the tilemap's transform and projection into account.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TileCursor {
+    /// Layer to get tile positions on
+    pub layer: i32,
+    /// Tile under the cursor, as seen by the highest order camera whose viewport contains the cursor
+    pub tile_pos: Option<IVec3>,
+    /// Tile under the cursor as seen by each camera whose viewport contains the cursor
+    pub camera_tile_positions: HashMap<Entity, IVec3>,
+}
+
+impl TileCursor {
+    /// Track the tile under the cursor on the specified layer
+    pub fn with_layer(layer: i32) -> Self {
+        Self {
+            layer,
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) fn update_tile_cursors_system(
+    mut tilemap_query: Query<(&TileMap, &GlobalTransform, &mut TileCursor)>,
+    camera_query: Query<(Entity, &Camera, &GlobalTransform)>,
+    window_query: Query<&Window>,
+    primary_window_query: Query<Entity, With<PrimaryWindow>>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    let primary_window = primary_window_query.get_single().ok();
+
+    // World position under the cursor for each camera whose viewport contains it, by camera order
+    let mut cursor_positions: Vec<(isize, Entity, Vec2)> = camera_query
+        .iter()
+        .filter(|(_, camera, _)| camera.is_active)
+        .filter_map(|(entity, camera, camera_transform)| {
+            let Some(NormalizedRenderTarget::Window(window_ref)) = camera.target.normalize(primary_window) else {
+                return None;
+            };
+
+            let cursor = window_query.get(window_ref.entity()).ok()?.cursor_position()?;
+
+            if !camera.logical_viewport_rect()?.contains(cursor) {
+                return None;
+            }
+
+            let world_pos = camera.viewport_to_world_2d(camera_transform, cursor).ok()?;
+
+            Some((camera.order, entity, world_pos))
+        })
+        .collect();
+
+    cursor_positions.sort_unstable_by_key(|(order, _, _)| *order);
+
+    for (tilemap, transform, mut cursor) in tilemap_query.iter_mut() {
+        let layer = cursor.layer;
+
+        let camera_tile_positions: HashMap<Entity, IVec3> = cursor_positions
+            .iter()
+            .filter_map(|(_, camera, world_pos)| {
+                Some((
+                    *camera,
+                    tilemap.world_to_tile(*world_pos, layer, transform, &texture_atlases)?,
+                ))
+            })
+            .collect();
+
+        let tile_pos = cursor_positions
+            .iter()
+            .rev()
+            .find_map(|(_, camera, _)| camera_tile_positions.get(camera).copied());
+
+        // Avoid triggering change detection when nothing changed
+        if cursor.tile_pos != tile_pos || cursor.camera_tile_positions != camera_tile_positions {
+            cursor.tile_pos = tile_pos;
+            cursor.camera_tile_positions = camera_tile_positions;
+        }
+    }
+}
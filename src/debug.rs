@@ -0,0 +1,262 @@
+use bevy::{prelude::*, render::RenderApp, utils::HashMap};
+
+use crate::{
+    culling::{ChunkCullState, ChunkCullingDebugChannel},
+    projection::{HexOrientation, TileMapProjection},
+    tilemap::CHUNK_SIZE,
+    TileMap,
+};
+
+/// Hexagonal grids with more cells than this within the tilemap's bounds don't have their grid drawn,
+/// as every cell is outlined individually
+const MAX_HEX_GRID_CELLS: i32 = 16384;
+
+/// Optional plugin drawing debug overlays for tilemaps with gizmos, such as grid lines and chunk borders.
+///
+/// What is drawn is configured by the [`TileMapDebugSettings`] resource.
+/// Requires [`SimpleTileMapPlugin`](crate::plugin::SimpleTileMapPlugin) and Bevy's gizmos.
+#[derive(Default)]
+pub struct TileMapDebugPlugin;
+
+impl Plugin for TileMapDebugPlugin {
+    fn build(&self, app: &mut App) {
+        let culling_debug_channel = ChunkCullingDebugChannel::default();
+
+        app.register_type::<TileMapDebugSettings>()
+            .init_resource::<TileMapDebugSettings>()
+            .insert_resource(culling_debug_channel.clone())
+            .add_systems(
+                PostUpdate,
+                (draw_tilemap_debug_system, draw_chunk_culling_system).after(TransformSystem::TransformPropagate),
+            );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(culling_debug_channel);
+        }
+    }
+}
+
+/// Settings for the overlays drawn by [`TileMapDebugPlugin`]
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource, Debug, Default)]
+pub struct TileMapDebugSettings {
+    /// Draw the outlines of the grid cells within the bounds of each tilemap
+    pub grid: bool,
+    pub grid_color: Color,
+    /// Draw the borders of each chunk
+    pub chunk_borders: bool,
+    pub chunk_border_color: Color,
+    /// Draw the bounds of the occupied tiles of each layer, at the layer's z
+    pub layer_bounds: bool,
+    pub layer_bounds_color: Color,
+    /// Draw the cull area of each chunk as of the last extraction, colored by whether the chunk was visible,
+    /// prefetched or culled
+    pub chunk_culling: bool,
+    pub visible_color: Color,
+    pub prefetched_color: Color,
+    pub culled_color: Color,
+}
+
+impl Default for TileMapDebugSettings {
+    fn default() -> Self {
+        Self {
+            grid: true,
+            grid_color: Color::srgba(1.0, 1.0, 1.0, 0.25),
+            chunk_borders: true,
+            chunk_border_color: Color::srgb(1.0, 0.8, 0.0),
+            layer_bounds: true,
+            layer_bounds_color: Color::srgb(0.0, 0.8, 1.0),
+            chunk_culling: false,
+            visible_color: Color::srgb(0.0, 1.0, 0.0),
+            prefetched_color: Color::srgb(1.0, 1.0, 0.0),
+            culled_color: Color::srgb(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+fn draw_tilemap_debug_system(
+    mut gizmos: Gizmos,
+    settings: Res<TileMapDebugSettings>,
+    tilemap_query: Query<(&TileMap, &GlobalTransform, &InheritedVisibility)>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (tilemap, transform, visibility) in tilemap_query.iter() {
+        if !visibility.get() {
+            continue;
+        }
+
+        let Some(tile_size) = tilemap.tile_size(&texture_atlases) else {
+            continue;
+        };
+
+        let tile_size = tile_size.as_vec2();
+        let projection = &tilemap.projection;
+        let grid_matrix = projection.grid_matrix(tile_size);
+
+        // Draw the outline of a rectangle of grid cells, in tile coordinates (inclusive)
+        let draw_cells = |gizmos: &mut Gizmos, rect: IRect, z: f32, color: Color| {
+            let min = rect.min.as_vec2() - 0.5;
+            let max = rect.max.as_vec2() + 0.5;
+
+            let corners = [
+                Vec2::new(min.x, min.y),
+                Vec2::new(max.x, min.y),
+                Vec2::new(max.x, max.y),
+                Vec2::new(min.x, max.y),
+                Vec2::new(min.x, min.y),
+            ]
+            .map(|corner| transform.transform_point((grid_matrix * corner).extend(z)));
+
+            gizmos.linestrip(corners, color);
+        };
+
+        if settings.grid {
+            if let Some(bounds) = tilemap.bounds() {
+                draw_grid(&mut gizmos, tilemap, transform, bounds, tile_size, settings.grid_color);
+            }
+        }
+
+        if settings.chunk_borders {
+            for chunk in tilemap.chunks.values() {
+                let rect = IRect::from_corners(
+                    chunk.origin.truncate(),
+                    chunk.origin.truncate() + CHUNK_SIZE.as_ivec2() - 1,
+                );
+
+                draw_cells(
+                    &mut gizmos,
+                    rect,
+                    tilemap.layer_z(chunk.origin.z),
+                    settings.chunk_border_color,
+                );
+            }
+        }
+
+        if settings.layer_bounds {
+            let mut layer_bounds: HashMap<i32, IRect> = HashMap::default();
+
+            for chunk in tilemap.chunks.values() {
+                let Some(bounds) = chunk.bounds() else {
+                    continue;
+                };
+
+                layer_bounds
+                    .entry(chunk.origin.z)
+                    .and_modify(|layer_bounds| *layer_bounds = layer_bounds.union(bounds))
+                    .or_insert(bounds);
+            }
+
+            for (layer, bounds) in layer_bounds {
+                draw_cells(&mut gizmos, bounds, tilemap.layer_z(layer), settings.layer_bounds_color);
+            }
+        }
+    }
+}
+
+/// Draw the chunk cull areas recorded during the last extraction.
+/// With pipelined rendering, these lag one frame behind.
+fn draw_chunk_culling_system(
+    mut gizmos: Gizmos,
+    settings: Res<TileMapDebugSettings>,
+    channel: Res<ChunkCullingDebugChannel>,
+) {
+    if !settings.chunk_culling {
+        return;
+    }
+
+    let Ok(cull_infos) = channel.0.lock() else {
+        return;
+    };
+
+    for cull_info in cull_infos.iter() {
+        let color = match cull_info.state {
+            ChunkCullState::Visible => settings.visible_color,
+            ChunkCullState::Prefetched => settings.prefetched_color,
+            ChunkCullState::Culled => settings.culled_color,
+        };
+
+        let corners = cull_info.corners;
+
+        gizmos.linestrip_2d(corners.into_iter().chain([corners[0]]), color);
+    }
+}
+
+/// Draw the grid lines of the cells within the bounds, in tile coordinates (inclusive)
+fn draw_grid(
+    gizmos: &mut Gizmos,
+    tilemap: &TileMap,
+    transform: &GlobalTransform,
+    bounds: IRect,
+    tile_size: Vec2,
+    color: Color,
+) {
+    let projection = &tilemap.projection;
+
+    if let TileMapProjection::Hexagonal {
+        tile_size: hex_size,
+        orientation,
+        ..
+    } = *projection
+    {
+        let size = bounds.size() + 1;
+        if size.x * size.y > MAX_HEX_GRID_CELLS {
+            return;
+        }
+
+        let half_size = hex_size / 2.0;
+        let corners = match orientation {
+            HexOrientation::PointyTop => [
+                vec2(half_size.x, half_size.y / 2.0),
+                vec2(0.0, half_size.y),
+                vec2(-half_size.x, half_size.y / 2.0),
+                vec2(-half_size.x, -half_size.y / 2.0),
+                vec2(0.0, -half_size.y),
+                vec2(half_size.x, -half_size.y / 2.0),
+            ],
+            HexOrientation::FlatTop => [
+                vec2(half_size.x, 0.0),
+                vec2(half_size.x / 2.0, half_size.y),
+                vec2(-half_size.x / 2.0, half_size.y),
+                vec2(-half_size.x, 0.0),
+                vec2(-half_size.x / 2.0, -half_size.y),
+                vec2(half_size.x / 2.0, -half_size.y),
+            ],
+        };
+
+        for y in bounds.min.y..=bounds.max.y {
+            for x in bounds.min.x..=bounds.max.x {
+                let center = projection.tile_to_local(ivec2(x, y), tile_size);
+                let points = (0..=corners.len())
+                    .map(|i| transform.transform_point((center + corners[i % corners.len()]).extend(0.0)));
+
+                gizmos.linestrip(points, color);
+            }
+        }
+
+        return;
+    }
+
+    let grid_matrix = projection.grid_matrix(tile_size);
+
+    // Tiles are centered on their position, so grid lines are half a tile from each position
+    let min = bounds.min.as_vec2() - 0.5;
+    let max = bounds.max.as_vec2() + 0.5;
+
+    let mut line = |start: Vec2, end: Vec2| {
+        gizmos.line(
+            transform.transform_point((grid_matrix * start).extend(0.0)),
+            transform.transform_point((grid_matrix * end).extend(0.0)),
+            color,
+        );
+    };
+
+    for x in bounds.min.x..=bounds.max.x + 1 {
+        let x = x as f32 - 0.5;
+        line(vec2(x, min.y), vec2(x, max.y));
+    }
+
+    for y in bounds.min.y..=bounds.max.y + 1 {
+        let y = y as f32 - 0.5;
+        line(vec2(min.x, y), vec2(max.x, y));
+    }
+}
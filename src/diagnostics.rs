@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use crate::TileMap;
+
+/// Memory usage and rendering work of the tilemaps in the most recently rendered frame.
+///
+/// This is updated in the main world at the start of every frame, and is always available.
+/// Add [`TilemapDiagnosticsPlugin`] to also report the values through Bevy's diagnostics,
+/// for example with [`LogDiagnosticsPlugin`](bevy::diagnostic::LogDiagnosticsPlugin).
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TilemapDiagnostics {
+    /// Chunks allocated in the main world, across all tilemaps
+    pub chunks: usize,
+    /// Chunks with instance data in the render world
+    pub meshed_chunks: usize,
+    /// Tile instances uploaded to the GPU
+    pub instances_uploaded: usize,
+    /// Size of the tilemap instance and uniform buffers on the GPU, in bytes
+    pub gpu_buffer_bytes: u64,
+    /// Size of the instance data kept on the CPU for batching chunks, in bytes
+    pub cpu_instance_bytes: usize,
+    /// Draw calls issued, across all cameras
+    pub draw_calls: usize,
+}
+
+/// Shared between the main world and render world, for passing [`TilemapDiagnostics`] back to the main world
+#[derive(Resource, Clone, Default)]
+pub(crate) struct TilemapDiagnosticsChannel(pub(crate) Arc<Mutex<TilemapDiagnostics>>);
+
+/// Copy the diagnostics written by the render world into the main world resource, and count allocated chunks
+pub(crate) fn receive_diagnostics_system(
+    channel: Res<TilemapDiagnosticsChannel>,
+    mut diagnostics: ResMut<TilemapDiagnostics>,
+    tilemap_query: Query<&TileMap>,
+) {
+    if let Ok(mut received) = channel.0.lock() {
+        *diagnostics = std::mem::take(&mut *received);
+    }
+
+    diagnostics.chunks = tilemap_query.iter().map(|tilemap| tilemap.chunks.len()).sum();
+}
+
+/// Optional plugin reporting [`TilemapDiagnostics`] through Bevy's diagnostics.
+///
+/// Requires [`SimpleTileMapPlugin`](crate::plugin::SimpleTileMapPlugin).
+#[derive(Default)]
+pub struct TilemapDiagnosticsPlugin;
+
+impl TilemapDiagnosticsPlugin {
+    pub const CHUNKS: DiagnosticPath = DiagnosticPath::const_new("tilemap/chunks");
+    pub const MESHED_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("tilemap/meshed_chunks");
+    pub const INSTANCES_UPLOADED: DiagnosticPath = DiagnosticPath::const_new("tilemap/instances_uploaded");
+    pub const GPU_BUFFER_BYTES: DiagnosticPath = DiagnosticPath::const_new("tilemap/gpu_buffer_bytes");
+    pub const CPU_INSTANCE_BYTES: DiagnosticPath = DiagnosticPath::const_new("tilemap/cpu_instance_bytes");
+    pub const DRAW_CALLS: DiagnosticPath = DiagnosticPath::const_new("tilemap/draw_calls");
+}
+
+impl Plugin for TilemapDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::CHUNKS))
+            .register_diagnostic(Diagnostic::new(Self::MESHED_CHUNKS))
+            .register_diagnostic(Diagnostic::new(Self::INSTANCES_UPLOADED))
+            .register_diagnostic(Diagnostic::new(Self::GPU_BUFFER_BYTES).with_suffix(" bytes"))
+            .register_diagnostic(Diagnostic::new(Self::CPU_INSTANCE_BYTES).with_suffix(" bytes"))
+            .register_diagnostic(Diagnostic::new(Self::DRAW_CALLS))
+            .add_systems(Update, report_diagnostics_system);
+    }
+}
+
+/// Add measurements of the current [`TilemapDiagnostics`] to Bevy's diagnostics
+fn report_diagnostics_system(mut diagnostics: Diagnostics, tilemap_diagnostics: Res<TilemapDiagnostics>) {
+    let measurements = [
+        (TilemapDiagnosticsPlugin::CHUNKS, tilemap_diagnostics.chunks as f64),
+        (
+            TilemapDiagnosticsPlugin::MESHED_CHUNKS,
+            tilemap_diagnostics.meshed_chunks as f64,
+        ),
+        (
+            TilemapDiagnosticsPlugin::INSTANCES_UPLOADED,
+            tilemap_diagnostics.instances_uploaded as f64,
+        ),
+        (
+            TilemapDiagnosticsPlugin::GPU_BUFFER_BYTES,
+            tilemap_diagnostics.gpu_buffer_bytes as f64,
+        ),
+        (
+            TilemapDiagnosticsPlugin::CPU_INSTANCE_BYTES,
+            tilemap_diagnostics.cpu_instance_bytes as f64,
+        ),
+        (
+            TilemapDiagnosticsPlugin::DRAW_CALLS,
+            tilemap_diagnostics.draw_calls as f64,
+        ),
+    ];
+
+    for (path, value) in measurements {
+        diagnostics.add_measurement(&path, || value);
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::{prelude::*, utils::HashSet};
+
+/// Why a chunk is dirty
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DirtyCause {
+    /// The chunk was created, because a tile was set within it or a whole chunk was inserted
+    Created,
+    /// Tiles in the chunk were changed
+    Modified,
+    /// All tiles in the chunk were cleared
+    Cleared,
+    /// The chunk was removed from the tilemap
+    Removed,
+}
+
+/// A chunk that changed during the current frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DirtyChunk {
+    /// Tilemap entity the chunk belongs to
+    pub tilemap: Entity,
+    pub chunk_pos: IVec3,
+    pub cause: DirtyCause,
+}
+
+/// Chunks that changed during the current frame, across all tilemaps.
+///
+/// This is rebuilt by [`TileMapSystem::UpdateChunks`](crate::plugin::TileMapSystem::UpdateChunks) every frame,
+/// and is the single source of truth for incremental updates of anything derived from chunks,
+/// such as rendering and [`ChunkExtensionData`](crate::extension::ChunkExtensionData).
+/// Systems reading it should run after that set.
+///
+/// Entries are in the order the changes were applied, so a replaced chunk is listed as removed and then created.
+/// A chunk is only listed once as modified, no matter how many of its tiles changed.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct DirtyChunks {
+    chunks: Vec<DirtyChunk>,
+    /// Tilemap entity and chunk position of every listed chunk, for fast lookups
+    keys: HashSet<(Entity, IVec3)>,
+}
+
+impl DirtyChunks {
+    /// Iterate over all dirty chunks
+    pub fn iter(&self) -> impl Iterator<Item = &DirtyChunk> {
+        self.chunks.iter()
+    }
+
+    /// Iterate over dirty chunks of the specified tilemap
+    pub fn iter_tilemap(&self, tilemap: Entity) -> impl Iterator<Item = &DirtyChunk> {
+        self.chunks
+            .iter()
+            .filter(move |dirty_chunk| dirty_chunk.tilemap == tilemap)
+    }
+
+    /// Check whether the chunk at the specified chunk position of a tilemap is dirty
+    pub fn contains(&self, tilemap: Entity, chunk_pos: IVec3) -> bool {
+        self.keys.contains(&(tilemap, chunk_pos))
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Record a chunk as dirty
+    pub(crate) fn mark(&mut self, tilemap: Entity, chunk_pos: IVec3, cause: DirtyCause) {
+        let is_new = self.keys.insert((tilemap, chunk_pos));
+
+        if cause == DirtyCause::Modified && !is_new {
+            return;
+        }
+
+        self.chunks.push(DirtyChunk {
+            tilemap,
+            chunk_pos,
+            cause,
+        });
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.chunks.clear();
+        self.keys.clear();
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    dirty::{DirtyCause, DirtyChunks},
+    plugin::TileMapSystem,
+    TileMap,
+};
+
+/// What happened to a chunk
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChunkLifecycle {
+    /// The chunk was created, because a tile was set within it
+    Created,
+    /// All tiles in the chunk were cleared
+    Cleared,
+    /// The chunk was removed from the tilemap
+    Evicted,
+}
+
+/// Sent by [`TileMapSystem::UpdateChunks`] whenever a chunk is created, cleared or removed
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChunkLifecycleEvent {
+    /// Tilemap entity the chunk belongs to
+    pub tilemap: Entity,
+    pub chunk_pos: IVec3,
+    pub kind: ChunkLifecycle,
+}
+
+/// Per-chunk data attached by other plugins, such as light grids or navigation data.
+///
+/// Register an extension with [`ChunkDataExtensionAppExt::add_chunk_data_extension`], and its data
+/// will be created, cleared and removed in lockstep with the chunks of every tilemap,
+/// stored in a [`ChunkExtensionData`] component on the tilemap entity.
+pub trait ChunkDataExtension: Send + Sync + 'static {
+    /// Create data for a newly created chunk
+    fn create(tilemap: &TileMap, chunk_pos: IVec3) -> Self;
+
+    /// Called when all tiles in the chunk have been cleared
+    fn clear(&mut self) {}
+}
+
+/// Storage for the per-chunk data of a [`ChunkDataExtension`]
+#[derive(Component)]
+pub struct ChunkExtensionData<T: ChunkDataExtension> {
+    chunks: HashMap<IVec3, T>,
+}
+
+impl<T: ChunkDataExtension> ChunkExtensionData<T> {
+    /// Get data for the chunk at the specified chunk position
+    pub fn get(&self, chunk_pos: IVec3) -> Option<&T> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    /// Get mutable data for the chunk at the specified chunk position
+    pub fn get_mut(&mut self, chunk_pos: IVec3) -> Option<&mut T> {
+        self.chunks.get_mut(&chunk_pos)
+    }
+
+    /// Iterate over data for all chunks
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &T)> {
+        self.chunks.iter().map(|(chunk_pos, data)| (*chunk_pos, data))
+    }
+}
+
+pub trait ChunkDataExtensionAppExt {
+    /// Register a [`ChunkDataExtension`], keeping its data in sync with tilemap chunks
+    fn add_chunk_data_extension<T: ChunkDataExtension>(&mut self) -> &mut Self;
+}
+
+impl ChunkDataExtensionAppExt for App {
+    fn add_chunk_data_extension<T: ChunkDataExtension>(&mut self) -> &mut Self {
+        self.add_systems(
+            Update,
+            sync_chunk_extension_system::<T>.in_set(TileMapSystem::SyncChunkExtensions),
+        )
+    }
+}
+
+/// Apply chunk changes to extension data
+fn sync_chunk_extension_system<T: ChunkDataExtension>(
+    mut commands: Commands,
+    dirty_chunks: Res<DirtyChunks>,
+    tilemap_query: Query<(Entity, &TileMap), Without<ChunkExtensionData<T>>>,
+    mut extension_query: Query<(&TileMap, &mut ChunkExtensionData<T>)>,
+) {
+    for dirty_chunk in dirty_chunks.iter() {
+        let Ok((tilemap, mut extension_data)) = extension_query.get_mut(dirty_chunk.tilemap) else {
+            continue;
+        };
+
+        match dirty_chunk.cause {
+            DirtyCause::Created => {
+                extension_data
+                    .chunks
+                    .insert(dirty_chunk.chunk_pos, T::create(tilemap, dirty_chunk.chunk_pos));
+            }
+            DirtyCause::Modified => {}
+            DirtyCause::Cleared => {
+                if let Some(data) = extension_data.chunks.get_mut(&dirty_chunk.chunk_pos) {
+                    data.clear();
+                }
+            }
+            DirtyCause::Removed => {
+                extension_data.chunks.remove(&dirty_chunk.chunk_pos);
+            }
+        }
+    }
+
+    // Attach extension data to tilemaps that don't have it yet, covering all their existing chunks
+    for (entity, tilemap) in tilemap_query.iter() {
+        let chunks = tilemap
+            .chunks
+            .keys()
+            .map(|chunk_pos| (*chunk_pos, T::create(tilemap, *chunk_pos)))
+            .collect();
+
+        commands.entity(entity).insert(ChunkExtensionData::<T> { chunks });
+    }
+}
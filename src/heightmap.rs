@@ -0,0 +1,100 @@
+use bevy::{image::TextureAccessError, prelude::*};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Tile, TileMap};
+
+/// Range of heights mapped to a tile by [`TileMap::set_tiles_from_heights`]
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub struct HeightBand {
+    /// Heights up to and including this value use this band, unless they are covered by an earlier band
+    pub max_height: f32,
+    /// Tile to place. If `None`, cells in this band are left untouched.
+    pub tile: Option<Tile>,
+}
+
+impl HeightBand {
+    pub fn new(max_height: f32, tile: Tile) -> Self {
+        Self {
+            max_height,
+            tile: Some(tile),
+        }
+    }
+}
+
+/// Find the tile for a height, given bands in ascending order
+#[inline]
+fn band_tile(bands: &[HeightBand], height: f32) -> Option<&Tile> {
+    bands.iter().find(|band| height <= band.max_height)?.tile.as_ref()
+}
+
+impl TileMap {
+    /// Set tiles on a layer from a heightmap, such as one produced by a noise function.
+    ///
+    /// `heights` is in row-major order with the first row at the bottom, and must contain `size.x * size.y` values.
+    /// Each height is mapped to the first of `bands` whose `max_height` it does not exceed,
+    /// so bands should be in ascending order (e.g. water, sand, grass, rock).
+    /// Heights above the last band are left untouched.
+    pub fn set_tiles_from_heights(
+        &mut self,
+        heights: &[f32],
+        size: UVec2,
+        origin: IVec2,
+        layer: i32,
+        bands: &[HeightBand],
+    ) {
+        assert_eq!(
+            heights.len(),
+            (size.x * size.y) as usize,
+            "heightmap must contain size.x * size.y values"
+        );
+
+        let width = size.x as usize;
+
+        self.set_tiles(heights.iter().enumerate().filter_map(|(i, height)| {
+            let tile = band_tile(bands, *height)?;
+            let pos = origin + IVec2::new((i % width) as i32, (i / width) as i32);
+
+            Some((pos.extend(layer), Some(tile.clone())))
+        }));
+    }
+
+    /// Set tiles on a layer from a grayscale heightmap image,
+    /// using the red channel as the height in the range 0.0 to 1.0.
+    ///
+    /// The bottom left pixel of the image is placed at `origin`. See [`TileMap::set_tiles_from_heights`]
+    /// for how heights are mapped to tiles. The image must be accessible on the CPU.
+    pub fn set_tiles_from_heightmap_image(
+        &mut self,
+        image: &Image,
+        origin: IVec2,
+        layer: i32,
+        bands: &[HeightBand],
+    ) -> Result<(), TextureAccessError> {
+        let size = image.size();
+
+        // Use the stored value rather than the decoded color, so that heights are not affected by gamma
+        let is_srgb = image.texture_descriptor.format.is_srgb();
+
+        let mut heights = Vec::with_capacity((size.x * size.y) as usize);
+
+        for y in (0..size.y).rev() {
+            for x in 0..size.x {
+                let color = image.get_color_at(x, y)?;
+
+                heights.push(if is_srgb {
+                    color.to_srgba().red
+                } else {
+                    color.to_linear().red
+                });
+            }
+        }
+
+        self.set_tiles_from_heights(&heights, size, origin, layer, bands);
+
+        Ok(())
+    }
+}
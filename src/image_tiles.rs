@@ -0,0 +1,69 @@
+use bevy::{image::TextureAccessError, prelude::*, utils::HashMap};
+
+use crate::{Tile, TileMap};
+
+/// Mapping from pixel colors to tiles, used by [`TileMap::set_tiles_from_image`].
+///
+/// Colors are matched exactly, by their 8-bit sRGBA value.
+#[derive(Clone, Debug, Default)]
+pub struct ColorToTileMapping {
+    tiles: HashMap<[u8; 4], Tile>,
+}
+
+impl ColorToTileMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map pixels of the specified color to a tile
+    pub fn with(mut self, color: impl Into<Color>, tile: Tile) -> Self {
+        self.insert(color, tile);
+        self
+    }
+
+    /// Map pixels of the specified color to a tile, replacing any previous tile for the color
+    pub fn insert(&mut self, color: impl Into<Color>, tile: Tile) {
+        self.tiles.insert(color.into().to_srgba().to_u8_array(), tile);
+    }
+
+    /// Get the tile pixels of the specified color map to, if any
+    pub fn get(&self, color: impl Into<Color>) -> Option<&Tile> {
+        self.tiles.get(&color.into().to_srgba().to_u8_array())
+    }
+}
+
+impl TileMap {
+    /// Set tiles on a layer from an image, where each pixel's color is mapped to a tile.
+    /// This allows painting levels in any image editor.
+    ///
+    /// The bottom left pixel of the image is placed at `origin`.
+    /// Pixels with colors that are not mapped are left untouched. The image must be accessible on the CPU.
+    pub fn set_tiles_from_image(
+        &mut self,
+        image: &Image,
+        origin: IVec2,
+        layer: i32,
+        mapping: &ColorToTileMapping,
+    ) -> Result<(), TextureAccessError> {
+        let size = image.size();
+
+        let mut tiles = Vec::new();
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let Some(tile) = mapping.get(image.get_color_at(x, y)?) else {
+                    continue;
+                };
+
+                // The image's y axis points down
+                let pos = origin + IVec2::new(x as i32, (size.y - 1 - y) as i32);
+
+                tiles.push((pos.extend(layer), Some(tile.clone())));
+            }
+        }
+
+        self.set_tiles(tiles);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use bevy::{prelude::*, render::view::RenderLayers, utils::HashMap};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Tile;
+
+/// Settings for a single layer of a [`TileMap`](crate::TileMap)
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub struct TileMapLayer {
+    /// Name of the layer, for looking it up with [`TileMap::layer_by_name`](crate::TileMap::layer_by_name)
+    pub name: Option<String>,
+    /// Custom properties, such as those carried over from external map editors
+    pub properties: HashMap<String, TileMapLayerProperty>,
+    /// Tile rendered in every empty cell within `fill_bounds`.
+    /// This allows large uniform backgrounds without storing a tile for every cell.
+    pub fill_tile: Option<Tile>,
+    /// Area to fill with `fill_tile`, in tile coordinates (inclusive)
+    pub fill_bounds: Option<IRect>,
+    /// Parallax factor, relative to the first 2D camera.
+    /// At 1.0 the layer moves with the rest of the world, at 0.0 it stays fixed relative to the camera,
+    /// and values in between make it move slower, as if it were further away.
+    pub parallax: Vec2,
+    /// Offset of the layer in world units, such as a layer offset from a map editor
+    pub offset: Vec2,
+    /// Image to use for this layer instead of the tilemap's image.
+    /// Only used if `texture_atlas_layout` is also set.
+    /// Not serialized, as handles are re-bound after deserializing the tilemap.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[reflect(skip_serializing)]
+    pub image: Option<Handle<Image>>,
+    /// Texture atlas layout to use for this layer instead of the tilemap's layout.
+    /// Only used if `image` is also set.
+    /// Not serialized, as handles are re-bound after deserializing the tilemap.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[reflect(skip_serializing)]
+    pub texture_atlas_layout: Option<Handle<TextureAtlasLayout>>,
+    /// Render layers this layer is visible on.
+    /// If not set, the layer is visible to cameras on the default render layer.
+    /// This allows, for example, a minimap camera to only render the terrain layer.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub render_layers: Option<RenderLayers>,
+    /// How the layer's tiles are blended with what is behind them.
+    /// Only used by tilemaps with [`AlphaMode2d::Blend`](bevy::sprite::AlphaMode2d::Blend).
+    pub blend_mode: TileBlendMode,
+    /// The layer's tiles are fully opaque and cover everything behind them.
+    /// Opaque layers are drawn in the opaque pass with depth writes, which reduces overdraw
+    /// on maps with several stacked full layers. Transparent pixels are drawn as opaque,
+    /// and the blend mode and [`TileMapMaterial`](crate::TileMapMaterial)s are not used.
+    pub opaque: bool,
+}
+
+/// How tiles are blended with what is behind them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Default, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum TileBlendMode {
+    /// Regular alpha blending
+    #[default]
+    Alpha,
+    /// The tile color is added, such as for glow and effect layers
+    Additive,
+    /// The tile color is multiplied, such as for shadow layers
+    Multiply,
+}
+
+impl Default for TileMapLayer {
+    fn default() -> Self {
+        Self {
+            name: None,
+            properties: Default::default(),
+            fill_tile: None,
+            fill_bounds: None,
+            parallax: Vec2::ONE,
+            offset: Vec2::ZERO,
+            image: None,
+            texture_atlas_layout: None,
+            render_layers: None,
+            blend_mode: TileBlendMode::Alpha,
+            opaque: false,
+        }
+    }
+}
+
+impl TileMapLayer {
+    /// Get a custom property by name
+    #[inline]
+    pub fn property(&self, name: &str) -> Option<&TileMapLayerProperty> {
+        self.properties.get(name)
+    }
+
+    /// Set a custom property, returning the previous value if there was one
+    pub fn set_property(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<TileMapLayerProperty>,
+    ) -> Option<TileMapLayerProperty> {
+        self.properties.insert(name.into(), value.into())
+    }
+
+    /// Get the fill tile and the bounds it covers, if both are set
+    #[inline]
+    pub fn fill(&self) -> Option<(&Tile, IRect)> {
+        Some((self.fill_tile.as_ref()?, self.fill_bounds?))
+    }
+
+    /// Get the image and texture atlas layout overriding the tilemap's, if both are set
+    #[inline]
+    pub fn tileset(&self) -> Option<(&Handle<Image>, &Handle<TextureAtlasLayout>)> {
+        Some((self.image.as_ref()?, self.texture_atlas_layout.as_ref()?))
+    }
+
+    /// Get the world-space offset to apply to the layer for parallax, given the camera position
+    #[inline]
+    pub fn parallax_offset(&self, camera_position: Vec2) -> Vec2 {
+        camera_position * (Vec2::ONE - self.parallax)
+    }
+
+    /// Get the world-space offset to apply to the layer, including parallax, given the camera position
+    #[inline]
+    pub fn total_offset(&self, camera_position: Vec2) -> Vec2 {
+        self.offset + self.parallax_offset(camera_position)
+    }
+}
+
+/// Value of a custom layer property
+#[derive(Clone, Debug, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum TileMapLayerProperty {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color(Color),
+}
+
+impl TileMapLayerProperty {
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the value as a float. Integers are converted.
+    #[inline]
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            Self::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for TileMapLayerProperty {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for TileMapLayerProperty {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for TileMapLayerProperty {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<String> for TileMapLayerProperty {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for TileMapLayerProperty {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<Color> for TileMapLayerProperty {
+    fn from(value: Color) -> Self {
+        Self::Color(value)
+    }
+}
@@ -0,0 +1,496 @@
+use std::{fmt, io, path::Path};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    render::view::RenderLayers,
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use crate::{Tile, TileData, TileFlags, TileMap, TileMapLayer};
+
+/// Project loaded from an LDtk `.ldtk` file, including levels saved in separate files.
+///
+/// Each level is instantiated as a child entity of the [`LdtkProjectRoot`], with a [`TileMap`]
+/// and an [`LdtkLevelInstance`], positioned at the level's world position. LDtk's y axis points down,
+/// so the tile at (x, y) in LDtk is placed at (x, -y), and the top left of each level is at its origin.
+///
+/// Tile, auto-layer and IntGrid layers with tiles become layers of the tilemap, numbered from 0 with the bottom layer.
+/// IntGrid values are stored in a [`TileData<i32>`](TileData) on the level entity, at the same positions.
+/// Layer names, offsets, opacity and visibility and tile flips and opacity are carried over.
+/// Only one tile is kept per cell of each layer, and all layers should have the same grid size.
+///
+/// Entities placed in entity layers are not spawned. Instead, an [`LdtkEntityEvent`] is sent for each of them
+/// when a level is instantiated, so the game can spawn its own entities for them.
+#[derive(Asset, TypePath, Debug)]
+pub struct LdtkProject {
+    /// Tilesets by their uid. Tilesets without an image are not included.
+    pub tilesets: HashMap<i64, LdtkTileset>,
+    pub levels: Vec<LdtkLevel>,
+}
+
+/// Tileset of an [`LdtkProject`]
+#[derive(Clone, Debug)]
+pub struct LdtkTileset {
+    pub image: Handle<Image>,
+    pub texture_atlas_layout: Handle<TextureAtlasLayout>,
+}
+
+/// Level of an [`LdtkProject`]
+#[derive(Clone, Debug)]
+pub struct LdtkLevel {
+    pub identifier: String,
+    pub iid: String,
+    /// Position of the level's top left corner in the world, in pixels, with the y axis pointing up
+    pub world_position: Vec2,
+    pub layers: Vec<LdtkLayer>,
+    pub entities: Vec<LdtkEntity>,
+}
+
+/// Tile or IntGrid layer of an [`LdtkLevel`]
+#[derive(Clone, Debug)]
+pub struct LdtkLayer {
+    /// Tilemap layer the tiles are placed on
+    pub z: i32,
+    /// Uid of the tileset used by the layer's tiles, if it has one
+    pub tileset: Option<i64>,
+    pub settings: TileMapLayer,
+    pub tiles: Vec<(IVec2, Tile)>,
+    /// Non-zero IntGrid values
+    pub int_grid: Vec<(IVec2, i32)>,
+}
+
+/// Entity placed in an entity layer of an [`LdtkLevel`]
+#[derive(Clone, Debug)]
+pub struct LdtkEntity {
+    pub identifier: String,
+    pub iid: String,
+    /// Name of the entity layer it was placed on
+    pub layer: String,
+    /// Position of the entity's pivot relative to the level, in pixels, with the y axis pointing up
+    pub position: Vec2,
+    /// Tile position of the entity's pivot
+    pub tile_pos: IVec2,
+    /// Size of the entity, in pixels
+    pub size: Vec2,
+    /// Values of the entity's custom fields, by identifier
+    pub fields: HashMap<String, serde_json::Value>,
+}
+
+/// Sent for each entity of a level when the level is instantiated by an [`LdtkProjectRoot`]
+#[derive(Event, Clone, Debug)]
+pub struct LdtkEntityEvent {
+    /// Level entity, which the entity's position is relative to
+    pub level: Entity,
+    pub entity: LdtkEntity,
+}
+
+/// Level instantiated from an [`LdtkProject`], as a child of its [`LdtkProjectRoot`]
+#[derive(Component, Clone, Debug)]
+pub struct LdtkLevelInstance {
+    pub identifier: String,
+    pub iid: String,
+}
+
+impl LdtkLevel {
+    /// Build the level's tilemap and IntGrid values
+    fn build(&self, tilesets: &HashMap<i64, LdtkTileset>) -> (TileMap, TileData<i32>) {
+        // The tilemap uses the first tileset, and layers using other tilesets override it
+        let base_tileset = self.layers.iter().find_map(|layer| layer.tileset);
+
+        let mut tilemap = match base_tileset.and_then(|uid| tilesets.get(&uid)) {
+            Some(tileset) => TileMap::new(tileset.image.clone(), tileset.texture_atlas_layout.clone()),
+            None => TileMap::new(Handle::default(), Handle::default()),
+        };
+
+        let mut int_grid = TileData::new();
+
+        for layer in self.layers.iter() {
+            let mut settings = layer.settings.clone();
+
+            if layer.tileset != base_tileset {
+                if let Some(tileset) = layer.tileset.and_then(|uid| tilesets.get(&uid)) {
+                    settings.image = Some(tileset.image.clone());
+                    settings.texture_atlas_layout = Some(tileset.texture_atlas_layout.clone());
+                }
+            }
+
+            tilemap.set_layer(layer.z, settings);
+            tilemap.set_tiles(
+                layer
+                    .tiles
+                    .iter()
+                    .map(|(pos, tile)| (pos.extend(layer.z), Some(tile.clone()))),
+            );
+
+            for (pos, value) in layer.int_grid.iter() {
+                int_grid.set(pos.extend(layer.z), Some(*value));
+            }
+        }
+
+        (tilemap, int_grid)
+    }
+}
+
+/// Error loading an [`LdtkProject`]
+#[derive(Debug)]
+pub enum LdtkProjectLoaderError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// A separate level file could not be read
+    Level(String, bevy::asset::ReadAssetBytesError),
+}
+
+impl fmt::Display for LdtkProjectLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read project: {err}"),
+            Self::Json(err) => write!(f, "could not parse project: {err}"),
+            Self::Level(path, err) => write!(f, "could not read level {path}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LdtkProjectLoaderError {}
+
+impl From<io::Error> for LdtkProjectLoaderError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for LdtkProjectLoaderError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// Parts of the LDtk project format that are used
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectJson {
+    defs: DefsJson,
+    levels: Vec<LevelJson>,
+}
+
+#[derive(Deserialize)]
+struct DefsJson {
+    tilesets: Vec<TilesetJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TilesetJson {
+    uid: i64,
+    rel_path: Option<String>,
+    tile_grid_size: u32,
+    spacing: u32,
+    padding: u32,
+    #[serde(rename = "__cWid")]
+    columns: u32,
+    #[serde(rename = "__cHei")]
+    rows: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LevelJson {
+    identifier: String,
+    iid: String,
+    world_x: i32,
+    world_y: i32,
+    /// Only set if levels are saved in separate files
+    external_rel_path: Option<String>,
+    /// Not set if the level is saved in a separate file
+    layer_instances: Option<Vec<LayerJson>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerJson {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__cWid")]
+    width: i32,
+    #[serde(rename = "__gridSize")]
+    grid_size: i32,
+    #[serde(rename = "__opacity")]
+    opacity: f32,
+    #[serde(rename = "__pxTotalOffsetX")]
+    offset_x: i32,
+    #[serde(rename = "__pxTotalOffsetY")]
+    offset_y: i32,
+    #[serde(rename = "__tilesetDefUid")]
+    tileset_uid: Option<i64>,
+    visible: bool,
+    grid_tiles: Vec<TileJson>,
+    auto_layer_tiles: Vec<TileJson>,
+    entity_instances: Vec<EntityJson>,
+    int_grid_csv: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+struct TileJson {
+    /// Position in the layer, in pixels
+    px: [i32; 2],
+    /// Flip bits, with bit 0 for x and bit 1 for y
+    f: u8,
+    /// Tile id in the tileset
+    t: u32,
+    /// Opacity
+    a: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityJson {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    iid: String,
+    px: [i32; 2],
+    #[serde(rename = "__grid")]
+    grid: [i32; 2],
+    width: f32,
+    height: f32,
+    field_instances: Vec<FieldJson>,
+}
+
+#[derive(Deserialize)]
+struct FieldJson {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__value")]
+    value: serde_json::Value,
+}
+
+/// Loads [`LdtkProject`]s from `.ldtk` files
+#[derive(Default)]
+pub struct LdtkProjectLoader;
+
+impl AssetLoader for LdtkProjectLoader {
+    type Asset = LdtkProject;
+    type Settings = ();
+    type Error = LdtkProjectLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let project: ProjectJson = serde_json::from_slice(&bytes)?;
+
+        // Paths in the project are relative to the project file
+        let project_dir = load_context.path().parent().unwrap_or(Path::new("")).to_path_buf();
+
+        let mut tilesets = HashMap::default();
+
+        for tileset in project.defs.tilesets.iter() {
+            let Some(rel_path) = tileset.rel_path.as_ref() else {
+                continue;
+            };
+
+            let texture_atlas_layout = TextureAtlasLayout::from_grid(
+                UVec2::splat(tileset.tile_grid_size),
+                tileset.columns,
+                tileset.rows,
+                Some(UVec2::splat(tileset.spacing)),
+                Some(UVec2::splat(tileset.padding)),
+            );
+
+            tilesets.insert(
+                tileset.uid,
+                LdtkTileset {
+                    image: load_context.load(project_dir.join(rel_path)),
+                    texture_atlas_layout: load_context
+                        .add_labeled_asset(format!("tileset{}", tileset.uid), texture_atlas_layout),
+                },
+            );
+        }
+
+        let mut levels = Vec::with_capacity(project.levels.len());
+
+        for level in project.levels {
+            let level = match (level.layer_instances.is_none(), level.external_rel_path.as_ref()) {
+                (true, Some(rel_path)) => {
+                    let path = project_dir.join(rel_path);
+                    let bytes = load_context
+                        .read_asset_bytes(path.clone())
+                        .await
+                        .map_err(|err| LdtkProjectLoaderError::Level(path.display().to_string(), err))?;
+
+                    serde_json::from_slice(&bytes)?
+                }
+                _ => level,
+            };
+
+            levels.push(convert_level(level));
+        }
+
+        Ok(LdtkProject { tilesets, levels })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+fn convert_level(level: LevelJson) -> LdtkLevel {
+    let layer_instances = level.layer_instances.unwrap_or_default();
+
+    let mut layers = Vec::new();
+    let mut entities = Vec::new();
+
+    // Layers are listed from the top down
+    for (index, layer) in layer_instances.into_iter().rev().enumerate() {
+        for entity in layer.entity_instances.iter() {
+            entities.push(LdtkEntity {
+                identifier: entity.identifier.clone(),
+                iid: entity.iid.clone(),
+                layer: layer.identifier.clone(),
+                position: Vec2::new(
+                    (entity.px[0] + layer.offset_x) as f32,
+                    -(entity.px[1] + layer.offset_y) as f32,
+                ),
+                tile_pos: IVec2::new(entity.grid[0], -entity.grid[1]),
+                size: Vec2::new(entity.width, entity.height),
+                fields: entity
+                    .field_instances
+                    .iter()
+                    .map(|field| (field.identifier.clone(), field.value.clone()))
+                    .collect(),
+            });
+        }
+
+        let grid_size = layer.grid_size.max(1);
+
+        let tiles: Vec<(IVec2, Tile)> = layer
+            .grid_tiles
+            .iter()
+            .chain(layer.auto_layer_tiles.iter())
+            .map(|tile| {
+                let mut flags = TileFlags::empty();
+                flags.set(TileFlags::FLIP_X, tile.f & 1 != 0);
+                flags.set(TileFlags::FLIP_Y, tile.f & 2 != 0);
+
+                (
+                    IVec2::new(tile.px[0] / grid_size, -(tile.px[1] / grid_size)),
+                    Tile {
+                        sprite_index: tile.t,
+                        color: Color::srgba(1.0, 1.0, 1.0, tile.a * layer.opacity),
+                        flags,
+                        ..default()
+                    },
+                )
+            })
+            .collect();
+
+        let int_grid: Vec<(IVec2, i32)> = layer
+            .int_grid_csv
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(i, value)| {
+                let i = i as i32;
+                (IVec2::new(i % layer.width.max(1), -(i / layer.width.max(1))), *value)
+            })
+            .collect();
+
+        if tiles.is_empty() && int_grid.is_empty() {
+            continue;
+        }
+
+        let settings = TileMapLayer {
+            name: Some(layer.identifier),
+            offset: Vec2::new(layer.offset_x as f32, -layer.offset_y as f32),
+            render_layers: (!layer.visible).then(RenderLayers::none),
+            ..default()
+        };
+
+        layers.push(LdtkLayer {
+            z: index as i32,
+            tileset: layer.tileset_uid,
+            settings,
+            tiles,
+            int_grid,
+        });
+    }
+
+    LdtkLevel {
+        identifier: level.identifier,
+        iid: level.iid,
+        world_position: Vec2::new(level.world_x as f32, -level.world_y as f32),
+        layers,
+        entities,
+    }
+}
+
+/// Instantiates the levels of an [`LdtkProject`] as child entities, once it is loaded.
+/// The levels are respawned when the project or its tilesets change, discarding any changes made to them.
+#[derive(Component, Clone, Debug, Deref)]
+#[require(Transform, Visibility)]
+pub struct LdtkProjectRoot(pub Handle<LdtkProject>);
+
+/// Spawn the levels of [`LdtkProjectRoot`]s when their project is loaded or changes
+pub(crate) fn instantiate_ldtk_projects_system(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<LdtkProject>>,
+    mut entity_events: EventWriter<LdtkEntityEvent>,
+    projects: Res<Assets<LdtkProject>>,
+    root_query: Query<(Entity, Ref<LdtkProjectRoot>, Option<&Children>)>,
+    level_query: Query<(), With<LdtkLevelInstance>>,
+) {
+    let changed_projects: Vec<AssetId<LdtkProject>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, root, children) in root_query.iter() {
+        if !root.is_changed() && !changed_projects.contains(&root.id()) {
+            continue;
+        }
+
+        // Not loaded yet. The levels are spawned once it is.
+        let Some(project) = projects.get(&root.0) else {
+            continue;
+        };
+
+        for child in children.into_iter().flatten() {
+            if level_query.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        for level in project.levels.iter() {
+            let (tilemap, int_grid) = level.build(&project.tilesets);
+
+            let level_entity = commands
+                .spawn((
+                    Name::new(level.identifier.clone()),
+                    LdtkLevelInstance {
+                        identifier: level.identifier.clone(),
+                        iid: level.iid.clone(),
+                    },
+                    tilemap,
+                    int_grid,
+                    Transform::from_translation(level.world_position.extend(0.0)),
+                ))
+                .set_parent(entity)
+                .id();
+
+            for ldtk_entity in level.entities.iter() {
+                entity_events.send(LdtkEntityEvent {
+                    level: level_entity,
+                    entity: ldtk_entity.clone(),
+                });
+            }
+        }
+    }
+}
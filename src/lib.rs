@@ -1,6 +1,64 @@
+pub mod ambient;
+pub mod animation;
+#[cfg(feature = "aseprite")]
+pub mod aseprite;
+#[cfg(feature = "ron")]
+pub mod asset;
+mod bake;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod camera;
+mod chunk_io;
+pub mod colliders;
+pub mod collision;
+pub mod culling;
+pub mod cursor;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod diagnostics;
+pub mod dirty;
+pub mod extension;
+mod heightmap;
+mod image_tiles;
+mod layer;
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
+pub mod lighting;
+pub mod minimap;
+mod nine_patch;
+pub mod occluders;
 pub mod plugin;
 pub mod prelude;
+#[cfg(feature = "procgen")]
+pub mod procgen;
+pub mod projection;
+pub mod provider;
 mod render;
+pub mod streaming;
+mod tile_data;
+#[cfg(feature = "tiled")]
+pub mod tiled;
 mod tilemap;
+#[cfg(feature = "serde")]
+mod tilemap_serde;
+pub mod tileset_array;
+pub mod unloading;
+#[cfg(feature = "weather")]
+pub mod weather;
 
-pub use self::tilemap::{Tile, TileFlags, TileMap};
+pub use self::animation::TileAnimation;
+pub use self::chunk_io::ChunkDecodeError;
+#[cfg(feature = "zstd")]
+pub use self::chunk_io::CompressedTileMapError;
+pub use self::heightmap::HeightBand;
+pub use self::image_tiles::ColorToTileMapping;
+pub use self::layer::{TileBlendMode, TileMapLayer, TileMapLayerProperty};
+pub use self::nine_patch::NinePatchTiles;
+pub use self::render::material::*;
+pub use self::render::pipeline::TilemapPipelineKey;
+pub use self::render::stats::TileMapRenderStats;
+pub use self::render::{
+    ChunkSource, ExtractedChunk, ExtractedTile, ExtractedTilemap, ExtractedTilemaps, TileMapShader,
+};
+pub use self::tile_data::TileData;
+pub use self::tilemap::{Chunk, Tile, TileColorMode, TileFlags, TileMap, TileShape};
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+/// Maximum number of [`TileMapPointLight`]s lighting tilemaps. Additional lights are ignored.
+pub const MAX_TILEMAP_LIGHTS: usize = 16;
+
+/// Normal map for the image of a tilemap, enabling lighting by [`TileMapPointLight`]s.
+///
+/// The normal map must have the same size and layout as the tilemap's image, and use OpenGL conventions (Y up).
+/// Layers with their own tileset, and tilesets packed by a [`TilesetArray`](crate::tileset_array::TilesetArray),
+/// are not lit.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component, Debug, Default)]
+pub struct TileMapNormalMap(pub Handle<Image>);
+
+/// Point light lighting tilemaps with a [`TileMapNormalMap`], positioned by the entity's transform.
+///
+/// Lit tiles receive [`TileMapAmbient`](crate::ambient::TileMapAmbient) light in addition to the lights,
+/// using a simple N-dot-L model.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Debug, Default)]
+#[require(Transform, Visibility)]
+pub struct TileMapPointLight {
+    pub color: Color,
+    pub intensity: f32,
+    /// Distance at which the light has faded out completely
+    pub range: f32,
+    /// Height of the light above the tilemap. Lower lights hit surfaces at shallower angles.
+    pub height: f32,
+}
+
+impl Default for TileMapPointLight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 1.0,
+            range: 256.0,
+            height: 32.0,
+        }
+    }
+}
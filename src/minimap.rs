@@ -0,0 +1,170 @@
+use bevy::{
+    image::BevyDefault,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::RenderLayers,
+    },
+};
+
+use crate::TileMap;
+
+/// Settings for a minimap, rendering a tilemap to an image with a secondary camera.
+///
+/// Use [`TileMapMinimap::spawn`] to set up the camera and image, and display the returned image,
+/// such as with a UI image node.
+///
+/// The rendered tilemap layers are added to the minimap's render layer, and the camera is kept
+/// centered on the tilemap's bounds. Other entities are only rendered if they are also on the render layer.
+#[derive(Clone, Debug)]
+pub struct TileMapMinimap {
+    /// Tilemap entity to render
+    pub tilemap: Entity,
+    /// Size of the image, in pixels
+    pub size: UVec2,
+    /// World units per pixel of the image
+    pub scale: f32,
+    /// Tilemap layers to render, or `None` for all layers
+    pub layers: Option<Vec<i32>>,
+    /// Render layer dedicated to the minimap, which should not be used by other cameras
+    pub render_layer: usize,
+    pub clear_color: Color,
+}
+
+impl TileMapMinimap {
+    /// Create minimap settings for the specified tilemap, rendering all layers on render layer 1
+    pub fn new(tilemap: Entity, size: UVec2, scale: f32) -> Self {
+        Self {
+            tilemap,
+            size,
+            scale,
+            layers: None,
+            render_layer: 1,
+            clear_color: Color::NONE,
+        }
+    }
+
+    /// Render only the specified tilemap layers
+    pub fn with_layers(mut self, layers: impl IntoIterator<Item = i32>) -> Self {
+        self.layers = Some(layers.into_iter().collect());
+        self
+    }
+
+    /// Spawn the minimap camera, and create the image it renders to.
+    /// Returns the camera entity and the image.
+    pub fn spawn(self, commands: &mut Commands, images: &mut Assets<Image>) -> (Entity, Handle<Image>) {
+        let size = Extent3d {
+            width: self.size.x,
+            height: self.size.y,
+            depth_or_array_layers: 1,
+        };
+
+        let mut image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::bevy_default(),
+            RenderAssetUsages::default(),
+        );
+
+        image.texture_descriptor.usage =
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+
+        let image = images.add(image);
+
+        let camera = commands
+            .spawn((
+                Camera2d,
+                Camera {
+                    target: RenderTarget::Image(image.clone().into()),
+                    // Render before the main camera, so the image is up to date when it is displayed
+                    order: -1,
+                    clear_color: ClearColorConfig::Custom(self.clear_color),
+                    ..default()
+                },
+                OrthographicProjection {
+                    scale: self.scale,
+                    ..OrthographicProjection::default_2d()
+                },
+                RenderLayers::layer(self.render_layer),
+                TileMapMinimapCamera {
+                    tilemap: self.tilemap,
+                    layers: self.layers,
+                    render_layer: self.render_layer,
+                },
+            ))
+            .id();
+
+        (camera, image)
+    }
+}
+
+/// Camera rendering a minimap of a tilemap. Spawned by [`TileMapMinimap::spawn`].
+#[derive(Component, Clone, Debug)]
+pub struct TileMapMinimapCamera {
+    /// Tilemap entity to render
+    pub tilemap: Entity,
+    /// Tilemap layers to render, or `None` for all layers
+    pub layers: Option<Vec<i32>>,
+    /// Render layer of the camera, which the rendered tilemap layers are added to
+    pub render_layer: usize,
+}
+
+/// Add the rendered tilemap layers to the render layer of their minimap cameras,
+/// and keep the cameras centered on their tilemap
+pub(crate) fn update_minimap_cameras_system(
+    mut camera_query: Query<(&TileMapMinimapCamera, &mut Transform)>,
+    mut tilemap_query: Query<(&mut TileMap, &GlobalTransform)>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (minimap_camera, mut camera_transform) in camera_query.iter_mut() {
+        let Ok((mut tilemap, tilemap_transform)) = tilemap_query.get_mut(minimap_camera.tilemap) else {
+            continue;
+        };
+
+        let layers: Vec<i32> = minimap_camera.layers.clone().unwrap_or_else(|| {
+            let mut layers: Vec<i32> = tilemap
+                .chunks
+                .keys()
+                .map(|chunk_pos| chunk_pos.z)
+                .chain(tilemap.layers().map(|(layer, _)| layer))
+                .collect();
+
+            layers.sort_unstable();
+            layers.dedup();
+            layers
+        });
+
+        for layer in layers {
+            let is_rendered = tilemap
+                .layer(layer)
+                .and_then(|settings| settings.render_layers.as_ref())
+                .is_some_and(|render_layers| {
+                    render_layers.intersects(&RenderLayers::layer(minimap_camera.render_layer))
+                });
+
+            // Only touch layers that need it, as changing layer settings causes their chunks to be remeshed
+            if !is_rendered {
+                let settings = tilemap.layer_mut(layer);
+                let render_layers = settings.render_layers.take().unwrap_or_default();
+
+                settings.render_layers = Some(render_layers.with(minimap_camera.render_layer));
+            }
+        }
+
+        let (Some(bounds), Some(tile_size)) = (tilemap.bounds(), tilemap.tile_size(&texture_atlases)) else {
+            continue;
+        };
+
+        let center = (bounds.min + bounds.max).as_vec2() / 2.0;
+        let local_center = tilemap.projection.grid_matrix(tile_size.as_vec2()) * center;
+        let world_center = tilemap_transform.transform_point(local_center.extend(0.0));
+
+        let translation = world_center.truncate().extend(camera_transform.translation.z);
+        if camera_transform.translation != translation {
+            camera_transform.translation = translation;
+        }
+    }
+}
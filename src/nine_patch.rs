@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Tile, TileMap};
+
+/// Tiles used to fill a rectangular area with a border, such as a room, a panel or a fenced area
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub struct NinePatchTiles {
+    pub top_left: Tile,
+    pub top: Tile,
+    pub top_right: Tile,
+    pub left: Tile,
+    /// Tile used for the interior. If `None`, the interior is left untouched.
+    pub center: Option<Tile>,
+    pub right: Tile,
+    pub bottom_left: Tile,
+    pub bottom: Tile,
+    pub bottom_right: Tile,
+}
+
+impl NinePatchTiles {
+    /// Get the tile for a position within a rectangle (inclusive)
+    fn tile_at(&self, rect: IRect, pos: IVec2) -> Option<&Tile> {
+        let left = pos.x == rect.min.x;
+        let right = !left && pos.x == rect.max.x;
+        let bottom = pos.y == rect.min.y;
+        let top = !bottom && pos.y == rect.max.y;
+
+        match (left, right, bottom, top) {
+            (true, _, true, _) => Some(&self.bottom_left),
+            (_, true, true, _) => Some(&self.bottom_right),
+            (true, _, _, true) => Some(&self.top_left),
+            (_, true, _, true) => Some(&self.top_right),
+            (true, _, _, _) => Some(&self.left),
+            (_, true, _, _) => Some(&self.right),
+            (_, _, true, _) => Some(&self.bottom),
+            (_, _, _, true) => Some(&self.top),
+            _ => self.center.as_ref(),
+        }
+    }
+}
+
+impl TileMap {
+    /// Fill a rectangle (inclusive) on a layer with corner, edge and center tiles.
+    ///
+    /// If the rectangle is only one tile wide or high, the left and bottom tiles take precedence.
+    pub fn fill_rect_nine_patch(&mut self, rect: IRect, layer: i32, tiles: &NinePatchTiles) {
+        let positions =
+            (rect.min.y..=rect.max.y).flat_map(|y| (rect.min.x..=rect.max.x).map(move |x| IVec2::new(x, y)));
+
+        self.set_tiles(positions.filter_map(|pos| {
+            let tile = tiles.tile_at(rect, pos)?;
+
+            Some((pos.extend(layer), Some(tile.clone())))
+        }));
+    }
+}
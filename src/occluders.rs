@@ -0,0 +1,170 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{dirty::DirtyChunks, Chunk, TileFlags, TileMap};
+
+/// Generates light occluders from the tiles of a tilemap flagged with [`TileFlags::OCCLUDER`].
+///
+/// Adjacent occluder tiles are merged into rectangles, each spawned as a child entity of the tilemap
+/// with a [`TileOccluder`]. With the `light_2d` feature, these also get a `bevy_light_2d` `LightOccluder2d`,
+/// so walls cast shadows without maintaining a separate occluder map.
+///
+/// Occluders are regenerated for changed chunks only. Only orthogonal tilemaps are supported.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TileMapOccluders {
+    /// Layers to generate occluders for, or `None` for all layers
+    pub layers: Option<Vec<i32>>,
+    /// Occluder entities of each chunk, by chunk position
+    chunk_occluders: HashMap<IVec3, Vec<Entity>>,
+    /// Whether occluders have been generated for all chunks, which happens once the tile size is known
+    initialized: bool,
+}
+
+impl TileMapOccluders {
+    /// Generate occluders for the specified layers only
+    pub fn with_layers(layers: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            layers: Some(layers.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Rectangular light occluder, generated from occluder tiles by [`TileMapOccluders`].
+/// Positioned at the center of the rectangle, relative to the tilemap.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component, Debug)]
+#[require(Transform, Visibility)]
+pub struct TileOccluder {
+    /// Half the size of the rectangle, in the tilemap's local units
+    pub half_size: Vec2,
+    /// Rectangle of occluder tiles covered, in tile coordinates (inclusive)
+    pub tiles: IRect,
+}
+
+pub(crate) fn update_occluders_system(
+    mut commands: Commands,
+    mut tilemap_query: Query<(Entity, &TileMap, &mut TileMapOccluders)>,
+    dirty_chunks: Res<DirtyChunks>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, tilemap, mut occluders) in tilemap_query.iter_mut() {
+        let Some(tile_size) = tilemap.tile_size(&texture_atlases) else {
+            continue;
+        };
+
+        let tile_size = tile_size.as_vec2();
+
+        let chunk_positions: Vec<IVec3> = if occluders.initialized {
+            dirty_chunks
+                .iter_tilemap(entity)
+                .map(|dirty_chunk| dirty_chunk.chunk_pos)
+                .collect()
+        } else {
+            occluders.initialized = true;
+            tilemap.chunks.keys().copied().collect()
+        };
+
+        for chunk_pos in chunk_positions {
+            // Despawning recursively also removes the occluders from the tilemap's children.
+            // Occluders may have been despawned already by the user.
+            if let Some(previous) = occluders.chunk_occluders.remove(&chunk_pos) {
+                for occluder in previous {
+                    if let Some(occluder) = commands.get_entity(occluder) {
+                        occluder.despawn_recursive();
+                    }
+                }
+            }
+
+            if occluders
+                .layers
+                .as_ref()
+                .is_some_and(|layers| !layers.contains(&chunk_pos.z))
+            {
+                continue;
+            }
+
+            let Some(chunk) = tilemap.chunks.get(&chunk_pos) else {
+                continue;
+            };
+
+            let z = tilemap.layer_z(chunk_pos.z);
+
+            let chunk_occluders: Vec<Entity> = occluder_rects(chunk)
+                .into_iter()
+                .map(|rect| {
+                    let center = (rect.min + rect.max).as_vec2() / 2.0 * tile_size;
+                    let half_size = (rect.size() + 1).as_vec2() * tile_size / 2.0;
+
+                    commands
+                        .spawn((
+                            TileOccluder { half_size, tiles: rect },
+                            Transform::from_translation(center.extend(z)),
+                            #[cfg(feature = "light_2d")]
+                            bevy_light_2d::prelude::LightOccluder2d {
+                                shape: bevy_light_2d::prelude::LightOccluder2dShape::Rectangle { half_size },
+                            },
+                        ))
+                        .set_parent(entity)
+                        .id()
+                })
+                .collect();
+
+            if !chunk_occluders.is_empty() {
+                occluders.chunk_occluders.insert(chunk_pos, chunk_occluders);
+            }
+        }
+    }
+}
+
+/// Merge the occluder tiles of a chunk into rectangles, in tile coordinates (inclusive).
+///
+/// Each row is split into runs of occluder tiles, and runs spanning the same columns as a run
+/// in the row below are merged with it.
+fn occluder_rects(chunk: &Chunk) -> Vec<IRect> {
+    let Some(bounds) = chunk.bounds() else {
+        return Vec::new();
+    };
+
+    let is_occluder = |x: i32, y: i32| {
+        chunk
+            .get_tile(IVec3::new(x, y, chunk.origin.z))
+            .is_some_and(|tile| tile.flags.contains(TileFlags::OCCLUDER))
+    };
+
+    let mut rects: Vec<IRect> = Vec::new();
+
+    // Rectangles that may still grow upwards, by the columns they span
+    let mut open: HashMap<(i32, i32), IRect> = HashMap::default();
+
+    for y in bounds.min.y..=bounds.max.y {
+        let mut next_open: HashMap<(i32, i32), IRect> = HashMap::default();
+        let mut x = bounds.min.x;
+
+        while x <= bounds.max.x {
+            if !is_occluder(x, y) {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+            while x < bounds.max.x && is_occluder(x + 1, y) {
+                x += 1;
+            }
+
+            let rect = match open.remove(&(start, x)) {
+                Some(rect) => IRect::new(rect.min.x, rect.min.y, rect.max.x, y),
+                None => IRect::new(start, y, x, y),
+            };
+
+            next_open.insert((start, x), rect);
+            x += 1;
+        }
+
+        // Rectangles not continued in this row are complete
+        rects.extend(open.into_values());
+        open = next_open;
+    }
+
+    rects.extend(open.into_values());
+    rects
+}
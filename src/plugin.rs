@@ -1,8 +1,9 @@
 use bevy::{
     asset::load_internal_asset,
-    core_pipeline::core_2d::Transparent2d,
+    core_pipeline::core_2d::{Opaque2d, Transparent2d},
     prelude::*,
     render::{
+        extract_resource::ExtractResourcePlugin,
         render_phase::AddRenderCommand,
         render_resource::{Shader, SpecializedRenderPipelines},
         view::{check_visibility, VisibilitySystems},
@@ -11,54 +12,243 @@ use bevy::{
 };
 
 use crate::{
+    ambient::{TileMapAmbient, TileMapColor},
+    colliders::TileCollider,
+    collision::TileCollision,
+    culling::{ChunkPrefetchSettings, TileMapCulling},
+    diagnostics::{TilemapDiagnostics, TilemapDiagnosticsChannel},
+    dirty::DirtyChunks,
+    extension::ChunkLifecycleEvent,
+    lighting::{TileMapNormalMap, TileMapPointLight},
+    occluders::TileOccluder,
+    projection::{HexCoordinates, HexOrientation, TileMapProjection},
+    provider::TileMapChunkProvider,
     render::{
-        self, draw::DrawTilemap, pipeline::TilemapPipeline, ExtractedTilemaps, ImageBindGroups, TilemapAssetEvents,
-        TilemapMeta, TILEMAP_SHADER_HANDLE,
+        self,
+        draw::DrawTilemap,
+        pipeline::TilemapPipeline,
+        stats::{TileMapRenderStats, TileMapRenderStatsChannel},
+        ExtractedMaterialTilemaps, ExtractedTilemapLights, ExtractedTilemaps, ImageBindGroups, TileMapShader,
+        TilemapAssetEvents, TilemapMeta, TILEMAP_SHADER_HANDLE,
     },
-    tilemap::WithTileMap,
+    tilemap::{Chunk, WithTileMap},
+    unloading::{ChunkUnloadEvent, ChunkUnloading},
+    NinePatchTiles, Tile, TileAnimation, TileBlendMode, TileColorMode, TileFlags, TileMap, TileMapLayer,
+    TileMapLayerProperty, TileShape,
 };
 
 #[derive(Default)]
 pub struct SimpleTileMapPlugin;
 
+/// System sets for the tilemap systems, for ordering other systems relative to them.
+///
+/// [`TileMapSystem::AnimateTiles`], [`TileMapSystem::UnloadChunks`], [`TileMapSystem::ProvideChunks`],
+/// [`TileMapSystem::UpdateChunks`] and [`TileMapSystem::SyncChunkExtensions`] run in the main world's
+/// [`Update`] schedule, and [`TileMapSystem::UpdateChunkAabbs`] in its [`PostUpdate`] schedule.
+/// The remaining sets run in the [`RenderApp`].
+///
+/// [`ExtractedTilemaps`] is populated by [`TileMapSystem::ExtractTilemaps`], and its chunks
+/// are consumed (drained) by [`TileMapSystem::QueueTilemaps`]. Systems that want to read
+/// the extracted tilemaps should run after the former and before the latter.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub enum TileMapSystem {
+    /// Advances tile animations and fade-outs.
+    AnimateTiles,
+    /// Unloads chunks far away from cameras, for tilemaps with [`ChunkUnloading`].
+    /// Runs before [`TileMapSystem::UpdateChunks`].
+    UnloadChunks,
+    /// Generates missing chunks near cameras, for tilemaps with a [`TileMapChunkProvider`],
+    /// and streams chunks of tilemaps with a [`TileMapStreaming`](crate::streaming::TileMapStreaming).
+    /// Runs after [`TileMapSystem::UnloadChunks`] and before [`TileMapSystem::UpdateChunks`].
+    ProvideChunks,
+    /// Applies queued tile changes to the tilemap chunks, and records the changed chunks in
+    /// [`DirtyChunks`].
+    UpdateChunks,
+    /// Keeps [`ChunkExtensionData`](crate::extension::ChunkExtensionData) in sync with the chunks.
+    /// Runs after [`TileMapSystem::UpdateChunks`].
+    SyncChunkExtensions,
+    /// Updates the world-space chunk bounding boxes. Runs in [`PostUpdate`] after transform propagation.
+    UpdateChunkAabbs,
+    /// Extracts tilemaps from the main world into [`ExtractedTilemaps`].
     ExtractTilemaps,
+    /// Extracts asset events relevant to tilemaps.
+    ExtractTilemapEvents,
+    /// Generates vertex data for extracted chunks and adds them to the render phases.
+    QueueTilemaps,
+    /// Uploads the view-specific data of the queued batches and the tilemap lights, and creates their bind group.
+    /// Runs in [`RenderSet::PrepareBindGroups`].
+    PrepareTilemaps,
 }
 
 impl Plugin for SimpleTileMapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, crate::tilemap::update_chunks_system);
+        app.register_type::<Tile>()
+            .register_type::<TileFlags>()
+            .register_type::<TileShape>()
+            .register_type::<TileColorMode>()
+            .register_type::<TileAnimation>()
+            .register_type::<Chunk>()
+            .register_type::<TileMapLayer>()
+            .register_type::<TileMapLayerProperty>()
+            .register_type::<TileBlendMode>()
+            .register_type::<NinePatchTiles>()
+            .register_type::<TileCollision>()
+            .register_type::<TileMap>()
+            .register_type::<TileMapProjection>()
+            .register_type::<HexOrientation>()
+            .register_type::<HexCoordinates>()
+            .register_type::<ChunkPrefetchSettings>()
+            .register_type::<TileMapCulling>()
+            .register_type::<TileMapAmbient>()
+            .register_type::<TileMapColor>()
+            .register_type::<TileMapNormalMap>()
+            .register_type::<TileMapPointLight>()
+            .register_type::<ChunkUnloading>()
+            .register_type::<TileOccluder>()
+            .register_type::<TileCollider>()
+            .init_resource::<TileMapAmbient>()
+            .init_resource::<ChunkPrefetchSettings>()
+            .init_resource::<TileMapRenderStats>()
+            .init_resource::<DirtyChunks>()
+            .init_resource::<TilemapDiagnostics>()
+            .add_event::<ChunkLifecycleEvent>()
+            .add_event::<ChunkUnloadEvent>();
+
+        app.configure_sets(
+            Update,
+            (
+                TileMapSystem::AnimateTiles.before(TileMapSystem::UpdateChunks),
+                TileMapSystem::UnloadChunks.before(TileMapSystem::ProvideChunks),
+                TileMapSystem::ProvideChunks.before(TileMapSystem::UpdateChunks),
+                TileMapSystem::SyncChunkExtensions.after(TileMapSystem::UpdateChunks),
+            ),
+        );
+
+        app.add_systems(
+            Update,
+            (
+                (
+                    crate::animation::advance_tile_animations_system,
+                    crate::animation::fade_tiles_system,
+                )
+                    .in_set(TileMapSystem::AnimateTiles),
+                crate::unloading::unload_distant_chunks_system.in_set(TileMapSystem::UnloadChunks),
+                crate::provider::provide_chunks_system.in_set(TileMapSystem::ProvideChunks),
+                crate::tilemap::update_chunks_system.in_set(TileMapSystem::UpdateChunks),
+                crate::tileset_array::build_tileset_arrays_system,
+                crate::minimap::update_minimap_cameras_system.after(TileMapSystem::UpdateChunks),
+                crate::occluders::update_occluders_system.after(TileMapSystem::UpdateChunks),
+                crate::colliders::update_colliders_system.after(TileMapSystem::UpdateChunks),
+                crate::cursor::update_tile_cursors_system.after(TileMapSystem::UpdateChunks),
+            ),
+        );
+
+        #[cfg(feature = "ron")]
+        app.init_asset::<crate::asset::TileMapAsset>()
+            .init_asset_loader::<crate::asset::TileMapAssetLoader>()
+            .add_systems(
+                Update,
+                crate::asset::instantiate_tilemap_assets_system.before(TileMapSystem::UpdateChunks),
+            );
+
+        #[cfg(feature = "aseprite")]
+        app.init_asset::<crate::aseprite::AsepriteTileMap>()
+            .init_asset_loader::<crate::aseprite::AsepriteTileMapLoader>()
+            .add_systems(
+                Update,
+                crate::aseprite::instantiate_aseprite_tilemaps_system.before(TileMapSystem::UpdateChunks),
+            );
+
+        #[cfg(feature = "ldtk")]
+        app.init_asset::<crate::ldtk::LdtkProject>()
+            .init_asset_loader::<crate::ldtk::LdtkProjectLoader>()
+            .add_event::<crate::ldtk::LdtkEntityEvent>()
+            .add_systems(
+                Update,
+                crate::ldtk::instantiate_ldtk_projects_system.before(TileMapSystem::UpdateChunks),
+            );
+
+        #[cfg(feature = "tiled")]
+        app.init_asset::<crate::tiled::TiledMap>()
+            .init_asset_loader::<crate::tiled::TiledMapLoader>()
+            .add_systems(
+                Update,
+                crate::tiled::instantiate_tiled_maps_system.before(TileMapSystem::UpdateChunks),
+            );
+
+        app.add_plugins(ExtractResourcePlugin::<TileMapAmbient>::default());
 
         load_internal_asset!(app, TILEMAP_SHADER_HANDLE, "render/tilemap.wgsl", Shader::from_wgsl);
 
         app.add_systems(
             PostUpdate,
-            check_visibility::<WithTileMap>.in_set(VisibilitySystems::CheckVisibility),
+            (
+                crate::culling::update_tilemap_aabbs_system.in_set(VisibilitySystems::CalculateBounds),
+                check_visibility::<WithTileMap>.in_set(VisibilitySystems::CheckVisibility),
+            ),
         );
 
+        app.add_systems(
+            PostUpdate,
+            crate::culling::update_chunk_aabbs_system
+                .in_set(TileMapSystem::UpdateChunkAabbs)
+                .after(TransformSystem::TransformPropagate),
+        );
+
+        let render_stats_channel = TileMapRenderStatsChannel::default();
+
+        let diagnostics_channel = TilemapDiagnosticsChannel::default();
+
+        app.insert_resource(render_stats_channel.clone())
+            .insert_resource(diagnostics_channel.clone())
+            .add_systems(
+                First,
+                (
+                    render::stats::receive_render_stats_system,
+                    crate::diagnostics::receive_diagnostics_system,
+                ),
+            );
+
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
+                .insert_resource(render_stats_channel)
+                .insert_resource(diagnostics_channel)
                 .init_resource::<ImageBindGroups>()
                 .init_resource::<SpecializedRenderPipelines<TilemapPipeline>>()
                 .init_resource::<TilemapMeta>()
                 .init_resource::<ExtractedTilemaps>()
+                .init_resource::<ExtractedTilemapLights>()
                 .init_resource::<TilemapAssetEvents>()
+                .init_resource::<ExtractedMaterialTilemaps>()
                 .add_render_command::<Transparent2d, DrawTilemap>()
+                .add_render_command::<Opaque2d, DrawTilemap>()
                 .add_systems(
                     ExtractSchedule,
                     (
                         render::extract::extract_tilemaps.in_set(TileMapSystem::ExtractTilemaps),
-                        render::extract::extract_tilemap_events,
+                        render::extract::extract_tilemap_events.in_set(TileMapSystem::ExtractTilemapEvents),
+                        render::extract::extract_tilemap_lights.in_set(TileMapSystem::ExtractTilemaps),
                     ),
                 )
-                .add_systems(Render, render::queue::queue_tilemaps.in_set(RenderSet::Queue));
+                .add_systems(
+                    Render,
+                    (
+                        render::queue::queue_tilemaps
+                            .in_set(TileMapSystem::QueueTilemaps)
+                            .in_set(RenderSet::Queue),
+                        render::prepare::prepare_tilemaps
+                            .in_set(TileMapSystem::PrepareTilemaps)
+                            .in_set(RenderSet::PrepareBindGroups),
+                    ),
+                );
         };
     }
 
     fn finish(&self, app: &mut App) {
+        let shader = app.world().get_resource::<TileMapShader>().cloned().unwrap_or_default();
+
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
-            render_app.init_resource::<TilemapPipeline>();
+            render_app.insert_resource(shader).init_resource::<TilemapPipeline>();
         }
     }
 }
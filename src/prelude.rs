@@ -1,2 +1,42 @@
-pub use crate::plugin::SimpleTileMapPlugin;
-pub use crate::tilemap::{Tile, TileMap};
+pub use crate::ambient::{TileMapAmbient, TileMapAmbientGroup, TileMapColor};
+pub use crate::animation::TileAnimation;
+#[cfg(feature = "aseprite")]
+pub use crate::aseprite::{AsepriteTileMap, AsepriteTileMapRoot};
+#[cfg(feature = "ron")]
+pub use crate::asset::{TileMapAsset, TileMapAssetRoot};
+pub use crate::camera::{TileCamera, TileCameraInput, TileCameraPlugin};
+pub use crate::colliders::{TileCollider, TileMapColliders};
+pub use crate::collision::{
+    CollisionDirection, CollisionSweep, TileCollision, TileCollisionMap, TileCollisionShape, TileCollisionShapes,
+};
+pub use crate::culling::TileMapCulling;
+pub use crate::cursor::TileCursor;
+#[cfg(feature = "debug")]
+pub use crate::debug::{TileMapDebugPlugin, TileMapDebugSettings};
+pub use crate::diagnostics::{TilemapDiagnostics, TilemapDiagnosticsPlugin};
+pub use crate::dirty::{DirtyCause, DirtyChunk, DirtyChunks};
+pub use crate::extension::{
+    ChunkDataExtension, ChunkDataExtensionAppExt, ChunkExtensionData, ChunkLifecycle, ChunkLifecycleEvent,
+};
+pub use crate::heightmap::HeightBand;
+pub use crate::image_tiles::ColorToTileMapping;
+pub use crate::layer::{TileBlendMode, TileMapLayer, TileMapLayerProperty};
+#[cfg(feature = "ldtk")]
+pub use crate::ldtk::{LdtkEntityEvent, LdtkLevelInstance, LdtkProject, LdtkProjectRoot};
+pub use crate::lighting::{TileMapNormalMap, TileMapPointLight};
+pub use crate::minimap::{TileMapMinimap, TileMapMinimapCamera};
+pub use crate::nine_patch::NinePatchTiles;
+pub use crate::occluders::{TileMapOccluders, TileOccluder};
+pub use crate::plugin::{SimpleTileMapPlugin, TileMapSystem};
+pub use crate::projection::{HexCoordinates, HexOrientation, TileMapProjection};
+pub use crate::provider::{ChunkProvider, TileMapChunkProvider};
+pub use crate::render::material::{TileMapMaterial, TileMapMaterialHandle, TileMapMaterialPlugin};
+pub use crate::render::stats::TileMapRenderStats;
+pub use crate::render::TileMapShader;
+pub use crate::streaming::{StreamingAnchor, TileMapStreaming, TileMapStreamingPlugin, TileMapStreamingSettings};
+pub use crate::tile_data::TileData;
+#[cfg(feature = "tiled")]
+pub use crate::tiled::{TiledMap, TiledMapRoot};
+pub use crate::tilemap::{Tile, TileMap, TileShape};
+pub use crate::tileset_array::TilesetArray;
+pub use crate::unloading::{ChunkUnloadEvent, ChunkUnloading};
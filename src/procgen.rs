@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+
+use crate::{Tile, TileMap};
+
+/// Cell of a generated grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Floor,
+    Wall,
+}
+
+/// Grid of cells produced by a generator, with (0, 0) in the bottom left corner
+#[derive(Clone, Debug)]
+pub struct CellGrid {
+    size: UVec2,
+    cells: Vec<Cell>,
+}
+
+impl CellGrid {
+    /// Create a grid where every cell is `cell`
+    pub fn new(size: UVec2, cell: Cell) -> Self {
+        Self {
+            size,
+            cells: vec![cell; (size.x * size.y) as usize],
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Get the cell at the specified position. Positions outside the grid are walls.
+    #[inline]
+    pub fn get(&self, pos: IVec2) -> Cell {
+        self.index(pos).map_or(Cell::Wall, |i| self.cells[i])
+    }
+
+    /// Set the cell at the specified position. Positions outside the grid are ignored.
+    #[inline]
+    pub fn set(&mut self, pos: IVec2, cell: Cell) {
+        if let Some(i) = self.index(pos) {
+            self.cells[i] = cell;
+        }
+    }
+
+    /// Iterate over all cells and their positions
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, Cell)> + '_ {
+        let width = self.size.x as usize;
+
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| (IVec2::new((i % width) as i32, (i / width) as i32), *cell))
+    }
+
+    /// Write the grid to a layer of a tilemap, with the grid's (0, 0) placed at `origin`.
+    ///
+    /// `choose_tile` picks the tile for each cell, given its position within the grid.
+    /// Cells for which it returns `None` are left untouched.
+    /// All tiles are queued in a single bulk write.
+    pub fn write_to_tilemap(
+        &self,
+        tilemap: &mut TileMap,
+        origin: IVec2,
+        layer: i32,
+        mut choose_tile: impl FnMut(IVec2, Cell) -> Option<Tile>,
+    ) {
+        tilemap.set_tiles(self.iter().filter_map(|(pos, cell)| {
+            let tile = choose_tile(pos, cell)?;
+
+            Some(((origin + pos).extend(layer), Some(tile)))
+        }));
+    }
+
+    #[inline]
+    fn contains(&self, pos: IVec2) -> bool {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as u32) < self.size.x && (pos.y as u32) < self.size.y
+    }
+
+    #[inline]
+    fn index(&self, pos: IVec2) -> Option<usize> {
+        self.contains(pos)
+            .then(|| (pos.x as u32 + pos.y as u32 * self.size.x) as usize)
+    }
+}
+
+/// Small deterministic random number generator (SplitMix64), so that the same seed always
+/// produces the same map on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in the range `0..n`
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next_u64() % n.max(1) as u64) as u32
+    }
+
+    /// Random number in the range `min..=max`
+    fn range(&mut self, min: i32, max: i32) -> i32 {
+        min + self.below((max - min + 1) as u32) as i32
+    }
+
+    /// Random number in the range `0.0..1.0`
+    fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+const CARDINAL_DIRECTIONS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// Generate a perfect maze using the recursive backtracker algorithm.
+///
+/// Passages are one cell wide, with walls between them, so maze cells are located at odd coordinates.
+/// Even sizes are reduced by one, so that the maze is surrounded by walls.
+pub fn maze(size: UVec2, seed: u64) -> CellGrid {
+    let mut rng = Rng::new(seed);
+    let mut grid = CellGrid::new(size, Cell::Wall);
+
+    if size.x < 3 || size.y < 3 {
+        return grid;
+    }
+
+    let start = IVec2::ONE;
+    grid.set(start, Cell::Floor);
+
+    let mut stack = vec![start];
+
+    while let Some(&current) = stack.last() {
+        let unvisited: Vec<IVec2> = CARDINAL_DIRECTIONS
+            .iter()
+            .map(|direction| current + *direction * 2)
+            .filter(|next| {
+                next.x > 0
+                    && next.y > 0
+                    && next.x < size.x as i32 - 1
+                    && next.y < size.y as i32 - 1
+                    && grid.get(*next) == Cell::Wall
+            })
+            .collect();
+
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let next = unvisited[rng.below(unvisited.len() as u32) as usize];
+
+        // Carve out the wall between the current and next cell
+        grid.set((current + next) / 2, Cell::Floor);
+        grid.set(next, Cell::Floor);
+
+        stack.push(next);
+    }
+
+    grid
+}
+
+/// Settings for [`bsp_dungeon`]
+#[derive(Clone, Debug)]
+pub struct BspSettings {
+    /// Minimum width and height of a room, not including walls
+    pub min_room_size: u32,
+    /// Maximum number of times the map is split. The number of rooms is at most 2^`max_depth`.
+    pub max_depth: u32,
+}
+
+impl Default for BspSettings {
+    fn default() -> Self {
+        Self {
+            min_room_size: 4,
+            max_depth: 5,
+        }
+    }
+}
+
+/// Generate rooms connected by corridors, by recursively splitting the map using binary space partitioning
+pub fn bsp_dungeon(size: UVec2, settings: &BspSettings, seed: u64) -> CellGrid {
+    let mut rng = Rng::new(seed);
+    let mut grid = CellGrid::new(size, Cell::Wall);
+
+    if size.x < 3 || size.y < 3 {
+        return grid;
+    }
+
+    // Keep a one cell wall around the edge of the map
+    let area = IRect::new(1, 1, size.x as i32 - 2, size.y as i32 - 2);
+
+    bsp_split(&mut grid, &mut rng, settings, area, 0);
+
+    grid
+}
+
+/// Split an area (inclusive) and carve rooms into it, returning the center of a room within it
+fn bsp_split(grid: &mut CellGrid, rng: &mut Rng, settings: &BspSettings, area: IRect, depth: u32) -> IVec2 {
+    let min_size = settings.min_room_size.max(1) as i32;
+
+    // Each half needs room for a room and a wall between them
+    let min_split_size = min_size * 2 + 1;
+    let size = area.size() + IVec2::ONE;
+
+    let can_split_x = size.x >= min_split_size;
+    let can_split_y = size.y >= min_split_size;
+
+    if depth >= settings.max_depth || !(can_split_x || can_split_y) {
+        // Carve a randomly sized room within the area
+        let room_size = IVec2::new(
+            rng.range(min_size.min(size.x), size.x),
+            rng.range(min_size.min(size.y), size.y),
+        );
+
+        let min = area.min + IVec2::new(rng.range(0, size.x - room_size.x), rng.range(0, size.y - room_size.y));
+        let room = IRect::from_corners(min, min + room_size - IVec2::ONE);
+
+        for y in room.min.y..=room.max.y {
+            for x in room.min.x..=room.max.x {
+                grid.set(IVec2::new(x, y), Cell::Floor);
+            }
+        }
+
+        return room.center();
+    }
+
+    // Split along the longest axis that can be split
+    let split_x = can_split_x && (!can_split_y || size.x >= size.y);
+
+    let (a, b) = if split_x {
+        let split = rng.range(area.min.x + min_size, area.max.x - min_size);
+
+        (
+            IRect::new(area.min.x, area.min.y, split - 1, area.max.y),
+            IRect::new(split + 1, area.min.y, area.max.x, area.max.y),
+        )
+    } else {
+        let split = rng.range(area.min.y + min_size, area.max.y - min_size);
+
+        (
+            IRect::new(area.min.x, area.min.y, area.max.x, split - 1),
+            IRect::new(area.min.x, split + 1, area.max.x, area.max.y),
+        )
+    };
+
+    let a_center = bsp_split(grid, rng, settings, a, depth + 1);
+    let b_center = bsp_split(grid, rng, settings, b, depth + 1);
+
+    // Connect the two halves with an L-shaped corridor
+    let corner = if rng.below(2) == 0 {
+        IVec2::new(b_center.x, a_center.y)
+    } else {
+        IVec2::new(a_center.x, b_center.y)
+    };
+
+    carve_line(grid, a_center, corner);
+    carve_line(grid, corner, b_center);
+
+    a_center
+}
+
+/// Carve a horizontal or vertical line of floor between two positions (inclusive)
+fn carve_line(grid: &mut CellGrid, from: IVec2, to: IVec2) {
+    let min = from.min(to);
+    let max = from.max(to);
+
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            grid.set(IVec2::new(x, y), Cell::Floor);
+        }
+    }
+}
+
+/// Settings for [`cellular_caves`]
+#[derive(Clone, Debug)]
+pub struct CaveSettings {
+    /// Probability of each cell initially being a wall
+    pub wall_probability: f32,
+    /// Number of smoothing iterations
+    pub iterations: u32,
+    /// A cell becomes a wall if at least this many of its 8 neighbors are walls
+    pub wall_threshold: u32,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            wall_probability: 0.45,
+            iterations: 5,
+            wall_threshold: 5,
+        }
+    }
+}
+
+/// Generate organic caves using a cellular automaton
+pub fn cellular_caves(size: UVec2, settings: &CaveSettings, seed: u64) -> CellGrid {
+    let mut rng = Rng::new(seed);
+    let mut grid = CellGrid::new(size, Cell::Wall);
+
+    // Randomly fill the interior, keeping walls along the edges
+    for y in 1..size.y as i32 - 1 {
+        for x in 1..size.x as i32 - 1 {
+            if rng.unit() >= settings.wall_probability {
+                grid.set(IVec2::new(x, y), Cell::Floor);
+            }
+        }
+    }
+
+    for _ in 0..settings.iterations {
+        let previous = grid.clone();
+
+        for (pos, _) in previous.iter() {
+            let walls = (-1..=1)
+                .flat_map(|y| (-1..=1).map(move |x| IVec2::new(x, y)))
+                .filter(|offset| *offset != IVec2::ZERO && previous.get(pos + *offset) == Cell::Wall)
+                .count() as u32;
+
+            let cell = if walls >= settings.wall_threshold {
+                Cell::Wall
+            } else {
+                Cell::Floor
+            };
+
+            grid.set(pos, cell);
+        }
+    }
+
+    grid
+}
@@ -0,0 +1,226 @@
+use bevy::{
+    math::{DMat2, DVec2},
+    prelude::*,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::tilemap::CHUNK_SIZE;
+
+/// How tile positions are laid out in the tilemap's local space
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum TileMapProjection {
+    /// Square grid, spaced by the size of the tileset's sprites
+    #[default]
+    Orthogonal,
+    /// Diamond isometric grid. Increasing x moves up and to the right, and increasing y up and to the left.
+    ///
+    /// Tiles further back are drawn first, so sprites taller than the diamond overlap the tiles behind them.
+    Isometric {
+        /// Width and height of the diamond, in pixels
+        tile_size: Vec2,
+    },
+    /// Hexagonal grid
+    Hexagonal {
+        /// Width and height of a hexagon, in pixels
+        tile_size: Vec2,
+        orientation: HexOrientation,
+        coordinates: HexCoordinates,
+    },
+}
+
+/// Orientation of the hexagons of a [`TileMapProjection::Hexagonal`] grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum HexOrientation {
+    /// Hexagons have a corner at the top, and are laid out in rows
+    #[default]
+    PointyTop,
+    /// Hexagons have an edge at the top, and are laid out in columns
+    FlatTop,
+}
+
+/// How tile positions map to hexagons in a [`TileMapProjection::Hexagonal`] grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum HexCoordinates {
+    /// Offset coordinates, where every odd row (pointy top) is shifted right by half a hexagon,
+    /// or every odd column (flat top) is shifted up by half a hexagon. Rectangular maps stay rectangular.
+    #[default]
+    Offset,
+    /// Axial coordinates, where x and y follow two of the three hexagonal axes. Neighbors are always
+    /// at the same relative positions, which simplifies distance and pathfinding calculations.
+    Axial,
+}
+
+impl TileMapProjection {
+    /// Get the matrix transforming tile positions into local positions, given the size of the tileset's sprites.
+    ///
+    /// For hexagonal grids with offset coordinates, this is only exact for even positions.
+    /// Use [`TileMapProjection::tile_to_local`] for individual tiles.
+    pub fn grid_matrix(&self, sprite_size: Vec2) -> Mat2 {
+        match *self {
+            Self::Orthogonal => Mat2::from_diagonal(sprite_size),
+            Self::Isometric { tile_size } => {
+                let half_size = tile_size / 2.0;
+
+                Mat2::from_cols(vec2(half_size.x, half_size.y), vec2(-half_size.x, half_size.y))
+            }
+            Self::Hexagonal {
+                tile_size,
+                orientation,
+                coordinates: HexCoordinates::Offset,
+            } => match orientation {
+                HexOrientation::PointyTop => Mat2::from_diagonal(vec2(tile_size.x, tile_size.y * 0.75)),
+                HexOrientation::FlatTop => Mat2::from_diagonal(vec2(tile_size.x * 0.75, tile_size.y)),
+            },
+            Self::Hexagonal {
+                tile_size,
+                orientation,
+                coordinates: HexCoordinates::Axial,
+            } => hex_axial_matrix(tile_size, orientation),
+        }
+    }
+
+    /// Get the local position of the center of a tile
+    pub fn tile_to_local(&self, tile_pos: IVec2, sprite_size: Vec2) -> Vec2 {
+        self.grid_matrix(sprite_size) * tile_pos.as_vec2() + self.tile_offset(tile_pos)
+    }
+
+    /// Get the position of the tile containing a local position
+    pub fn local_to_tile(&self, local_pos: Vec2, sprite_size: Vec2) -> IVec2 {
+        match *self {
+            Self::Hexagonal {
+                tile_size,
+                orientation,
+                coordinates,
+            } => {
+                let axial = hex_round(hex_axial_matrix(tile_size, orientation).inverse() * local_pos);
+
+                match coordinates {
+                    HexCoordinates::Axial => axial,
+                    HexCoordinates::Offset => axial_to_offset(axial, orientation),
+                }
+            }
+            _ => (self.grid_matrix(sprite_size).inverse() * local_pos).round().as_ivec2(),
+        }
+    }
+
+    /// Get the world position of the center of a hexagon.
+    /// Returns `None` if this is not a hexagonal projection.
+    pub fn hex_to_world(&self, hex: IVec2, transform: &GlobalTransform) -> Option<Vec3> {
+        let Self::Hexagonal { .. } = self else {
+            return None;
+        };
+
+        Some(transform.transform_point(self.tile_to_local(hex, Vec2::ZERO).extend(0.0)))
+    }
+
+    /// Get the hexagon containing a world position.
+    /// Returns `None` if this is not a hexagonal projection.
+    pub fn world_to_hex(&self, world_pos: Vec3, transform: &GlobalTransform) -> Option<IVec2> {
+        let Self::Hexagonal { .. } = self else {
+            return None;
+        };
+
+        let local_pos = transform.affine().inverse().transform_point3(world_pos);
+
+        Some(self.local_to_tile(local_pos.truncate(), Vec2::ZERO))
+    }
+
+    /// Get the offset of a tile from its position given by the grid matrix,
+    /// for odd rows or columns of hexagonal grids with offset coordinates
+    #[inline]
+    pub(crate) fn tile_offset(&self, tile_pos: IVec2) -> Vec2 {
+        match *self {
+            Self::Hexagonal {
+                tile_size,
+                orientation: HexOrientation::PointyTop,
+                coordinates: HexCoordinates::Offset,
+            } if tile_pos.y & 1 != 0 => vec2(tile_size.x / 2.0, 0.0),
+            Self::Hexagonal {
+                tile_size,
+                orientation: HexOrientation::FlatTop,
+                coordinates: HexCoordinates::Offset,
+            } if tile_pos.x & 1 != 0 => vec2(0.0, tile_size.y / 2.0),
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// Whether tiles must be drawn back to front, because they may overlap tiles behind them
+    #[inline]
+    pub(crate) fn is_depth_sorted(&self) -> bool {
+        matches!(self, Self::Isometric { .. })
+    }
+
+    /// Get the local bounding rectangle of the chunk with the specified origin, in double precision
+    pub(crate) fn chunk_bounds(&self, origin: IVec2, sprite_size: Vec2) -> (DVec2, DVec2) {
+        let grid_matrix: DMat2 = self.grid_matrix(sprite_size).as_dmat2();
+
+        // Tiles are centered on their position, so the chunk starts half a tile before its origin
+        let min = origin.as_dvec2() - 0.5;
+        let max = min + CHUNK_SIZE.as_dvec2();
+
+        let (min, max) = [
+            DVec2::new(min.x, min.y),
+            DVec2::new(max.x, min.y),
+            DVec2::new(max.x, max.y),
+            DVec2::new(min.x, max.y),
+        ]
+        .map(|corner| grid_matrix * corner)
+        .into_iter()
+        .fold((DVec2::MAX, DVec2::MIN), |(min, max), corner| {
+            (min.min(corner), max.max(corner))
+        });
+
+        // Hexagons extend past the grid cells, and odd rows or columns may be offset
+        match *self {
+            Self::Hexagonal { tile_size, .. } => {
+                let margin = tile_size.as_dvec2() / 2.0;
+
+                (min - margin, max + margin)
+            }
+            _ => (min, max),
+        }
+    }
+}
+
+/// Get the matrix transforming axial hexagon coordinates into local positions
+fn hex_axial_matrix(tile_size: Vec2, orientation: HexOrientation) -> Mat2 {
+    match orientation {
+        HexOrientation::PointyTop => {
+            Mat2::from_cols(vec2(tile_size.x, 0.0), vec2(tile_size.x / 2.0, tile_size.y * 0.75))
+        }
+        HexOrientation::FlatTop => Mat2::from_cols(vec2(tile_size.x * 0.75, tile_size.y / 2.0), vec2(0.0, tile_size.y)),
+    }
+}
+
+/// Round fractional axial coordinates to the hexagon containing them
+fn hex_round(axial: Vec2) -> IVec2 {
+    let cube = axial.extend(-axial.x - axial.y);
+    let rounded = cube.round();
+    let diff = (rounded - cube).abs();
+
+    // Rounding all components may not result in valid cube coordinates,
+    // so recalculate the component that was rounded the most from the other two
+    if diff.x > diff.y && diff.x > diff.z {
+        ivec2((-rounded.y - rounded.z) as i32, rounded.y as i32)
+    } else if diff.y > diff.z {
+        ivec2(rounded.x as i32, (-rounded.x - rounded.z) as i32)
+    } else {
+        rounded.truncate().as_ivec2()
+    }
+}
+
+/// Convert axial hexagon coordinates to offset coordinates
+fn axial_to_offset(axial: IVec2, orientation: HexOrientation) -> IVec2 {
+    match orientation {
+        HexOrientation::PointyTop => ivec2(axial.x + (axial.y - (axial.y & 1)) / 2, axial.y),
+        HexOrientation::FlatTop => ivec2(axial.x, axial.y + (axial.x - (axial.x & 1)) / 2),
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{culling::camera_chunk_positions, Chunk, TileMap};
+
+/// Generates chunks on demand, such as for infinite procedurally generated worlds.
+///
+/// Use with a [`TileMapChunkProvider`] component on the tilemap entity.
+pub trait ChunkProvider: Send + Sync + 'static {
+    /// Generate the chunk at the specified chunk position, or `None` if there should be no chunk there.
+    ///
+    /// Create the chunk with [`Chunk::from_chunk_pos`] and fill it using [`Chunk::set_tiles`].
+    fn generate(&self, chunk_pos: IVec3) -> Option<Chunk>;
+}
+
+impl<F: Fn(IVec3) -> Option<Chunk> + Send + Sync + 'static> ChunkProvider for F {
+    fn generate(&self, chunk_pos: IVec3) -> Option<Chunk> {
+        self(chunk_pos)
+    }
+}
+
+/// Generate missing chunks of a tilemap around 2D cameras using a [`ChunkProvider`]
+#[derive(Component)]
+pub struct TileMapChunkProvider {
+    provider: Box<dyn ChunkProvider>,
+    /// Layers to generate chunks for
+    pub layers: Vec<i32>,
+    /// Number of chunks around each camera to generate, in each direction
+    pub distance: u32,
+    /// Maximum number of chunks generated per frame
+    pub budget: usize,
+    /// Chunk positions for which the provider returned no chunk, so they are not requested again
+    /// until they have been out of range.
+    empty: HashSet<IVec3>,
+}
+
+impl TileMapChunkProvider {
+    pub fn new(provider: impl ChunkProvider, layers: impl Into<Vec<i32>>) -> Self {
+        Self {
+            provider: Box::new(provider),
+            layers: layers.into(),
+            distance: 2,
+            budget: 4,
+            empty: HashSet::default(),
+        }
+    }
+
+    pub fn with_distance(mut self, distance: u32) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = budget;
+        self
+    }
+}
+
+/// Generate missing chunks near cameras
+pub(crate) fn provide_chunks_system(
+    mut tilemap_query: Query<(&mut TileMap, &mut TileMapChunkProvider, &GlobalTransform)>,
+    camera_query: Query<&GlobalTransform, With<Camera2d>>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (mut tilemap, mut chunk_provider, transform) in tilemap_query.iter_mut() {
+        let Some(camera_chunks) = camera_chunk_positions(&tilemap, transform, &camera_query, &texture_atlases) else {
+            continue;
+        };
+
+        let distance = chunk_provider.distance as i32;
+
+        let in_range = |chunk_pos: IVec3| {
+            camera_chunks
+                .iter()
+                .any(|camera_chunk| (chunk_pos.truncate() - *camera_chunk).abs().max_element() <= distance)
+        };
+
+        // Forget empty chunks that have gone out of range, so they don't accumulate
+        chunk_provider.empty.retain(|chunk_pos| in_range(*chunk_pos));
+
+        // Missing chunks in range of any camera, nearest first
+        let mut missing: Vec<(i32, IVec3)> = Vec::new();
+        let mut seen: HashSet<IVec3> = HashSet::default();
+
+        for camera_chunk in camera_chunks.iter() {
+            for y in -distance..=distance {
+                for x in -distance..=distance {
+                    for layer in chunk_provider.layers.iter() {
+                        let chunk_pos = (*camera_chunk + IVec2::new(x, y)).extend(*layer);
+
+                        if tilemap.chunks.contains_key(&chunk_pos)
+                            || chunk_provider.empty.contains(&chunk_pos)
+                            || !seen.insert(chunk_pos)
+                        {
+                            continue;
+                        }
+
+                        missing.push((x.abs().max(y.abs()), chunk_pos));
+                    }
+                }
+            }
+        }
+
+        missing.sort_by_key(|(distance, _)| *distance);
+
+        let budget = chunk_provider.budget;
+
+        for (_, chunk_pos) in missing.into_iter().take(budget) {
+            match chunk_provider.provider.generate(chunk_pos) {
+                Some(chunk) => tilemap.insert_chunk(chunk),
+                None => {
+                    chunk_provider.empty.insert(chunk_pos);
+                }
+            }
+        }
+    }
+}
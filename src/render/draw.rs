@@ -59,7 +59,10 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTilemapTextureBindGro
 
         pass.set_bind_group(
             I,
-            image_bind_groups.values.get(&tilemap_batch.image_handle_id).unwrap(),
+            image_bind_groups
+                .values
+                .get(&(tilemap_batch.image_handle_id, tilemap_batch.normal_map_id))
+                .unwrap(),
             &[],
         );
 
@@ -85,9 +88,12 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTilemapTileGpuDataBin
         };
 
         let tilemap_batch = query_batch.get(entity).unwrap();
-        let chunk_meta = tilemap_meta.into_inner().chunks.get(&tilemap_batch.chunk_key).unwrap();
 
-        pass.set_bind_group(I, chunk_meta.tilemap_gpu_data_bind_group.as_ref().unwrap(), &[0]);
+        pass.set_bind_group(
+            I,
+            tilemap_meta.into_inner().tilemap_gpu_data_bind_group.as_ref().unwrap(),
+            &[tilemap_batch.gpu_data_offset],
+        );
 
         RenderCommandResult::Success
     }
@@ -111,11 +117,13 @@ impl<P: PhaseItem> RenderCommand<P> for SetVertexBuffer {
         };
 
         let tilemap_batch = query_batch.get(entity).unwrap();
-        let chunk_meta = tilemap_meta.into_inner().chunks.get(&tilemap_batch.chunk_key).unwrap();
+        let batch_meta = tilemap_meta.into_inner().batches.get(&tilemap_batch.batch_key).unwrap();
+
+        let Some(buffer) = batch_meta.instances.buffer() else {
+            return RenderCommandResult::Skip;
+        };
 
-        if let Some(buffer) = chunk_meta.vertices.buffer() {
-            pass.set_vertex_buffer(0, buffer.slice(..));
-        }
+        pass.set_vertex_buffer(0, buffer.slice(..));
 
         RenderCommandResult::Success
     }
@@ -138,7 +146,8 @@ impl<P: PhaseItem> RenderCommand<P> for DrawTilemapBatch {
             return RenderCommandResult::Skip;
         };
 
-        pass.draw(batch.range.clone(), 0..1);
+        // Each instance is a quad of two triangles, generated in the vertex shader
+        pass.draw(0..6, batch.range.clone());
 
         RenderCommandResult::Success
     }
@@ -1,19 +1,23 @@
-use std::ops::Mul;
-
 use bevy::asset::{AssetEvent, Assets};
 use bevy::ecs::prelude::*;
 use bevy::image::Image;
-use bevy::math::uvec2;
+use bevy::math::{uvec2, DVec2};
 use bevy::prelude::*;
+use bevy::render::render_resource::TextureViewDimension;
 use bevy::render::sync_world::RenderEntity;
+use bevy::render::view::NoFrustumCulling;
 use bevy::render::Extract;
 use bevy::transform::components::GlobalTransform;
 
-#[cfg(not(target_arch = "wasm32"))]
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
-use crate::tilemap::{row_major_pos, CHUNK_HEIGHT, CHUNK_WIDTH};
-use crate::TileMap;
+use crate::ambient::TileMapColor;
+use crate::culling::{ChunkCullInfo, ChunkCullState, ChunkCullingDebugChannel, ChunkPrefetchSettings, TileMapCulling};
+use crate::dirty::DirtyChunks;
+use crate::lighting::{TileMapNormalMap, TileMapPointLight, MAX_TILEMAP_LIGHTS};
+use crate::tilemap::{calc_chunk_origin, calc_chunk_pos, row_major_pos, TILES_PER_CHUNK};
+use crate::{Chunk, TileMap};
 
 use super::*;
 
@@ -29,169 +33,442 @@ pub fn extract_tilemap_events(
     }
 }
 
+pub fn extract_tilemap_lights(
+    mut extracted_lights: ResMut<ExtractedTilemapLights>,
+    light_query: Extract<Query<(&TileMapPointLight, &GlobalTransform, &InheritedVisibility)>>,
+) {
+    extracted_lights.lights.clear();
+    extracted_lights.lights.extend(
+        light_query
+            .iter()
+            .filter(|(_, _, visibility)| visibility.get())
+            .take(MAX_TILEMAP_LIGHTS)
+            .map(|(light, transform, _)| GpuTilemapLight {
+                position: transform.translation().truncate().extend(light.height),
+                range: light.range,
+                color: light.color.to_linear().to_vec4() * light.intensity,
+            }),
+    );
+}
+
 #[allow(clippy::type_complexity)]
 pub fn extract_tilemaps(
     mut extracted_tilemaps: ResMut<ExtractedTilemaps>,
+    tilemap_meta: Res<TilemapMeta>,
     images: Extract<Res<Assets<Image>>>,
     texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
-    tilemap_query: Extract<Query<(Entity, RenderEntity, &ViewVisibility, &TileMap, &GlobalTransform)>>,
-    window_query: Extract<Query<&Window>>,
-    camera_transform_query: Extract<Query<&GlobalTransform, With<Camera2d>>>,
+    tilemap_query: Extract<
+        Query<(
+            Entity,
+            RenderEntity,
+            &ViewVisibility,
+            &TileMap,
+            &GlobalTransform,
+            Option<&TileMapColor>,
+            Option<&TileMapNormalMap>,
+            Has<NoFrustumCulling>,
+        )>,
+    >,
+    // Not only `Camera2d`s, as tilemaps without depth testing are drawn by cameras with custom passes
+    camera_query: Extract<Query<(Entity, RenderEntity, &Camera, &GlobalTransform)>>,
+    prefetch_settings: Extract<Res<ChunkPrefetchSettings>>,
+    dirty_chunks: Extract<Res<DirtyChunks>>,
+    time: Extract<Res<Time>>,
+    culling_debug_channel: Option<Res<ChunkCullingDebugChannel>>,
+    mut previous_camera_positions: Local<HashMap<Entity, Vec2>>,
 ) {
-    enum Anchor {
-        BottomLeft,
-        Center,
+    struct LayerTileset<'a> {
+        texture_atlas: &'a TextureAtlasLayout,
+        image_handle_id: AssetId<Image>,
+        texture_atlas_layout_id: AssetId<TextureAtlasLayout>,
+        texture_size: UVec2,
+        tile_size: UVec2,
+        /// Whether the image is an array texture built by a [`TilesetArray`](crate::tileset_array::TilesetArray)
+        texture_array: bool,
     }
 
-    struct Rect {
-        anchor: Anchor,
-        position: Vec2,
-        size: Vec2,
-    }
+    let delta_secs = time.delta_secs();
 
-    impl Rect {
-        #[inline]
-        pub fn is_intersecting(&self, other: &Rect) -> bool {
-            self.get_center_position().distance(other.get_center_position()) < (self.get_radius() + other.get_radius())
-        }
+    let (camera_rects, prefetch_rects) = {
+        // Visible area and position of each camera, by the render entity of its view
+        let mut camera_rects: Vec<(Entity, CullQuad, Vec2)> = Vec::with_capacity(3);
+        // Area each camera is about to see, and its position
+        let mut prefetch_rects: Vec<(CullQuad, Vec2)> = Vec::new();
 
-        #[inline]
-        pub fn get_center_position(&self) -> Vec2 {
-            match self.anchor {
-                Anchor::BottomLeft => self.position + (self.size / 2.0),
-                Anchor::Center => self.position,
-            }
-        }
-
-        #[inline]
-        pub fn get_radius(&self) -> f32 {
-            let half_size = self.size / Vec2::splat(2.0);
-            (half_size.x.powf(2.0) + half_size.y.powf(2.0)).sqrt()
-        }
-    }
+        let mut camera_positions: HashMap<Entity, Vec2> = HashMap::default();
 
-    let window = window_query.iter().next();
-    if window.is_none() {
-        return;
-    }
+        for (camera_entity, view_entity, camera, camera_transform) in camera_query.iter() {
+            if !camera.is_active {
+                continue;
+            }
 
-    let window = window.unwrap();
+            let camera_position = camera_transform.translation().truncate();
+
+            // The visible area is the corners of the view in normalized device coordinates, transformed back
+            // into the world. This accounts for the camera's projection, viewport and render target, as well as
+            // the scale and rotation of its transform.
+            let ndc_to_world =
+                |x: f32, y: f32| Some(camera.ndc_to_world(camera_transform, vec3(x, y, 0.5))?.truncate());
+
+            let (Some(bottom_left), Some(bottom_right), Some(top_right), Some(top_left)) = (
+                ndc_to_world(-1.0, -1.0),
+                ndc_to_world(1.0, -1.0),
+                ndc_to_world(1.0, 1.0),
+                ndc_to_world(-1.0, 1.0),
+            ) else {
+                // The projection is not computed yet, such as before the render target's size is known
+                continue;
+            };
 
-    let window_size = Vec2::new(window.width(), window.height());
+            let camera_rect = CullQuad::from_corners([bottom_left, bottom_right, top_right, top_left]);
 
-    let camera_rects = {
-        let mut camera_rects: Vec<Rect> = Vec::with_capacity(3);
+            camera_positions.insert(camera_entity, camera_position);
 
-        for camera_transform in camera_transform_query.iter() {
-            let (camera_scale, _, camera_translation) = camera_transform.to_scale_rotation_translation();
-            let camera_size = window_size * camera_scale.truncate();
+            // Prefetch chunks in the direction the camera is moving
+            if prefetch_settings.look_ahead > 0.0 && delta_secs > 0.0 {
+                if let Some(previous_position) = previous_camera_positions.get(&camera_entity) {
+                    let velocity = (camera_position - *previous_position) / delta_secs;
 
-            let camera_rect = Rect {
-                anchor: Anchor::Center,
-                position: camera_translation.truncate(),
-                size: camera_size,
-            };
+                    if velocity != Vec2::ZERO {
+                        prefetch_rects.push((
+                            camera_rect.translated(velocity * prefetch_settings.look_ahead),
+                            camera_position,
+                        ));
+                    }
+                }
+            }
 
-            camera_rects.push(camera_rect);
+            camera_rects.push((view_entity, camera_rect, camera_position));
         }
 
-        camera_rects
+        *previous_camera_positions = camera_positions;
+
+        (camera_rects, prefetch_rects)
     };
 
     extracted_tilemaps.tilemaps.clear();
 
-    for (original_entity, entity, view_visibility, tilemap, transform) in tilemap_query.iter() {
+    // Cull info is only recorded when debugging culling
+    let mut cull_infos: Option<Vec<ChunkCullInfo>> = culling_debug_channel.as_ref().map(|_| Vec::new());
+
+    for (original_entity, entity, view_visibility, tilemap, transform, tilemap_color, normal_map, no_culling) in
+        tilemap_query.iter()
+    {
         if !view_visibility.get() {
             continue;
         }
 
-        if let Some(texture_atlas) = texture_atlases.get(&tilemap.texture_atlas_layout) {
-            if images.contains(&tilemap.image) {
-                let (scale, _, _) = transform.to_scale_rotation_translation();
-
-                // Determine tile size in pixels from first sprite in TextureAtlas.
-                // It is assumed and mandated that all sprites in the sprite sheet are the same size.
-                let tile0_tex = texture_atlas.textures.first().unwrap();
-                let tile_size = uvec2(tile0_tex.width(), tile0_tex.height());
-
-                let chunk_pixel_size = uvec2(CHUNK_WIDTH, CHUNK_HEIGHT) * tile_size;
-                let chunk_pixel_size = chunk_pixel_size * scale.truncate().as_uvec2();
-
-                let chunk_iter = tilemap.chunks.iter();
-
-                // Exclude chunks that are not visible
-                let chunks: Vec<_> = chunk_iter
-                    .filter_map(|(_, chunk)| {
-                        let chunk_translation =
-                            (chunk.origin.truncate().as_vec2() * tile_size.as_vec2()).extend(chunk.origin.z as f32);
-                        let chunk_translation = transform.mul(chunk_translation);
-
-                        let chunk_rect = Rect {
-                            anchor: Anchor::BottomLeft,
-                            position: chunk_translation.truncate(),
-                            size: chunk_pixel_size.as_vec2(),
-                        };
-
-                        if camera_rects.iter().all(|cr| !cr.is_intersecting(&chunk_rect)) {
-                            // Chunk is outside the camera, skip it.
-                            return None;
-                        }
-
-                        Some(chunk)
-                    })
-                    .collect();
-
-                let visible_chunks: Vec<IVec3> = chunks.iter().map(|c| c.origin).collect();
-
-                #[cfg(target_arch = "wasm32")]
-                let chunk_iter = chunks.iter();
-                #[cfg(not(target_arch = "wasm32"))]
-                let chunk_iter = chunks.par_iter();
-
-                // Extract chunks
-                let chunks: Vec<ExtractedChunk> = chunk_iter
-                    .filter_map(|chunk| {
-                        #[cfg(target_arch = "wasm32")]
-                        let tile_iter = chunk.tiles.iter();
-                        #[cfg(not(target_arch = "wasm32"))]
-                        let tile_iter = chunk.tiles.par_iter();
-
-                        let tiles: Vec<ExtractedTile> = tile_iter
-                            .enumerate()
-                            .filter_map(|(i, tile)| {
-                                if let Some(tile) = tile {
-                                    let rect = texture_atlas.textures[tile.sprite_index as usize];
-
-                                    Some(ExtractedTile {
-                                        pos: chunk.origin.truncate() + row_major_pos(i),
-                                        rect,
-                                        color: tile.color.into(),
-                                        flags: tile.flags,
-                                    })
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect();
+        let no_culling = no_culling || tilemap.culling == TileMapCulling::Disabled;
+
+        // Combine chunk offsets with the transform in double precision, to remain accurate far from the origin
+        let transform_matrix = transform.compute_matrix().as_dmat4();
+
+        // Chunks to consider for rendering. This includes all existing chunks,
+        // as well as chunks that don't exist but are covered by a layer fill.
+        let mut candidate_chunks: Vec<(IVec3, Option<&Chunk>)> = tilemap
+            .chunks
+            .values()
+            .map(|chunk| (chunk.origin, Some(chunk)))
+            .collect();
+
+        for (layer, (_, fill_bounds)) in tilemap
+            .layers()
+            .filter_map(|(layer, settings)| Some((layer, settings.fill()?)))
+        {
+            let min_chunk_pos = calc_chunk_pos(fill_bounds.min.extend(layer));
+            let max_chunk_pos = calc_chunk_pos(fill_bounds.max.extend(layer));
+
+            for y in min_chunk_pos.y..=max_chunk_pos.y {
+                for x in min_chunk_pos.x..=max_chunk_pos.x {
+                    let chunk_pos = IVec3::new(x, y, layer);
+
+                    if !tilemap.chunks.contains_key(&chunk_pos) {
+                        candidate_chunks.push((calc_chunk_origin(chunk_pos), None));
+                    }
+                }
+            }
+        }
 
-                        Some(ExtractedChunk {
-                            origin: chunk.origin,
-                            tiles,
-                        })
-                    })
-                    .collect();
-
-                extracted_tilemaps.tilemaps.insert(
-                    (entity, original_entity.into()),
-                    ExtractedTilemap {
-                        transform: *transform,
-                        image_handle_id: tilemap.image.id(),
-                        tile_size,
-                        chunks,
-                        visible_chunks,
-                    },
-                );
+        // Resolve the tileset of each layer. Layers whose tileset is not loaded yet are skipped.
+        let mut layer_tilesets: HashMap<i32, LayerTileset> = HashMap::default();
+
+        for layer in candidate_chunks.iter().map(|(origin, _)| origin.z) {
+            if layer_tilesets.contains_key(&layer) {
+                continue;
             }
+
+            let (image, texture_atlas_layout) = tilemap.layer_tileset(layer);
+
+            let Some(texture_atlas) = texture_atlases.get(texture_atlas_layout) else {
+                continue;
+            };
+
+            let Some(texture) = images.get(image) else {
+                continue;
+            };
+
+            // Determine tile size in pixels from first sprite in TextureAtlas.
+            // It is assumed and mandated that all sprites in the sprite sheet are the same size.
+            let Some(tile0_tex) = texture_atlas.textures.first() else {
+                continue;
+            };
+
+            layer_tilesets.insert(
+                layer,
+                LayerTileset {
+                    texture_atlas,
+                    image_handle_id: image.id(),
+                    texture_atlas_layout_id: texture_atlas_layout.id(),
+                    texture_size: texture.size(),
+                    tile_size: uvec2(tile0_tex.width(), tile0_tex.height()),
+                    texture_array: texture
+                        .texture_view_descriptor
+                        .as_ref()
+                        .is_some_and(|descriptor| descriptor.dimension == Some(TextureViewDimension::D2Array)),
+                },
+            );
+        }
+
+        // Offset and parallax factor of each layer. Parallax depends on the camera, so it is applied per view.
+        let layer_offset = |layer: i32| -> (Vec2, Vec2) {
+            tilemap
+                .layer(layer)
+                .map(|settings| (settings.offset, settings.parallax))
+                .unwrap_or((Vec2::ZERO, Vec2::ONE))
+        };
+
+        let mut visible_chunks: Vec<IVec3> = Vec::new();
+        let mut view_visible_chunks: HashMap<Entity, Vec<IVec3>> = HashMap::default();
+
+        // Exclude chunks that are neither visible nor about to become visible
+        let chunks: Vec<_> = candidate_chunks
+            .into_iter()
+            .filter_map(|(origin, chunk)| {
+                let tileset = layer_tilesets.get(&origin.z)?;
+                let (min, max) = tilemap
+                    .projection
+                    .chunk_bounds(origin.truncate(), tileset.tile_size.as_vec2());
+
+                let margin = (tileset.tile_size.as_vec2() * tilemap.cull_margin).as_dvec2();
+                let (min, max) = (min - margin, max + margin);
+
+                let z = tilemap.layer_z(origin.z) as f64;
+                let (offset, parallax) = layer_offset(origin.z);
+
+                // Transform every corner, as the tilemap may be rotated
+                let chunk_rect = CullQuad::from_corners(
+                    [
+                        DVec2::new(min.x, min.y),
+                        DVec2::new(max.x, min.y),
+                        DVec2::new(max.x, max.y),
+                        DVec2::new(min.x, max.y),
+                    ]
+                    .map(|corner| transform_matrix.transform_point3(corner.extend(z)).as_vec3().truncate() + offset),
+                );
+
+                // Where the chunk appears to a camera, with the layer's parallax relative to that camera
+                let parallax_rect =
+                    |camera_position: Vec2| chunk_rect.translated(camera_position * (Vec2::ONE - parallax));
+
+                // Each camera culls separately, so that split-screen and multi-window views only draw what they see.
+                // Chunks of tilemaps with culling disabled or `NoFrustumCulling` are drawn by every camera.
+                let mut is_visible = false;
+                for (view_entity, _, _) in camera_rects
+                    .iter()
+                    .filter(|(_, cr, position)| no_culling || cr.intersects(&parallax_rect(*position)))
+                {
+                    view_visible_chunks.entry(*view_entity).or_default().push(origin);
+                    is_visible = true;
+                }
+
+                let state = if is_visible {
+                    ChunkCullState::Visible
+                } else if prefetch_rects
+                    .iter()
+                    .any(|(pr, position)| pr.intersects(&parallax_rect(*position)))
+                {
+                    ChunkCullState::Prefetched
+                } else {
+                    ChunkCullState::Culled
+                };
+
+                if let Some(cull_infos) = cull_infos.as_mut() {
+                    cull_infos.push(ChunkCullInfo {
+                        tilemap: original_entity,
+                        origin,
+                        // As seen by the first camera
+                        corners: parallax_rect(camera_rects.first().map_or(Vec2::ZERO, |(_, _, position)| *position))
+                            .corners,
+                        state,
+                    });
+                }
+
+                match state {
+                    ChunkCullState::Visible => visible_chunks.push(origin),
+                    ChunkCullState::Prefetched => {}
+                    // Chunk is outside the camera, and not about to come into view. Skip it.
+                    ChunkCullState::Culled => return None,
+                }
+
+                Some((origin, chunk, tileset))
+            })
+            .collect();
+
+        #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+        let chunk_iter = chunks.iter();
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        let chunk_iter = chunks.par_iter();
+
+        // Animated tiles may change without the chunk changing, so chunks of animated tilemaps are always remeshed
+        let has_animations = tilemap.has_animations();
+
+        // Extract chunks
+        let chunks: Vec<ExtractedChunk> = chunk_iter
+            .map(|(origin, chunk, tileset)| {
+                let source = ChunkSource {
+                    layers_changed_at: tilemap.layers_changed_at,
+                    color_mode: tilemap.color_mode,
+                    z: tilemap.layer_z(origin.z),
+                    z_step: tilemap.layer_z_scale,
+                    image_handle_id: tileset.image_handle_id,
+                    texture_atlas_layout_id: tileset.texture_atlas_layout_id,
+                    texture_size: tileset.texture_size,
+                    tile_size: tileset.tile_size,
+                    texture_array: tileset.texture_array,
+                    projection: tilemap.projection,
+                };
+
+                // Skip extracting tiles of chunks that have not changed since they were last meshed
+                let is_meshed = !has_animations
+                    && !dirty_chunks.contains(original_entity, calc_chunk_pos(*origin))
+                    && tilemap_meta
+                        .chunks
+                        .get(&(entity, *origin))
+                        .is_some_and(|chunk_meta| chunk_meta.source == Some(source));
+
+                let tiles = (!is_meshed).then(|| {
+                    let fill = tilemap.layer(origin.z).and_then(|layer| layer.fill());
+
+                    #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+                    let tile_iter = 0..TILES_PER_CHUNK;
+                    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+                    let tile_iter = (0..TILES_PER_CHUNK).into_par_iter();
+
+                    tile_iter
+                        .filter_map(|i| {
+                            let local_pos = row_major_pos(i);
+                            let pos = origin.truncate() + local_pos;
+
+                            // Use the fill tile for empty cells within the fill bounds
+                            let tile = chunk.and_then(|chunk| chunk.tile_at_index(i)).or_else(|| {
+                                fill.and_then(|(fill_tile, fill_bounds)| fill_bounds.contains(pos).then_some(fill_tile))
+                            })?;
+
+                            let sprite_index = tilemap.animated_sprite_index(pos.extend(origin.z), tile.sprite_index);
+
+                            // Sprite indices of tileset arrays also encode the array layer
+                            let (array_layer, sprite_index) = if tileset.texture_array {
+                                let sprite_count = tileset.texture_atlas.len() as u32;
+                                (sprite_index / sprite_count, sprite_index % sprite_count)
+                            } else {
+                                (0, sprite_index)
+                            };
+
+                            let rect = *tileset.texture_atlas.textures.get(sprite_index as usize)?;
+
+                            Some(ExtractedTile {
+                                pos: local_pos,
+                                rect,
+                                color: tile.render_color(tilemap.color_mode),
+                                flags: tile.flags,
+                                custom_size: tile.custom_size,
+                                array_layer,
+                            })
+                        })
+                        .collect()
+                });
+
+                let (offset, parallax) = layer_offset(origin.z);
+
+                ExtractedChunk {
+                    origin: *origin,
+                    offset,
+                    parallax,
+                    source,
+                    render_layers: tilemap
+                        .layer(origin.z)
+                        .and_then(|layer| layer.render_layers.clone())
+                        .unwrap_or_default(),
+                    blend_mode: tilemap
+                        .layer(origin.z)
+                        .map(|layer| layer.blend_mode)
+                        .unwrap_or_default(),
+                    opaque: tilemap.layer(origin.z).is_some_and(|layer| layer.opaque),
+                    tiles,
+                }
+            })
+            .collect();
+
+        extracted_tilemaps.tilemaps.insert(
+            (entity, original_entity.into()),
+            ExtractedTilemap {
+                transform: *transform,
+                alpha_mode: tilemap.alpha_mode,
+                depth_write_cutoff: tilemap.depth_write_cutoff,
+                depth_test: tilemap.depth_test,
+                uv_inset: tilemap.uv_inset,
+                color: tilemap_color.copied().unwrap_or_default().0.to_linear(),
+                normal_map: normal_map.map(|normal_map| (tilemap.image.id(), normal_map.0.id())),
+                chunks,
+                visible_chunks,
+                view_visible_chunks,
+            },
+        );
+    }
+
+    if let (Some(channel), Some(cull_infos)) = (culling_debug_channel, cull_infos) {
+        if let Ok(mut received) = channel.0.lock() {
+            *received = cull_infos;
         }
     }
 }
+
+/// Convex quadrilateral in world space, for testing chunks against the visible areas of cameras
+#[derive(Clone, Copy)]
+struct CullQuad {
+    corners: [Vec2; 4],
+}
+
+impl CullQuad {
+    #[inline]
+    fn from_corners(corners: [Vec2; 4]) -> Self {
+        Self { corners }
+    }
+
+    #[inline]
+    fn translated(&self, offset: Vec2) -> Self {
+        Self::from_corners(self.corners.map(|corner| corner + offset))
+    }
+
+    /// Test for intersection using the separating axis theorem.
+    /// The quads intersect unless they are separated along the normal of one of their edges.
+    fn intersects(&self, other: &CullQuad) -> bool {
+        let project = |quad: &CullQuad, axis: Vec2| {
+            quad.corners
+                .iter()
+                .map(|corner| corner.dot(axis))
+                .fold((f32::MAX, f32::MIN), |(min, max), d| (min.min(d), max.max(d)))
+        };
+
+        [self, other].into_iter().all(|quad| {
+            (0..4).all(|i| {
+                let axis = (quad.corners[(i + 1) % 4] - quad.corners[i]).perp();
+
+                let (min_a, max_a) = project(self, axis);
+                let (min_b, max_b) = project(other, axis);
+
+                min_a <= max_b && min_b <= max_a
+            })
+        })
+    }
+}
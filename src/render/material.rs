@@ -0,0 +1,318 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use bevy::core_pipeline::core_2d::Transparent2d;
+use bevy::ecs::system::lifetimeless::*;
+use bevy::ecs::system::SystemParamItem;
+use bevy::prelude::*;
+use bevy::render::render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets};
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult, SetItemPipeline, TrackedRenderPass,
+    ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::sync_world::RenderEntity;
+use bevy::render::{Extract, Render, RenderApp, RenderSet};
+use bevy::utils::HashMap;
+
+use crate::plugin::TileMapSystem;
+use crate::TileMap;
+
+use super::draw::{
+    DrawTilemap, DrawTilemapBatch, SetTilemapTextureBindGroup, SetTilemapTileGpuDataBindGroup, SetTilemapViewBindGroup,
+    SetVertexBuffer,
+};
+use super::pipeline::{TilemapPipeline, TilemapPipelineKey};
+use super::{ExtractedMaterialTilemaps, TilemapBatch};
+
+/// Custom material for tilemaps, replacing or extending the built-in tilemap shader.
+///
+/// The material's bind group is bound at group 3, after the view (0), tileset texture (1) and tilemap data (2).
+/// Custom shaders must take the same vertex inputs as the built-in `tilemap.wgsl`, and fragment shaders
+/// its `VertexOutput`, including the centroid interpolation used when the `MULTISAMPLED` shader def is set.
+/// The `TILEMAP_MATERIAL` shader def is set for all material pipelines.
+///
+/// Register the material with [`TileMapMaterialPlugin`], and add a [`TileMapMaterialHandle`] to the tilemap.
+/// Tilemaps with a material are drawn in the transparent phase, including layers that are opaque.
+pub trait TileMapMaterial: Asset + AsBindGroup + Clone + Sized {
+    /// Vertex shader. Defaults to the built-in tilemap shader.
+    fn vertex_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Fragment shader. Defaults to the built-in tilemap shader.
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Customize the pipeline descriptor, such as by adding shader defs
+    fn specialize(_descriptor: &mut RenderPipelineDescriptor, _key: TilemapPipelineKey, _data: &Self::Data) {}
+}
+
+/// Material of a tilemap
+#[derive(Component, Deref, DerefMut)]
+pub struct TileMapMaterialHandle<M: TileMapMaterial>(pub Handle<M>);
+
+impl<M: TileMapMaterial> Clone for TileMapMaterialHandle<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Adds support for tilemaps with the material `M`.
+///
+/// Requires [`SimpleTileMapPlugin`](crate::plugin::SimpleTileMapPlugin).
+pub struct TileMapMaterialPlugin<M: TileMapMaterial>(PhantomData<M>);
+
+impl<M: TileMapMaterial> Default for TileMapMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: TileMapMaterial> Plugin for TileMapMaterialPlugin<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    fn build(&self, app: &mut App) {
+        app.init_asset::<M>()
+            .add_plugins(RenderAssetPlugin::<PreparedTileMapMaterial<M>>::default());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<ExtractedTileMapMaterials<M>>()
+                .init_resource::<SpecializedRenderPipelines<TileMapMaterialPipeline<M>>>()
+                .add_render_command::<Transparent2d, DrawTileMapMaterial<M>>()
+                .add_systems(ExtractSchedule, extract_tilemap_materials::<M>)
+                .add_systems(
+                    Render,
+                    queue_tilemap_materials::<M>
+                        .after(TileMapSystem::QueueTilemaps)
+                        .in_set(RenderSet::Queue),
+                );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<TileMapMaterialPipeline<M>>();
+        }
+    }
+}
+
+/// Bind group of a material, prepared for rendering
+pub struct PreparedTileMapMaterial<M: TileMapMaterial> {
+    pub bind_group: BindGroup,
+    pub key: M::Data,
+}
+
+impl<M: TileMapMaterial> RenderAsset for PreparedTileMapMaterial<M> {
+    type SourceAsset = M;
+
+    type Param = (SRes<RenderDevice>, SRes<TileMapMaterialPipeline<M>>, M::Param);
+
+    fn prepare_asset(
+        material: Self::SourceAsset,
+        (render_device, pipeline, material_param): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
+        match material.as_bind_group(&pipeline.material_layout, render_device, material_param) {
+            Ok(prepared) => Ok(PreparedTileMapMaterial {
+                bind_group: prepared.bind_group,
+                key: prepared.data,
+            }),
+            Err(AsBindGroupError::RetryNextUpdate) => Err(PrepareAssetError::RetryNextUpdate(material)),
+            Err(other) => Err(PrepareAssetError::AsBindGroupError(other)),
+        }
+    }
+}
+
+/// Tilemap pipeline with a material bind group and custom shaders
+#[derive(Resource)]
+pub struct TileMapMaterialPipeline<M: TileMapMaterial> {
+    tilemap_pipeline: TilemapPipeline,
+    material_layout: BindGroupLayout,
+    vertex_shader: Option<Handle<Shader>>,
+    fragment_shader: Option<Handle<Shader>>,
+    marker: PhantomData<M>,
+}
+
+impl<M: TileMapMaterial> FromWorld for TileMapMaterialPipeline<M> {
+    fn from_world(world: &mut World) -> Self {
+        world.init_resource::<TilemapPipeline>();
+
+        let asset_server = world.resource::<AssetServer>();
+        let render_device = world.resource::<RenderDevice>();
+
+        let load_shader = |shader: ShaderRef| match shader {
+            ShaderRef::Default => None,
+            ShaderRef::Handle(handle) => Some(handle),
+            ShaderRef::Path(path) => Some(asset_server.load(path)),
+        };
+
+        Self {
+            tilemap_pipeline: world.resource::<TilemapPipeline>().clone(),
+            material_layout: M::bind_group_layout(render_device),
+            vertex_shader: load_shader(M::vertex_shader()),
+            fragment_shader: load_shader(M::fragment_shader()),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M: TileMapMaterial> SpecializedRenderPipeline for TileMapMaterialPipeline<M>
+where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    type Key = (TilemapPipelineKey, M::Data);
+
+    fn specialize(&self, (tilemap_key, data): Self::Key) -> RenderPipelineDescriptor {
+        let mut descriptor = self.tilemap_pipeline.specialize(tilemap_key);
+
+        descriptor.vertex.shader_defs.push("TILEMAP_MATERIAL".into());
+
+        if let Some(shader) = &self.vertex_shader {
+            descriptor.vertex.shader = shader.clone();
+        }
+
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push("TILEMAP_MATERIAL".into());
+
+            if let Some(shader) = &self.fragment_shader {
+                fragment.shader = shader.clone();
+            }
+        }
+
+        descriptor.layout.push(self.material_layout.clone());
+        descriptor.label = Some("tilemap_material_pipeline".into());
+
+        M::specialize(&mut descriptor, tilemap_key, &data);
+
+        descriptor
+    }
+}
+
+/// Materials of the extracted tilemaps, by render entity
+#[derive(Resource)]
+pub struct ExtractedTileMapMaterials<M: TileMapMaterial> {
+    materials: HashMap<Entity, AssetId<M>>,
+}
+
+impl<M: TileMapMaterial> Default for ExtractedTileMapMaterials<M> {
+    fn default() -> Self {
+        Self {
+            materials: HashMap::default(),
+        }
+    }
+}
+
+fn extract_tilemap_materials<M: TileMapMaterial>(
+    mut extracted_materials: ResMut<ExtractedTileMapMaterials<M>>,
+    mut material_tilemaps: ResMut<ExtractedMaterialTilemaps>,
+    tilemap_query: Extract<Query<(RenderEntity, &TileMapMaterialHandle<M>), With<TileMap>>>,
+) {
+    extracted_materials.materials.clear();
+
+    for (entity, material) in tilemap_query.iter() {
+        extracted_materials.materials.insert(entity, material.id());
+        material_tilemaps.entities.insert(entity);
+    }
+}
+
+/// Switch the batches of tilemaps with the material `M` over to the material pipeline.
+/// Batches are drawn with the built-in pipeline until their material is prepared.
+#[allow(clippy::too_many_arguments)]
+fn queue_tilemap_materials<M: TileMapMaterial>(
+    draw_functions: Res<DrawFunctions<Transparent2d>>,
+    material_pipeline: Res<TileMapMaterialPipeline<M>>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<TileMapMaterialPipeline<M>>>,
+    pipeline_cache: Res<PipelineCache>,
+    materials: Res<RenderAssets<PreparedTileMapMaterial<M>>>,
+    extracted_materials: Res<ExtractedTileMapMaterials<M>>,
+    batch_query: Query<&TilemapBatch>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+) where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    if extracted_materials.materials.is_empty() {
+        return;
+    }
+
+    let draw_tilemap_function = draw_functions.read().id::<DrawTilemap>();
+    let draw_material_function = draw_functions.read().id::<DrawTileMapMaterial<M>>();
+
+    for transparent_phase in transparent_render_phases.values_mut() {
+        for item in transparent_phase.items.iter_mut() {
+            if item.draw_function != draw_tilemap_function {
+                continue;
+            }
+
+            let Ok(batch) = batch_query.get(item.entity.0) else {
+                continue;
+            };
+
+            let Some(material) = extracted_materials
+                .materials
+                .get(&batch.batch_key.0)
+                .and_then(|material_id| materials.get(*material_id))
+            else {
+                continue;
+            };
+
+            item.pipeline = pipelines.specialize(
+                &pipeline_cache,
+                &material_pipeline,
+                (batch.pipeline_key, material.key.clone()),
+            );
+            item.draw_function = draw_material_function;
+        }
+    }
+}
+
+pub type DrawTileMapMaterial<M> = (
+    SetItemPipeline,
+    SetTilemapViewBindGroup<0>,
+    SetTilemapTextureBindGroup<1>,
+    SetTilemapTileGpuDataBindGroup<2>,
+    SetTileMapMaterialBindGroup<M, 3>,
+    SetVertexBuffer,
+    DrawTilemapBatch,
+);
+
+pub struct SetTileMapMaterialBindGroup<M: TileMapMaterial, const I: usize>(PhantomData<M>);
+impl<P: PhaseItem, M: TileMapMaterial, const I: usize> RenderCommand<P> for SetTileMapMaterialBindGroup<M, I> {
+    type Param = (
+        SRes<RenderAssets<PreparedTileMapMaterial<M>>>,
+        SRes<ExtractedTileMapMaterials<M>>,
+        SQuery<Read<TilemapBatch>>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = Entity;
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        entity: Option<Entity>,
+        (materials, extracted_materials, query_batch): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(entity) = entity else {
+            return RenderCommandResult::Skip;
+        };
+
+        let tilemap_batch = query_batch.get(entity).unwrap();
+
+        let Some(material) = extracted_materials
+            .into_inner()
+            .materials
+            .get(&tilemap_batch.batch_key.0)
+            .and_then(|material_id| materials.into_inner().get(*material_id))
+        else {
+            return RenderCommandResult::Skip;
+        };
+
+        pass.set_bind_group(I, &material.bind_group, &[]);
+
+        RenderCommandResult::Success
+    }
+}
@@ -2,43 +2,116 @@ use std::ops::Range;
 
 use bevy::{
     color::LinearRgba,
-    math::{IVec2, IVec3, Mat4, URect, UVec2, Vec2},
-    prelude::{AssetEvent, AssetId, Component, Entity, GlobalTransform, Handle, Image, Resource, Shader},
+    math::{IVec2, IVec3, Mat4, URect, UVec2, Vec2, Vec3, Vec4},
+    prelude::{
+        AssetEvent, AssetId, Component, Entity, GlobalTransform, Handle, Image, Resource, Shader, TextureAtlasLayout,
+    },
     render::{
-        render_resource::{BindGroup, BufferUsages, DynamicUniformBuffer, RawBufferVec, ShaderType},
+        render_resource::{BindGroup, BufferUsages, DynamicUniformBuffer, RawBufferVec, ShaderType, UniformBuffer},
         sync_world::MainEntity,
+        view::RenderLayers,
     },
-    utils::HashMap,
+    sprite::AlphaMode2d,
+    utils::{HashMap, HashSet, Instant},
 };
 use bytemuck::{Pod, Zeroable};
 
-use crate::TileFlags;
+use crate::lighting::MAX_TILEMAP_LIGHTS;
+use crate::projection::TileMapProjection;
+use crate::{TileBlendMode, TileColorMode, TileFlags};
+
+use self::pipeline::TilemapPipelineKey;
 
 pub mod draw;
 pub mod extract;
+pub mod material;
 pub mod pipeline;
+pub mod prepare;
 pub mod queue;
+pub mod stats;
 
 pub const TILEMAP_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(9765236402292098257);
 
+/// Shader used to render all tilemaps, replacing the built-in `tilemap.wgsl`.
+///
+/// Insert this resource before [`SimpleTileMapPlugin`](crate::plugin::SimpleTileMapPlugin) finishes building,
+/// as the pipeline reads it once when it is created. The shader must have the same vertex inputs, bindings
+/// and shader defs as the built-in one, which is a good starting point for a copy.
+/// It is also used by materials that don't override the vertex or fragment shader.
+///
+/// To use a different shader for individual tilemaps, use a [`TileMapMaterial`](material::TileMapMaterial).
+#[derive(Resource, Clone, Debug, Deref)]
+pub struct TileMapShader(pub Handle<Shader>);
+
+impl Default for TileMapShader {
+    fn default() -> Self {
+        Self(TILEMAP_SHADER_HANDLE)
+    }
+}
+
 pub struct ExtractedTile {
+    /// Position relative to the chunk origin
     pub pos: IVec2,
     pub rect: URect,
     pub color: LinearRgba,
     pub flags: TileFlags,
+    pub custom_size: Option<Vec2>,
+    /// Layer of the array texture, for tilemaps using a [`TilesetArray`](crate::tileset_array::TilesetArray)
+    pub array_layer: u32,
+}
+
+/// Main world state a chunk's instances are generated from, other than its tiles.
+/// If this has not changed since the chunk was last meshed, and the chunk is not in
+/// [`DirtyChunks`](crate::dirty::DirtyChunks), its instances are reused.
+#[derive(Clone, Copy, PartialEq)]
+pub struct ChunkSource {
+    /// When the tilemap's layer settings last changed
+    pub layers_changed_at: Instant,
+    pub color_mode: TileColorMode,
+    /// Local z of the chunk's layer
+    pub z: f32,
+    /// Local z distance between layers, used to scale depth bias
+    pub z_step: f32,
+    pub image_handle_id: AssetId<Image>,
+    pub texture_atlas_layout_id: AssetId<TextureAtlasLayout>,
+    pub texture_size: UVec2,
+    pub tile_size: UVec2,
+    /// Whether the image is an array texture
+    pub texture_array: bool,
+    pub projection: TileMapProjection,
 }
 
 pub struct ExtractedChunk {
     pub origin: IVec3,
-    pub tiles: Vec<ExtractedTile>,
+    /// World-space offset applied to the chunk
+    pub offset: Vec2,
+    /// Parallax factor of the chunk's layer, applied relative to each view's camera
+    pub parallax: Vec2,
+    pub source: ChunkSource,
+    pub render_layers: RenderLayers,
+    pub blend_mode: TileBlendMode,
+    /// Whether the chunk's layer is drawn in the opaque pass
+    pub opaque: bool,
+    /// Tiles of the chunk, or `None` if the chunk is unchanged since it was last meshed
+    pub tiles: Option<Vec<ExtractedTile>>,
 }
 
 pub struct ExtractedTilemap {
     pub transform: GlobalTransform,
-    pub image_handle_id: AssetId<Image>,
-    pub tile_size: UVec2,
+    pub alpha_mode: AlphaMode2d,
+    pub depth_write_cutoff: Option<f32>,
+    pub depth_test: bool,
+    /// Inset of sprite UVs from the edges of their atlas rects, in texels
+    pub uv_inset: f32,
+    /// Tint from [`TileMapColor`](crate::ambient::TileMapColor)
+    pub color: LinearRgba,
+    /// Image of the tilemap and its [`TileMapNormalMap`](crate::lighting::TileMapNormalMap)
+    pub normal_map: Option<(AssetId<Image>, AssetId<Image>)>,
     pub chunks: Vec<ExtractedChunk>,
+    /// Chunks visible to any view
     pub visible_chunks: Vec<IVec3>,
+    /// Chunks visible to each view, by view entity
+    pub view_visible_chunks: HashMap<Entity, Vec<IVec3>>,
 }
 
 #[derive(Default, Resource)]
@@ -46,64 +119,158 @@ pub struct ExtractedTilemaps {
     pub tilemaps: HashMap<(Entity, MainEntity), ExtractedTilemap>,
 }
 
+/// Lights for normal mapped tilemaps, extracted from [`TileMapPointLight`](crate::lighting::TileMapPointLight)s
+#[derive(Default, Resource)]
+pub struct ExtractedTilemapLights {
+    pub lights: Vec<GpuTilemapLight>,
+}
+
+/// Render entities of the extracted tilemaps with a [`TileMapMaterial`](material::TileMapMaterial) of any type.
+/// Materials are only applied in the transparent phase, so these tilemaps are drawn there even if opaque.
+#[derive(Default, Resource)]
+pub struct ExtractedMaterialTilemaps {
+    pub entities: HashSet<Entity>,
+}
+
 #[derive(Default, Resource)]
 pub struct TilemapAssetEvents {
     pub images: Vec<AssetEvent<Image>>,
 }
 
+/// A tile, drawn as one instance of a quad generated in the vertex shader
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
-struct TilemapVertex {
+struct TilemapInstance {
+    /// Center of the quad, relative to the chunk or batch origin
     pub position: [f32; 3],
-    pub uv: [f32; 2],
-    pub tile_uv: [f32; 2],
+    pub size: [f32; 2],
+    /// Min and max UV of the sprite's atlas rect in the texture.
+    /// The shader clamps samples to within the rect, inset by [`TilemapGpuData::uv_inset`].
+    pub uv_rect: [f32; 4],
     pub color: [f32; 4],
+    pub flags: u32,
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Default, Pod, Zeroable, ShaderType)]
+impl TilemapInstance {
+    // Must match the flags in tilemap.wgsl
+    const FLIP_X: u32 = 1 << 0;
+    const FLIP_Y: u32 = 1 << 1;
+    const EMISSIVE: u32 = 1 << 2;
+    /// The [`TileShape`](crate::TileShape) is stored in bits 8-11 of the flags
+    const SHAPE_SHIFT: u32 = 8;
+    /// The array layer is stored in the upper 16 bits of the flags
+    const ARRAY_LAYER_SHIFT: u32 = 16;
+}
+
+#[derive(Copy, Clone, Default, ShaderType)]
 pub struct TilemapGpuData {
+    /// Transform from chunk-local coordinates to world coordinates relative to the view translation
     pub transform: Mat4,
     pub tile_size: Vec2,
     pub texture_size: Vec2,
+    pub alpha_cutoff: f32,
+    /// Inset of sampled UVs from the edges of each sprite's atlas rect, in texels
+    pub uv_inset: f32,
+    pub ambient: Vec4,
+    /// Tint of the whole tilemap
+    pub color: Vec4,
+}
+
+#[derive(Copy, Clone, Default, ShaderType)]
+pub struct GpuTilemapLight {
+    /// World position of the light, with its height above the tilemap as z
+    pub position: Vec3,
+    pub range: f32,
+    /// Linear color, with intensity applied
+    pub color: Vec4,
 }
 
+#[derive(Clone, Default, ShaderType)]
+pub struct TilemapLightsUniform {
+    pub lights: [GpuTilemapLight; MAX_TILEMAP_LIGHTS],
+    pub count: u32,
+}
+
+#[derive(Default)]
 pub struct ChunkMeta {
-    vertices: RawBufferVec<TilemapVertex>,
-    tilemap_gpu_data: DynamicUniformBuffer<TilemapGpuData>,
-    tilemap_gpu_data_bind_group: Option<BindGroup>,
+    /// Instances relative to the chunk origin
+    instances: Vec<TilemapInstance>,
+    /// What the instances were generated from
+    source: Option<ChunkSource>,
+    image_handle_id: AssetId<Image>,
     texture_size: UVec2,
     tile_size: UVec2,
+    texture_array: bool,
+    offset: Vec2,
+    parallax: Vec2,
+    z: f32,
+    render_layers: RenderLayers,
+    blend_mode: TileBlendMode,
+    opaque: bool,
+    projection: TileMapProjection,
+    /// Whether any tile in the chunk has a depth bias
+    has_depth_bias: bool,
 }
 
-impl Default for ChunkMeta {
+pub type ChunkKey = (Entity, IVec3);
+
+/// Tilemap entity and layer of a batch
+pub type BatchKey = (Entity, i32);
+
+/// Instances of the visible chunks of a tilemap layer, drawn together
+pub struct BatchMeta {
+    /// Origins of the chunks in the batch, in the order their instances are stored.
+    /// Instances are relative to the first chunk's origin.
+    chunks: Vec<IVec3>,
+    /// Range of instances of each chunk, in the same order as `chunks`.
+    /// Only meaningful if the instances are not sorted across chunks.
+    chunk_ranges: Vec<Range<u32>>,
+    /// Whether instances were sorted across chunks, so that chunks can't be updated in place
+    sorted: bool,
+    instances: RawBufferVec<TilemapInstance>,
+}
+
+impl Default for BatchMeta {
     fn default() -> Self {
         Self {
-            vertices: RawBufferVec::new(BufferUsages::VERTEX),
-            tilemap_gpu_data: DynamicUniformBuffer::default(),
-            tilemap_gpu_data_bind_group: None,
-            texture_size: UVec2::ZERO,
-            tile_size: UVec2::ZERO,
+            chunks: Vec::new(),
+            chunk_ranges: Vec::new(),
+            sorted: false,
+            instances: RawBufferVec::new(BufferUsages::VERTEX),
         }
     }
 }
 
-pub type ChunkKey = (Entity, IVec3);
-
 #[derive(Default, Resource)]
 pub struct TilemapMeta {
     chunks: HashMap<ChunkKey, ChunkMeta>,
+    batches: HashMap<BatchKey, BatchMeta>,
+    /// Instance allocations of chunks that are no longer extracted, for reuse by new chunks
+    instance_pool: Vec<Vec<TilemapInstance>>,
+    /// Batches that are no longer drawn, for reuse of their instance buffers
+    batch_pool: Vec<BatchMeta>,
+    tilemap_gpu_data: DynamicUniformBuffer<TilemapGpuData>,
+    lights: UniformBuffer<TilemapLightsUniform>,
+    tilemap_gpu_data_bind_group: Option<BindGroup>,
     view_bind_group: Option<BindGroup>,
 }
 
+/// Visible chunks of a tilemap layer, drawn together as a single phase item
 #[derive(Component, PartialEq, Clone, Eq)]
 pub struct TilemapBatch {
     image_handle_id: AssetId<Image>,
+    normal_map_id: Option<AssetId<Image>>,
+    /// Dynamic offset of the view-specific data in the GPU data buffer
+    gpu_data_offset: u32,
+    /// Range of instances to draw
     range: Range<u32>,
-    chunk_key: (Entity, IVec3),
+    batch_key: BatchKey,
+    /// Key the batch's pipeline was specialized with, for specializing material pipelines
+    pipeline_key: TilemapPipelineKey,
 }
 
 #[derive(Default, Resource)]
 pub struct ImageBindGroups {
-    values: HashMap<AssetId<Image>, BindGroup>,
+    /// Bind groups by tileset image and normal map
+    values: HashMap<(AssetId<Image>, Option<AssetId<Image>>), BindGroup>,
 }
@@ -2,17 +2,24 @@ use bevy::core_pipeline::core_2d::CORE_2D_DEPTH_FORMAT;
 use bevy::ecs::prelude::*;
 use bevy::ecs::system::SystemState;
 use bevy::image::BevyDefault;
-use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
-use bevy::render::view::ViewUniform;
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, texture_2d_array, uniform_buffer};
+use bevy::render::view::{ViewTarget, ViewUniform};
 use bevy::render::{render_resource::*, renderer::RenderDevice};
+use bevy::sprite::AlphaMode2d;
 
 use super::*;
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct TilemapPipeline {
     pub(super) view_layout: BindGroupLayout,
     pub(super) material_layout: BindGroupLayout,
+    /// Material layout for array textures built by a [`TilesetArray`](crate::tileset_array::TilesetArray)
+    pub(super) material_array_layout: BindGroupLayout,
+    /// Material layout for tilemaps with a [`TileMapNormalMap`](crate::lighting::TileMapNormalMap)
+    pub(super) material_normal_map_layout: BindGroupLayout,
     pub(super) tilemap_gpu_data_layout: BindGroupLayout,
+    /// Shader from [`TileMapShader`]
+    pub(super) shader: Handle<Shader>,
 }
 
 bitflags::bitflags! {
@@ -22,6 +29,15 @@ bitflags::bitflags! {
     // MSAA uses the highest 6 bits for the MSAA sample count - 1 to support up to 64x MSAA.
     pub struct TilemapPipelineKey: u32 {
         const NONE                        = 0;
+        const ALPHA_MASK                  = 1 << 0;
+        const OPAQUE                      = 1 << 1;
+        const TEXTURE_ARRAY               = 1 << 2;
+        const NORMAL_MAP                  = 1 << 3;
+        const HDR                         = 1 << 4;
+        const BLEND_ADDITIVE              = 1 << 5;
+        const BLEND_MULTIPLY              = 1 << 6;
+        const DEPTH_WRITE                 = 1 << 7;
+        const NO_DEPTH                    = 1 << 8;
         const MSAA_RESERVED_BITS          = TilemapPipelineKey::MSAA_MASK_BITS << TilemapPipelineKey::MSAA_SHIFT_BITS;
     }
 }
@@ -40,6 +56,24 @@ impl TilemapPipelineKey {
     pub const fn msaa_samples(&self) -> u32 {
         1 << ((self.bits() >> Self::MSAA_SHIFT_BITS) & Self::MSAA_MASK_BITS)
     }
+
+    #[inline]
+    pub const fn from_alpha_mode(alpha_mode: AlphaMode2d) -> Self {
+        match alpha_mode {
+            AlphaMode2d::Blend => Self::NONE,
+            AlphaMode2d::Mask(_) => Self::ALPHA_MASK,
+            AlphaMode2d::Opaque => Self::OPAQUE,
+        }
+    }
+
+    #[inline]
+    pub const fn from_blend_mode(blend_mode: TileBlendMode) -> Self {
+        match blend_mode {
+            TileBlendMode::Alpha => Self::NONE,
+            TileBlendMode::Additive => Self::BLEND_ADDITIVE,
+            TileBlendMode::Multiply => Self::BLEND_MULTIPLY,
+        }
+    }
 }
 
 impl FromWorld for TilemapPipeline {
@@ -63,18 +97,47 @@ impl FromWorld for TilemapPipeline {
             ),
         );
 
+        let material_array_layout = render_device.create_bind_group_layout(
+            "tilemap_material_array_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d_array(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
+        let material_normal_map_layout = render_device.create_bind_group_layout(
+            "tilemap_material_normal_map_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                ),
+            ),
+        );
+
         let tilemap_gpu_data_layout = render_device.create_bind_group_layout(
             "tilemap_tilemap_gpu_data_layout",
             &BindGroupLayoutEntries::sequential(
                 ShaderStages::VERTEX_FRAGMENT,
-                (uniform_buffer::<TilemapGpuData>(true),),
+                (
+                    uniform_buffer::<TilemapGpuData>(true),
+                    uniform_buffer::<TilemapLightsUniform>(false),
+                ),
             ),
         );
 
         Self {
             view_layout,
             material_layout,
+            material_array_layout,
+            material_normal_map_layout,
             tilemap_gpu_data_layout,
+            shader: world.get_resource::<TileMapShader>().cloned().unwrap_or_default().0,
         }
     }
 }
@@ -83,41 +146,131 @@ impl SpecializedRenderPipeline for TilemapPipeline {
     type Key = TilemapPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let vertex_formats = vec![
+        let instance_formats = vec![
             // Position
             VertexFormat::Float32x3,
-            // UV
-            VertexFormat::Float32x2,
-            // Tile UV
+            // Size
             VertexFormat::Float32x2,
+            // UV rect
+            VertexFormat::Float32x4,
             // Color
             VertexFormat::Float32x4,
+            // Flags
+            VertexFormat::Uint32,
         ];
 
-        let vertex_buffer_layout = VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, vertex_formats);
+        let instance_buffer_layout =
+            VertexBufferLayout::from_vertex_formats(VertexStepMode::Instance, instance_formats);
+
+        let mut shader_defs = Vec::new();
 
-        let shader_defs = vec![];
+        let blend = if key.contains(TilemapPipelineKey::ALPHA_MASK) {
+            shader_defs.push("ALPHA_MASK".into());
+            None
+        } else if key.contains(TilemapPipelineKey::OPAQUE) {
+            shader_defs.push("OPAQUE".into());
+            None
+        } else if key.contains(TilemapPipelineKey::BLEND_ADDITIVE) {
+            Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            })
+        } else if key.contains(TilemapPipelineKey::BLEND_MULTIPLY) {
+            // The shader premultiplies the color by alpha, so transparent parts leave the destination unchanged
+            shader_defs.push("BLEND_MULTIPLY".into());
+            Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            })
+        } else {
+            Some(BlendState::ALPHA_BLENDING)
+        };
+
+        let material_layout = if key.contains(TilemapPipelineKey::TEXTURE_ARRAY) {
+            shader_defs.push("TEXTURE_ARRAY".into());
+            &self.material_array_layout
+        } else if key.contains(TilemapPipelineKey::NORMAL_MAP) {
+            shader_defs.push("NORMAL_MAP".into());
+            &self.material_normal_map_layout
+        } else {
+            &self.material_layout
+        };
+
+        if key.msaa_samples() > 1 {
+            shader_defs.push("MULTISAMPLED".into());
+        }
+
+        let format = if key.contains(TilemapPipelineKey::HDR) {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+
+        // Masked and opaque tiles have no partially transparent fragments, so they can safely write depth.
+        // Blended tiles can opt into depth writes, discarding fragments below the alpha cutoff.
+        let depth_write_enabled = if blend.is_none() {
+            true
+        } else if key.contains(TilemapPipelineKey::DEPTH_WRITE) {
+            shader_defs.push("DEPTH_WRITE".into());
+            true
+        } else {
+            false
+        };
+
+        // Tilemaps without depth testing are rendered into passes without a depth attachment
+        let depth_stencil = (!key.contains(TilemapPipelineKey::NO_DEPTH)).then(|| DepthStencilState {
+            format: CORE_2D_DEPTH_FORMAT,
+            depth_write_enabled,
+            depth_compare: CompareFunction::GreaterEqual,
+            stencil: StencilState {
+                front: StencilFaceState::IGNORE,
+                back: StencilFaceState::IGNORE,
+                read_mask: 0,
+                write_mask: 0,
+            },
+            bias: DepthBiasState {
+                constant: 0,
+                slope_scale: 0.0,
+                clamp: 0.0,
+            },
+        });
 
         RenderPipelineDescriptor {
             vertex: VertexState {
-                shader: TILEMAP_SHADER_HANDLE,
+                shader: self.shader.clone(),
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
-                buffers: vec![vertex_buffer_layout],
+                buffers: vec![instance_buffer_layout],
             },
             fragment: Some(FragmentState {
-                shader: TILEMAP_SHADER_HANDLE,
+                shader: self.shader.clone(),
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
-                    format: TextureFormat::bevy_default(),
-                    blend: Some(BlendState::ALPHA_BLENDING),
+                    format,
+                    blend,
                     write_mask: ColorWrites::ALL,
                 })],
             }),
             layout: vec![
                 self.view_layout.clone(),
-                self.material_layout.clone(),
+                material_layout.clone(),
                 self.tilemap_gpu_data_layout.clone(),
             ],
             primitive: PrimitiveState {
@@ -129,22 +282,7 @@ impl SpecializedRenderPipeline for TilemapPipeline {
                 topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
             },
-            depth_stencil: Some(DepthStencilState {
-                format: CORE_2D_DEPTH_FORMAT,
-                depth_write_enabled: false,
-                depth_compare: CompareFunction::GreaterEqual,
-                stencil: StencilState {
-                    front: StencilFaceState::IGNORE,
-                    back: StencilFaceState::IGNORE,
-                    read_mask: 0,
-                    write_mask: 0,
-                },
-                bias: DepthBiasState {
-                    constant: 0,
-                    slope_scale: 0.0,
-                    clamp: 0.0,
-                },
-            }),
+            depth_stencil,
             multisample: MultisampleState {
                 count: key.msaa_samples(),
                 mask: !0,
@@ -0,0 +1,49 @@
+use bevy::ecs::prelude::*;
+use bevy::render::{
+    render_resource::*,
+    renderer::{RenderDevice, RenderQueue},
+};
+
+use super::pipeline::TilemapPipeline;
+use super::*;
+
+/// Upload the view-specific data of all batches queued by any view, and the lights shared by all batches
+pub fn prepare_tilemaps(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut tilemap_meta: ResMut<TilemapMeta>,
+    tilemap_pipeline: Res<TilemapPipeline>,
+    extracted_lights: Res<ExtractedTilemapLights>,
+) {
+    let tilemap_meta = &mut *tilemap_meta;
+
+    if tilemap_meta.tilemap_gpu_data.is_empty() {
+        return;
+    }
+
+    tilemap_meta
+        .tilemap_gpu_data
+        .write_buffer(&render_device, &render_queue);
+
+    // Lights are shared by all batches
+    let mut lights = TilemapLightsUniform {
+        count: extracted_lights.lights.len() as u32,
+        ..Default::default()
+    };
+
+    for (light, extracted_light) in lights.lights.iter_mut().zip(extracted_lights.lights.iter()) {
+        *light = *extracted_light;
+    }
+
+    tilemap_meta.lights.set(lights);
+    tilemap_meta.lights.write_buffer(&render_device, &render_queue);
+
+    tilemap_meta.tilemap_gpu_data_bind_group = Some(render_device.create_bind_group(
+        Some("tilemap_gpu_data_bind_group"),
+        &tilemap_pipeline.tilemap_gpu_data_layout,
+        &BindGroupEntries::sequential((
+            tilemap_meta.tilemap_gpu_data.binding().unwrap(),
+            tilemap_meta.lights.binding().unwrap(),
+        )),
+    ));
+}
@@ -1,14 +1,16 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 
 use bevy::asset::AssetEvent;
-use bevy::core_pipeline::core_2d::Transparent2d;
+use bevy::core_pipeline::core_2d::{Camera2d, Opaque2d, Opaque2dBinKey, Transparent2d};
 use bevy::ecs::prelude::*;
 use bevy::image::Image;
-use bevy::math::{FloatOrd, Vec2};
+use bevy::math::{DMat4, DVec2, FloatOrd};
 use bevy::prelude::*;
-use bevy::render::render_phase::{PhaseItemExtraIndex, ViewSortedRenderPhases};
+use bevy::render::render_phase::{
+    BinnedRenderPhaseType, PhaseItemExtraIndex, ViewBinnedRenderPhases, ViewSortedRenderPhases,
+};
 use bevy::render::texture::GpuImage;
-use bevy::render::view::ExtractedView;
+use bevy::render::view::{ExtractedView, RenderLayers};
 use bevy::render::{
     render_asset::RenderAssets,
     render_phase::DrawFunctions,
@@ -16,37 +18,30 @@ use bevy::render::{
     renderer::{RenderDevice, RenderQueue},
     view::ViewUniforms,
 };
+use bevy::sprite::AlphaMode2d;
 
-use bevy::utils::hashbrown::HashMap;
-#[cfg(not(target_arch = "wasm32"))]
+use bevy::utils::hashbrown::{HashMap, HashSet};
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use crate::ambient::TileMapAmbient;
+use crate::diagnostics::{TilemapDiagnostics, TilemapDiagnosticsChannel};
+use crate::tilemap::calc_chunk_pos;
 use crate::TileFlags;
 
 use super::draw::DrawTilemap;
 use super::pipeline::{TilemapPipeline, TilemapPipelineKey};
+use super::stats::{TileMapRenderStats, TileMapRenderStatsChannel};
 use super::*;
 
-const QUAD_INDICES: [usize; 6] = [0, 2, 3, 0, 1, 2];
-
-const QUAD_VERTEX_POSITIONS: [Vec2; 4] = [
-    Vec2::from_array([-0.5, -0.5]),
-    Vec2::from_array([0.5, -0.5]),
-    Vec2::from_array([0.5, 0.5]),
-    Vec2::from_array([-0.5, 0.5]),
-];
-
-const QUAD_UVS: [Vec2; 4] = [
-    Vec2::from_array([0., 1.]),
-    Vec2::from_array([1., 1.]),
-    Vec2::from_array([1., 0.]),
-    Vec2::from_array([0., 0.]),
-];
+/// Maximum number of unused chunk instance allocations and batch buffers kept for reuse
+const MAX_POOLED_BUFFERS: usize = 64;
 
 #[allow(clippy::too_many_arguments)]
 pub fn queue_tilemaps(
     mut commands: Commands,
     draw_functions: Res<DrawFunctions<Transparent2d>>,
+    opaque_draw_functions: Res<DrawFunctions<Opaque2d>>,
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
     mut tilemap_meta: ResMut<TilemapMeta>,
@@ -57,22 +52,35 @@ pub fn queue_tilemaps(
     mut image_bind_groups: ResMut<ImageBindGroups>,
     gpu_images: Res<RenderAssets<GpuImage>>,
     mut extracted_tilemaps: ResMut<ExtractedTilemaps>,
+    mut material_tilemaps: ResMut<ExtractedMaterialTilemaps>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
-    views: Query<(Entity, &Msaa), With<ExtractedView>>,
+    mut opaque_render_phases: ResMut<ViewBinnedRenderPhases<Opaque2d>>,
+    views: Query<(Entity, &ExtractedView, &Msaa, Option<&RenderLayers>, Has<Camera2d>)>,
     events: Res<TilemapAssetEvents>,
+    ambient: Res<TileMapAmbient>,
+    render_stats_channel: Res<TileMapRenderStatsChannel>,
+    diagnostics_channel: Res<TilemapDiagnosticsChannel>,
 ) {
+    // Material extraction adds to these every frame
+    let material_tilemaps = std::mem::take(&mut material_tilemaps.entities);
+
+    let mut render_stats = TileMapRenderStats::default();
+    let mut diagnostics = TilemapDiagnostics::default();
+
     // If an image has changed, the GpuImage has (probably) changed
     for event in &events.images {
         match event {
             AssetEvent::Added { .. } | AssetEvent::Unused { .. } | AssetEvent::LoadedWithDependencies { .. } => {}
             AssetEvent::Modified { id } | AssetEvent::Removed { id } => {
-                image_bind_groups.values.remove(id);
+                image_bind_groups
+                    .values
+                    .retain(|(image_id, normal_map_id), _| image_id != id && *normal_map_id != Some(*id));
             }
         };
     }
 
     if let Some(view_binding) = view_uniforms.uniforms.binding() {
-        let tilemap_meta = &mut tilemap_meta;
+        let tilemap_meta = &mut *tilemap_meta;
 
         tilemap_meta.view_bind_group = Some(render_device.create_bind_group(
             Some("tilemap_view_bind_group"),
@@ -84,212 +92,634 @@ pub fn queue_tilemaps(
         ));
 
         let draw_tilemap_function = draw_functions.read().get_id::<DrawTilemap>().unwrap();
+        let draw_tilemap_opaque_function = opaque_draw_functions.read().get_id::<DrawTilemap>().unwrap();
+
+        let tilemaps = &mut extracted_tilemaps.tilemaps;
+        let image_bind_groups = &mut *image_bind_groups;
+
+        let mut visible_chunks: Vec<(Entity, IVec3)> = Vec::new();
+        // Batches with chunks visible to each view, so that views only draw the batches they can see
+        let mut view_batch_keys: HashMap<Entity, HashSet<BatchKey>> = HashMap::default();
+        let mut remeshed_chunks: HashSet<ChunkKey> = HashSet::default();
+
+        // Metadata of chunks that are not extracted this frame is recycled afterwards
+        let mut previous_chunks = std::mem::take(&mut tilemap_meta.chunks);
+        let mut tilemap_transforms: HashMap<Entity, GlobalTransform> = HashMap::default();
+        let mut tilemap_main_entities: HashMap<Entity, MainEntity> = HashMap::default();
+        let mut tilemap_alpha_modes: HashMap<Entity, AlphaMode2d> = HashMap::default();
+        let mut tilemap_depth_write_cutoffs: HashMap<Entity, Option<f32>> = HashMap::default();
+        let mut tilemap_depth_tests: HashMap<Entity, bool> = HashMap::default();
+        let mut tilemap_colors: HashMap<Entity, LinearRgba> = HashMap::default();
+        let mut tilemap_uv_insets: HashMap<Entity, f32> = HashMap::default();
+        let mut tilemap_normal_maps: HashMap<Entity, (AssetId<Image>, AssetId<Image>)> = HashMap::default();
+
+        for ((entity, main_entity), tilemap) in tilemaps.iter_mut() {
+            // Set up bind groups for the images used by the tilemap's chunks
+            let mut image_sizes: HashMap<AssetId<Image>, UVec2> = HashMap::default();
+
+            for source in tilemap.chunks.iter().map(|c| c.source) {
+                let image_handle_id = source.image_handle_id;
+
+                if image_sizes.contains_key(&image_handle_id) {
+                    continue;
+                }
 
-        for (view_entity, msaa) in views.iter() {
-            let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
-                continue;
-            };
-
-            let key = TilemapPipelineKey::from_msaa_samples(msaa.samples());
-            let pipeline = pipelines.specialize(&pipeline_cache, &tilemap_pipeline, key);
-
-            let tilemaps = &mut extracted_tilemaps.tilemaps;
-            let image_bind_groups = &mut *image_bind_groups;
-
-            transparent_phase.items.reserve(tilemaps.len());
-
-            let mut visible_chunks: Vec<(Entity, IVec3)> = Vec::new();
-            let mut tilemap_transforms: HashMap<Entity, GlobalTransform> = HashMap::default();
-            let mut tilemap_image_handle_ids: HashMap<Entity, AssetId<Image>> = HashMap::default();
-            let mut tilemap_main_entities: HashMap<Entity, MainEntity> = HashMap::default();
+                if let Some(gpu_image) = gpu_images.get(image_handle_id) {
+                    image_sizes.insert(image_handle_id, gpu_image.size);
 
-            for ((entity, main_entity), tilemap) in tilemaps.iter_mut() {
-                let image_size;
-                // Set-up a new possible batch
-                if let Some(gpu_image) = gpu_images.get(tilemap.image_handle_id) {
-                    image_size = gpu_image.size;
+                    let material_layout = if source.texture_array {
+                        &tilemap_pipeline.material_array_layout
+                    } else {
+                        &tilemap_pipeline.material_layout
+                    };
 
                     image_bind_groups
                         .values
-                        .entry(tilemap.image_handle_id)
+                        .entry((image_handle_id, None))
                         .or_insert_with(|| {
                             render_device.create_bind_group(
                                 Some("tilemap_material_bind_group"),
-                                &tilemap_pipeline.material_layout,
+                                material_layout,
                                 &BindGroupEntries::sequential((&gpu_image.texture_view, &gpu_image.sampler)),
                             )
                         });
-                } else {
-                    // Skip this item if the texture is not ready
-                    continue;
                 }
+            }
 
-                // Yank each chunk's GPU metadata (if one exists) out of the HashMap
-                // so that we can pass it into the parallel iterator later.
-                // Maybe there is a cleaner way of doing this, but I can't think of one
-                // so this will have to do for now.
-                let chonks: Vec<(ExtractedChunk, Option<(ChunkKey, ChunkMeta)>)> = tilemap
-                    .chunks
-                    .drain(..)
-                    .map(|c| {
-                        let entry = tilemap_meta.chunks.remove_entry(&(*entity, c.origin));
-
-                        (c, entry)
-                    })
-                    .collect();
+            // Set up a bind group for the image with its normal map, once both are ready
+            if let Some((image_handle_id, normal_map_id)) = tilemap.normal_map {
+                if let (Some(gpu_image), Some(gpu_normal_map)) =
+                    (gpu_images.get(image_handle_id), gpu_images.get(normal_map_id))
+                {
+                    image_bind_groups
+                        .values
+                        .entry((image_handle_id, Some(normal_map_id)))
+                        .or_insert_with(|| {
+                            render_device.create_bind_group(
+                                Some("tilemap_material_normal_map_bind_group"),
+                                &tilemap_pipeline.material_normal_map_layout,
+                                &BindGroupEntries::sequential((
+                                    &gpu_image.texture_view,
+                                    &gpu_image.sampler,
+                                    &gpu_normal_map.texture_view,
+                                )),
+                            )
+                        });
 
-                #[cfg(target_arch = "wasm32")]
-                let chonk_iter = chonks.into_iter();
-                #[cfg(not(target_arch = "wasm32"))]
-                let chonk_iter = chonks.into_par_iter();
+                    tilemap_normal_maps.insert(*entity, (image_handle_id, normal_map_id));
+                }
+            }
 
-                // Process extracted chunks in parallel, updating their metadata.
-                let results: Vec<(ChunkKey, ChunkMeta)> = chonk_iter
-                    .map(|(chunk, chunk_meta)| {
-                        let (key, mut chunk_meta) = if let Some((key, chunk_meta)) = chunk_meta {
-                            (key, chunk_meta)
-                        } else {
-                            ((*entity, chunk.origin), ChunkMeta::default())
-                        };
+            // Skip chunks whose texture is not ready
+            tilemap
+                .chunks
+                .retain(|c| image_sizes.contains_key(&c.source.image_handle_id));
+
+            // Yank each chunk's GPU metadata (if one exists) out of the HashMap
+            // so that we can pass it into the parallel iterator later.
+            // New chunks reuse a pooled instance allocation, if there is one.
+            // Maybe there is a cleaner way of doing this, but I can't think of one
+            // so this will have to do for now.
+            let chonks: Vec<(ExtractedChunk, ChunkMeta)> = tilemap
+                .chunks
+                .drain(..)
+                .map(|c| {
+                    let chunk_meta = previous_chunks
+                        .remove(&(*entity, c.origin))
+                        .unwrap_or_else(|| ChunkMeta {
+                            instances: tilemap_meta.instance_pool.pop().unwrap_or_default(),
+                            ..Default::default()
+                        });
 
-                        chunk_meta.tile_size = tilemap.tile_size;
-                        chunk_meta.texture_size = image_size;
-                        chunk_meta.vertices.clear();
+                    (c, chunk_meta)
+                })
+                .collect();
 
-                        let image_size = image_size.as_vec2();
+            #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+            let chonk_iter = chonks.into_iter();
+            #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+            let chonk_iter = chonks.into_par_iter();
+
+            // Process extracted chunks in parallel, updating their metadata.
+            // Only chunks that changed since they were last meshed have their instances rebuilt.
+            let results: Vec<(ChunkKey, ChunkMeta, bool)> = chonk_iter
+                .filter_map(|(chunk, mut chunk_meta)| {
+                    let key = (*entity, chunk.origin);
+
+                    chunk_meta.offset = chunk.offset;
+                    chunk_meta.parallax = chunk.parallax;
+                    chunk_meta.render_layers = chunk.render_layers;
+                    chunk_meta.blend_mode = chunk.blend_mode;
+                    chunk_meta.opaque = chunk.opaque;
+
+                    // Unchanged chunks keep their instances
+                    let Some(mut tiles) = chunk.tiles else {
+                        return chunk_meta.source.is_some().then_some((key, chunk_meta, false));
+                    };
+
+                    let source = chunk.source;
+                    let image_size = *image_sizes.get(&source.image_handle_id).unwrap();
+
+                    chunk_meta.source = Some(source);
+                    chunk_meta.tile_size = source.tile_size;
+                    chunk_meta.image_handle_id = source.image_handle_id;
+                    chunk_meta.texture_size = image_size;
+                    chunk_meta.texture_array = source.texture_array;
+                    chunk_meta.z = source.z;
+                    chunk_meta.projection = source.projection;
+                    chunk_meta.instances.clear();
+
+                    let image_size = image_size.as_vec2();
+                    let grid_matrix = source.projection.grid_matrix(source.tile_size.as_vec2());
+
+                    let z = source.z;
+
+                    // Tiles with a depth bias need to be drawn after the tiles they should appear in front of.
+                    // The sort is stable, so tiles with equal bias keep their order.
+                    // With depth sorted projections, tiles further back are also drawn first.
+                    chunk_meta.has_depth_bias = tiles.iter().any(|tile| tile.flags.depth_bias() != 0);
+                    if source.projection.is_depth_sorted() {
+                        tiles.sort_by_key(|tile| (tile.flags.depth_bias(), Reverse(tile.pos.x + tile.pos.y)));
+                    } else if chunk_meta.has_depth_bias {
+                        tiles.sort_by_key(|tile| tile.flags.depth_bias());
+                    }
+
+                    chunk_meta.instances.extend(tiles.iter().map(|tile| {
+                        let rect = tile.rect.as_rect();
+                        let uv_min = rect.min / image_size;
+                        let uv_max = rect.max / image_size;
+
+                        // Instances are relative to the chunk origin, which is applied by the batch transform
+                        let tile_pos = grid_matrix * tile.pos.as_vec2() + source.projection.tile_offset(tile.pos);
+
+                        // Use custom size, if one is specified
+                        let quad_size = tile.custom_size.unwrap_or(rect.size());
+
+                        let z = z + tile.flags.depth_bias() as f32 * TileFlags::DEPTH_BIAS_STEP * source.z_step;
+
+                        let mut flags = tile.array_layer << TilemapInstance::ARRAY_LAYER_SHIFT;
+
+                        if tile.flags.contains(TileFlags::FLIP_X) {
+                            flags |= TilemapInstance::FLIP_X;
+                        }
 
-                        let z = chunk.origin.z as f32;
+                        if tile.flags.contains(TileFlags::FLIP_Y) {
+                            flags |= TilemapInstance::FLIP_Y;
+                        }
 
-                        for tile in chunk.tiles.iter() {
-                            // Calculate vertex data for this item
+                        if tile.flags.contains(TileFlags::EMISSIVE) {
+                            flags |= TilemapInstance::EMISSIVE;
+                        }
 
-                            let mut uvs = QUAD_UVS;
+                        flags |= (tile.flags.shape() as u32) << TilemapInstance::SHAPE_SHIFT;
 
-                            if tile.flags.contains(TileFlags::FLIP_X) {
-                                uvs = [uvs[1], uvs[0], uvs[3], uvs[2]];
-                            }
+                        TilemapInstance {
+                            position: tile_pos.extend(z).into(),
+                            size: quad_size.into(),
+                            uv_rect: [uv_min.x, uv_min.y, uv_max.x, uv_max.y],
+                            color: tile.color.to_f32_array(),
+                            flags,
+                        }
+                    }));
 
-                            if tile.flags.contains(TileFlags::FLIP_Y) {
-                                uvs = [uvs[3], uvs[2], uvs[1], uvs[0]];
-                            }
+                    Some((key, chunk_meta, true))
+                })
+                .collect();
 
-                            let tile_uvs = uvs;
+            // (Re-)Insert chunk metadata into the HashMap
+            for (key, chunk_meta, is_remeshed) in results {
+                if is_remeshed {
+                    remeshed_chunks.insert(key);
+                }
 
-                            // If a rect is specified, adjust UVs and the size of the quad
-                            let rect = tile.rect.as_rect();
-                            let quad_size = rect.size();
-                            for uv in &mut uvs {
-                                *uv = (rect.min + *uv * quad_size) / image_size;
-                            }
+                tilemap_meta.chunks.insert(key, chunk_meta);
+            }
 
-                            let tile_pos = tile.pos.as_vec2() * quad_size;
+            visible_chunks.extend(tilemap.visible_chunks.drain(..).map(|pos| (*entity, pos)));
+            for (view_entity, chunks) in tilemap.view_visible_chunks.drain() {
+                view_batch_keys
+                    .entry(view_entity)
+                    .or_default()
+                    .extend(chunks.into_iter().map(|origin| (*entity, origin.z)));
+            }
+            tilemap_transforms.insert(*entity, tilemap.transform);
+            tilemap_main_entities.insert(*entity, *main_entity);
+            tilemap_alpha_modes.insert(*entity, tilemap.alpha_mode);
+            tilemap_depth_write_cutoffs.insert(*entity, tilemap.depth_write_cutoff);
+            tilemap_depth_tests.insert(*entity, tilemap.depth_test);
+            tilemap_colors.insert(*entity, tilemap.color);
+            tilemap_uv_insets.insert(*entity, tilemap.uv_inset);
+        }
 
-                            // Apply size and global transform
-                            let positions = QUAD_VERTEX_POSITIONS
-                                .map(|quad_pos| (tile_pos + (quad_pos * quad_size)).extend(z).into());
+        // Keep the instance allocations of chunks that went out of range, to reuse for new chunks
+        for (_, mut chunk_meta) in previous_chunks {
+            if tilemap_meta.instance_pool.len() < MAX_POOLED_BUFFERS {
+                chunk_meta.instances.clear();
+                tilemap_meta.instance_pool.push(chunk_meta.instances);
+            }
+        }
 
-                            // Store the vertex data and add the item to the render phase
-                            let color = tile.color.to_f32_array();
+        // All chunks of a tilemap layer share texture, z and offset, so they end up adjacent in the
+        // transparent phase. Merge them into a single batch, to reduce the number of phase items and draw calls.
+        let mut layer_chunks: HashMap<BatchKey, Vec<IVec3>> = HashMap::default();
 
-                            for i in QUAD_INDICES.iter() {
-                                chunk_meta.vertices.push(TilemapVertex {
-                                    position: positions[*i],
-                                    uv: uvs[*i].into(),
-                                    tile_uv: tile_uvs[*i].into(),
-                                    color,
-                                });
-                            }
-                        }
+        for (entity, origin) in visible_chunks {
+            if tilemap_meta.chunks.contains_key(&(entity, origin)) {
+                layer_chunks.entry((entity, origin.z)).or_default().push(origin);
+            }
+        }
 
-                        (key, chunk_meta)
-                    })
+        let TilemapMeta {
+            chunks: chunk_metas,
+            batches: batch_metas,
+            batch_pool,
+            tilemap_gpu_data,
+            ..
+        } = &mut *tilemap_meta;
+
+        // Recycle batches of layers that are no longer visible, keeping their instance buffers for reuse
+        let unused_batches: Vec<BatchKey> = batch_metas
+            .keys()
+            .filter(|key| !layer_chunks.contains_key(*key))
+            .copied()
+            .collect();
+
+        for batch_key in unused_batches {
+            let Some(mut batch_meta) = batch_metas.remove(&batch_key) else {
+                continue;
+            };
+
+            if batch_pool.len() < MAX_POOLED_BUFFERS {
+                batch_meta.chunks.clear();
+                batch_pool.push(batch_meta);
+            }
+        }
+
+        let mut batch_keys: Vec<BatchKey> = Vec::with_capacity(layer_chunks.len());
+
+        for (batch_key, mut chunks) in layer_chunks {
+            let (entity, _) = batch_key;
+
+            chunks.sort_unstable_by_key(|origin| (origin.y, origin.x));
+
+            let batch_meta = batch_metas
+                .entry(batch_key)
+                .or_insert_with(|| batch_pool.pop().unwrap_or_default());
+            batch_keys.push(batch_key);
+
+            let origin = chunks[0];
+
+            // Only rebuild and upload the batch's instances if any of its chunks changed
+            if batch_meta.chunks == chunks {
+                let changed: Vec<usize> = (0..chunks.len())
+                    .filter(|i| remeshed_chunks.contains(&(entity, chunks[*i])))
                     .collect();
 
-                // (Re-)Insert chunk metadata into the HashMap
-                for (key, chunk_meta) in results {
-                    tilemap_meta.chunks.insert(key, chunk_meta);
+                if changed.is_empty() {
+                    continue;
                 }
 
-                visible_chunks.extend(tilemap.visible_chunks.drain(..).map(|pos| (*entity, pos)));
-                tilemap_transforms.insert(*entity, tilemap.transform);
-                tilemap_image_handle_ids.insert(*entity, tilemap.image_handle_id);
-                tilemap_main_entities.insert(*entity, *main_entity);
+                // If the changed chunks still have the same number of instances, overwrite them in place
+                // and only upload their byte ranges. Chunks that now need sorting across chunks can't be.
+                let can_update_in_place = !batch_meta.sorted
+                    && batch_meta.instances.buffer().is_some()
+                    && changed.iter().all(|i| {
+                        let range = &batch_meta.chunk_ranges[*i];
+                        let chunk_meta = &chunk_metas[&(entity, chunks[*i])];
+
+                        chunk_meta.instances.len() == range.len()
+                            && !chunk_meta.has_depth_bias
+                            && !chunk_meta.projection.is_depth_sorted()
+                    });
+
+                if can_update_in_place {
+                    let BatchMeta {
+                        chunk_ranges,
+                        instances,
+                        ..
+                    } = batch_meta;
+
+                    for i in changed {
+                        let chunk_origin = chunks[i];
+                        let chunk_meta = &chunk_metas[&(entity, chunk_origin)];
+                        let range = chunk_ranges[i].start as usize..chunk_ranges[i].end as usize;
+
+                        let translation = chunk_meta
+                            .projection
+                            .tile_to_local((chunk_origin - origin).truncate(), chunk_meta.tile_size.as_vec2())
+                            .extend(0.0);
+
+                        for (batch_instance, instance) in instances.values_mut()[range.clone()]
+                            .iter_mut()
+                            .zip(chunk_meta.instances.iter())
+                        {
+                            *batch_instance = TilemapInstance {
+                                position: (Vec3::from(instance.position) + translation).into(),
+                                ..*instance
+                            };
+                        }
+
+                        let offset = (range.start * size_of::<TilemapInstance>()) as u64;
+                        render_queue.write_buffer(
+                            instances.buffer().unwrap(),
+                            offset,
+                            bytemuck::cast_slice(&instances.values()[range.clone()]),
+                        );
+
+                        diagnostics.instances_uploaded += range.len();
+                    }
+
+                    continue;
+                }
             }
 
-            let mut sorted_chunks: Vec<_> = tilemap_meta
-                .chunks
-                .iter_mut()
-                .filter(|(key, _)| {
-                    // If chunk is not visible, there is no need to draw it.
-                    visible_chunks.contains(key)
-                })
-                .map(|(key, chunk_meta)| {
-                    let (entity, _) = key;
-                    let tilemap_transform = tilemap_transforms.get(entity).unwrap();
+            let mut has_depth_bias = false;
+            let mut is_depth_sorted = false;
 
-                    (key, tilemap_transform, chunk_meta)
-                })
-                .collect();
+            batch_meta.instances.clear();
+            batch_meta.chunk_ranges.clear();
+
+            for chunk_origin in chunks.iter() {
+                let chunk_meta = &chunk_metas[&(entity, *chunk_origin)];
 
-            sorted_chunks.sort_unstable_by(|((_, a), att, _), ((_, b), btt, _)| {
-                let att_translation = att.translation();
-                let btt_translation = btt.translation();
+                // Move the chunk's instances to be relative to the batch origin
+                let translation = chunk_meta
+                    .projection
+                    .tile_to_local((*chunk_origin - origin).truncate(), chunk_meta.tile_size.as_vec2())
+                    .extend(0.0);
 
-                match att_translation.z.partial_cmp(&btt_translation.z) {
-                    Some(Ordering::Equal) | None => a.z.cmp(&b.z),
-                    Some(other) => other,
+                has_depth_bias |= chunk_meta.has_depth_bias;
+                is_depth_sorted |= chunk_meta.projection.is_depth_sorted();
+
+                let start = batch_meta.instances.len() as u32;
+
+                for instance in chunk_meta.instances.iter() {
+                    batch_meta.instances.push(TilemapInstance {
+                        position: (Vec3::from(instance.position) + translation).into(),
+                        ..*instance
+                    });
                 }
-            });
 
-            // Render all chunks.
-            for (key, tilemap_transform, chunk_meta) in sorted_chunks.into_iter() {
-                let (tilemap_entity, _) = key;
+                batch_meta.chunk_ranges.push(start..batch_meta.instances.len() as u32);
+            }
 
-                chunk_meta.tilemap_gpu_data.clear();
-                chunk_meta.tilemap_gpu_data.push(&TilemapGpuData {
-                    transform: tilemap_transform.compute_matrix(),
-                    tile_size: chunk_meta.tile_size.as_vec2(),
-                    texture_size: chunk_meta.texture_size.as_vec2(),
+            batch_meta.sorted = is_depth_sorted || has_depth_bias;
+
+            // Depth bias and depth sorting must also be respected between tiles of different chunks
+            if is_depth_sorted {
+                batch_meta.instances.values_mut().sort_by(|a, b| {
+                    a.position[2]
+                        .total_cmp(&b.position[2])
+                        .then(b.position[1].total_cmp(&a.position[1]))
                 });
+            } else if has_depth_bias {
+                batch_meta
+                    .instances
+                    .values_mut()
+                    .sort_by(|a, b| a.position[2].total_cmp(&b.position[2]));
+            }
+
+            batch_meta.instances.write_buffer(&render_device, &render_queue);
+            batch_meta.chunks = chunks;
+
+            diagnostics.instances_uploaded += batch_meta.instances.len();
+        }
+
+        let chunk_metas = &*chunk_metas;
+        let batch_metas = &*batch_metas;
+
+        // Origin and metadata of the chunk a batch's instances are relative to
+        let batch_chunk_meta = |batch_key: BatchKey| {
+            let origin = batch_metas[&batch_key].chunks[0];
+
+            (origin, &chunk_metas[&(batch_key.0, origin)])
+        };
+
+        batch_keys.sort_unstable_by(|a, b| {
+            let (_, am) = batch_chunk_meta(*a);
+            let (_, bm) = batch_chunk_meta(*b);
+
+            let a_z = tilemap_transforms[&a.0].transform_point(Vec3::Z * am.z).z;
+            let b_z = tilemap_transforms[&b.0].transform_point(Vec3::Z * bm.z).z;
+
+            match a_z.partial_cmp(&b_z) {
+                Some(Ordering::Equal) | None => a.1.cmp(&b.1),
+                Some(other) => other,
+            }
+        });
+
+        // GPU data is pushed once per view that draws a batch
+        tilemap_gpu_data.clear();
+
+        for (view_entity, view, msaa, view_render_layers, is_core_2d) in views.iter() {
+            let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
+                continue;
+            };
 
-                chunk_meta.tilemap_gpu_data.write_buffer(&render_device, &render_queue);
-                chunk_meta.vertices.write_buffer(&render_device, &render_queue);
+            let mut opaque_phase = opaque_render_phases.get_mut(&view_entity);
 
-                chunk_meta.tilemap_gpu_data_bind_group = Some(render_device.create_bind_group(
-                    Some("tilemap_gpu_data_bind_group"),
-                    &tilemap_pipeline.tilemap_gpu_data_layout,
-                    &[BindGroupEntry {
-                        binding: 0,
-                        resource: chunk_meta.tilemap_gpu_data.binding().unwrap(),
-                    }],
-                ));
+            let mut view_key = TilemapPipelineKey::from_msaa_samples(msaa.samples());
 
-                let translation = tilemap_transform.translation();
+            if view.hdr {
+                view_key |= TilemapPipelineKey::HDR;
+            }
+
+            let view_render_layers = view_render_layers.cloned().unwrap_or_default();
+
+            let Some(view_batch_keys) = view_batch_keys.get(&view_entity) else {
+                continue;
+            };
 
-                // These items will be sorted by depth with other phase items
-                let sort_key = FloatOrd(translation.z);
+            // Batches are positioned relative to the view, to avoid precision loss far from the world origin
+            let view_translation = view.world_from_view.translation().as_dvec3();
 
-                let vertex_count = chunk_meta.vertices.len() as u32;
+            transparent_phase.items.reserve(batch_keys.len());
 
-                let batch = TilemapBatch {
-                    chunk_key: *key,
-                    image_handle_id: *tilemap_image_handle_ids.get(tilemap_entity).unwrap(),
-                    range: 0..vertex_count,
+            for batch_key in batch_keys.iter() {
+                let (tilemap_entity, layer) = batch_key;
+                let (batch_origin, chunk_meta) = batch_chunk_meta(*batch_key);
+                let batch_meta = &batch_metas[batch_key];
+
+                if !view_batch_keys.contains(batch_key) || !view_render_layers.intersects(&chunk_meta.render_layers) {
+                    continue;
+                }
+
+                // Batches whose chunks were all cleared have nothing to draw, and no instance buffer
+                if batch_meta.instances.is_empty() {
+                    continue;
+                }
+
+                let tilemap_transform = tilemap_transforms.get(tilemap_entity).unwrap();
+
+                let alpha_mode = *tilemap_alpha_modes.get(tilemap_entity).unwrap();
+                let depth_test = tilemap_depth_tests[tilemap_entity];
+
+                // The core 2D passes always have a depth attachment, which pipelines without depth testing
+                // are incompatible with. Other views with a transparent phase are custom passes, which are
+                // only drawn into by tilemaps without depth testing, as they may not have a depth attachment.
+                if depth_test != is_core_2d {
+                    continue;
+                }
+
+                let depth_write_cutoff = tilemap_depth_write_cutoffs[tilemap_entity]
+                    .filter(|_| alpha_mode == AlphaMode2d::Blend && depth_test);
+                let alpha_cutoff = match alpha_mode {
+                    AlphaMode2d::Mask(threshold) => threshold,
+                    _ => depth_write_cutoff.unwrap_or(0.0),
                 };
 
-                let batch_entity = commands.spawn(batch).id();
+                // Layers known to be opaque are drawn in the opaque pass, regardless of the tilemap's alpha mode.
+                // Without depth testing, everything is drawn in the transparent pass, so it is sorted by z.
+                // Materials are only applied in the transparent pass, so tilemaps with one are drawn there too.
+                let is_opaque = depth_test
+                    && !material_tilemaps.contains(tilemap_entity)
+                    && (alpha_mode == AlphaMode2d::Opaque || chunk_meta.opaque);
+
+                // Views without an opaque phase don't draw opaque batches
+                if is_opaque && opaque_phase.is_none() {
+                    continue;
+                }
+
+                let mut pipeline_key = if is_opaque {
+                    view_key | TilemapPipelineKey::OPAQUE
+                } else {
+                    view_key | TilemapPipelineKey::from_alpha_mode(alpha_mode)
+                };
+
+                if alpha_mode == AlphaMode2d::Blend && !is_opaque {
+                    pipeline_key |= TilemapPipelineKey::from_blend_mode(chunk_meta.blend_mode);
+                }
+
+                if depth_write_cutoff.is_some() && !is_opaque {
+                    pipeline_key |= TilemapPipelineKey::DEPTH_WRITE;
+                }
+
+                if !depth_test {
+                    pipeline_key |= TilemapPipelineKey::NO_DEPTH;
+                }
+
+                if chunk_meta.texture_array {
+                    pipeline_key |= TilemapPipelineKey::TEXTURE_ARRAY;
+                }
+
+                // Only layers using the tilemap's own image are lit
+                let normal_map_id = tilemap_normal_maps
+                    .get(tilemap_entity)
+                    .filter(|(image_handle_id, _)| {
+                        *image_handle_id == chunk_meta.image_handle_id && !chunk_meta.texture_array
+                    })
+                    .map(|(_, normal_map_id)| *normal_map_id);
+
+                if normal_map_id.is_some() {
+                    pipeline_key |= TilemapPipelineKey::NORMAL_MAP;
+                }
+
+                let pipeline = pipelines.specialize(&pipeline_cache, &tilemap_pipeline, pipeline_key);
+
+                // Position of the batch origin within the tilemap. This is combined with the tilemap transform
+                // in double precision, so that only the final translation is rounded to f32.
+                let grid_matrix = chunk_meta.projection.grid_matrix(chunk_meta.tile_size.as_vec2());
+                let batch_origin = (grid_matrix.as_dmat2() * batch_origin.truncate().as_dvec2()).extend(0.0);
+                // Parallax is relative to the camera of this view
+                let offset = chunk_meta.offset.as_dvec2()
+                    + view_translation.truncate() * (DVec2::ONE - chunk_meta.parallax.as_dvec2());
+
+                let transform = DMat4::from_translation(offset.extend(0.0) - view_translation)
+                    * tilemap_transform.compute_matrix().as_dmat4()
+                    * DMat4::from_translation(batch_origin);
+
+                let gpu_data = TilemapGpuData {
+                    transform: transform.as_mat4(),
+                    tile_size: chunk_meta.tile_size.as_vec2(),
+                    texture_size: chunk_meta.texture_size.as_vec2(),
+                    alpha_cutoff,
+                    uv_inset: tilemap_uv_insets[tilemap_entity],
+                    ambient: ambient.layer_color(*layer).to_vec4(),
+                    color: tilemap_colors[tilemap_entity].to_vec4(),
+                };
+
+                // These items will be sorted by depth with other phase items,
+                // so use the world z of the batch's layer
+                let sort_key = FloatOrd(tilemap_transform.transform_point(Vec3::Z * chunk_meta.z).z);
+
+                let image_handle_id = chunk_meta.image_handle_id;
+                let gpu_data_offset = tilemap_gpu_data.push(&gpu_data);
+
+                let instance_count = batch_meta.instances.len() as u32;
+
+                let batch_entity = commands
+                    .spawn(TilemapBatch {
+                        image_handle_id,
+                        normal_map_id,
+                        gpu_data_offset,
+                        range: 0..instance_count,
+                        batch_key: *batch_key,
+                        pipeline_key,
+                    })
+                    .id();
 
                 let main_entity = tilemap_main_entities.get(tilemap_entity).unwrap();
 
-                transparent_phase.add(Transparent2d {
-                    draw_function: draw_tilemap_function,
-                    pipeline,
-                    entity: (batch_entity, *main_entity),
-                    sort_key,
-                    batch_range: 0..1,
-                    extra_index: PhaseItemExtraIndex::NONE,
-                });
+                render_stats.chunks.extend(
+                    batch_meta
+                        .chunks
+                        .iter()
+                        .map(|origin| (main_entity.id(), calc_chunk_pos(*origin))),
+                );
+                render_stats.tile_count += instance_count as usize;
+
+                if let Some(opaque_phase) = opaque_phase.as_mut().filter(|_| is_opaque) {
+                    opaque_phase.add(
+                        Opaque2dBinKey {
+                            pipeline,
+                            draw_function: draw_tilemap_opaque_function,
+                            asset_id: image_handle_id.untyped(),
+                            material_bind_group_id: None,
+                        },
+                        (batch_entity, *main_entity),
+                        BinnedRenderPhaseType::NonMesh,
+                    );
+                } else {
+                    transparent_phase.add(Transparent2d {
+                        draw_function: draw_tilemap_function,
+                        pipeline,
+                        entity: (batch_entity, *main_entity),
+                        sort_key,
+                        batch_range: 0..1,
+                        extra_index: PhaseItemExtraIndex::NONE,
+                    });
+                }
+
+                diagnostics.draw_calls += 1;
             }
         }
+
+        let instance_size = std::mem::size_of::<TilemapInstance>();
+
+        diagnostics.meshed_chunks = tilemap_meta.chunks.len();
+        diagnostics.cpu_instance_bytes = tilemap_meta
+            .chunks
+            .values()
+            .map(|chunk_meta| &chunk_meta.instances)
+            .chain(tilemap_meta.instance_pool.iter())
+            .map(|instances| instances.capacity() * instance_size)
+            .sum();
+        diagnostics.gpu_buffer_bytes = tilemap_meta
+            .batches
+            .values()
+            .chain(tilemap_meta.batch_pool.iter())
+            .filter_map(|batch_meta| batch_meta.instances.buffer())
+            .chain(tilemap_meta.tilemap_gpu_data.buffer())
+            .chain(tilemap_meta.lights.buffer())
+            .map(|buffer| buffer.size())
+            .sum();
+    }
+
+    if let Ok(mut stats) = render_stats_channel.0.lock() {
+        *stats = render_stats;
+    }
+
+    if let Ok(mut received) = diagnostics_channel.0.lock() {
+        *received = diagnostics;
     }
 }
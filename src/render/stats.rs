@@ -0,0 +1,30 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+
+/// What was actually drawn by the tilemap renderer in the most recently rendered frame.
+///
+/// This is updated in the main world from the render world at the start of every frame.
+/// With pipelined rendering, it lags one frame behind the frame being simulated.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TileMapRenderStats {
+    /// Chunks drawn, as tilemap entity and chunk position.
+    /// A chunk drawn by multiple cameras is listed once per camera.
+    pub chunks: Vec<(Entity, IVec3)>,
+    /// Number of tiles drawn, across all cameras
+    pub tile_count: usize,
+}
+
+/// Shared between the main world and render world, for passing [`TileMapRenderStats`] back to the main world
+#[derive(Resource, Clone, Default)]
+pub(crate) struct TileMapRenderStatsChannel(pub(crate) Arc<Mutex<TileMapRenderStats>>);
+
+/// Copy the stats written by the render world into the main world resource
+pub(crate) fn receive_render_stats_system(
+    channel: Res<TileMapRenderStatsChannel>,
+    mut render_stats: ResMut<TileMapRenderStats>,
+) {
+    if let Ok(mut received) = channel.0.lock() {
+        *render_stats = std::mem::take(&mut *received);
+    }
+}
@@ -1,15 +1,36 @@
+// Leading fields of Bevy's view uniform
 struct View {
-    view_proj: mat4x4<f32>,
-    world_position: vec3<f32>,
+    clip_from_world: mat4x4<f32>,
+    unjittered_clip_from_world: mat4x4<f32>,
+    world_from_clip: mat4x4<f32>,
+    world_from_view: mat4x4<f32>,
+    view_from_world: mat4x4<f32>,
+    clip_from_view: mat4x4<f32>,
 };
 
 @group(0) @binding(0)
 var<uniform> view: View;
 
 struct VertexOutput {
+#ifdef MULTISAMPLED
+    // With MSAA, pixels along the edges of a tile are shaded even if their center is outside of the tile.
+    // Centroid interpolation keeps the UVs within the tile, instead of extrapolating into adjacent sprites.
+    @location(0) @interpolate(perspective, centroid) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(perspective, centroid) tile_uv: vec2<f32>,
+#else
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
     @location(2) tile_uv: vec2<f32>,
+#endif
+    @location(3) @interpolate(flat) array_layer: u32,
+    @location(4) world_position: vec2<f32>,
+    // Sign of the normal map's X and Y, for flipped tiles
+    @location(5) @interpolate(flat) normal_flip: vec2<f32>,
+    // 1.0 for emissive tiles, which are not lit
+    @location(6) @interpolate(flat) emissive: f32,
+    // Min and max UV of the sprite's atlas rect
+    @location(7) @interpolate(flat) uv_rect: vec4<f32>,
     @builtin(position) position: vec4<f32>,
 };
 
@@ -17,58 +38,207 @@ struct TilemapGpuData {
     transform: mat4x4<f32>,
     tile_size: vec2<f32>,
     texture_size: vec2<f32>,
+    alpha_cutoff: f32,
+    // Inset of samples from the edges of the sprite's atlas rect, in texels
+    uv_inset: f32,
+    ambient: vec4<f32>,
+    color: vec4<f32>,
 };
 
 @group(2) @binding(0)
 var<uniform> tilemap: TilemapGpuData;
 
+struct TilemapLight {
+    // World position, with the height above the tilemap as z
+    position: vec3<f32>,
+    range: f32,
+    color: vec4<f32>,
+};
+
+// Must match MAX_TILEMAP_LIGHTS
+const MAX_LIGHTS: u32 = 16u;
+
+struct TilemapLights {
+    lights: array<TilemapLight, MAX_LIGHTS>,
+    count: u32,
+};
+
+@group(2) @binding(1)
+var<uniform> lights: TilemapLights;
+
+// Tile instance flags. Must match TilemapInstance.
+const FLIP_X: u32 = 1u;
+const FLIP_Y: u32 = 2u;
+const EMISSIVE: u32 = 4u;
+const SHAPE_SHIFT: u32 = 8u;
+const SHAPE_MASK: u32 = 15u;
+const ARRAY_LAYER_SHIFT: u32 = 16u;
+
 @vertex
 fn vertex(
     @builtin(vertex_index) vertex_index: u32,
-    @location(0) vertex_position: vec3<f32>,
-    @location(1) vertex_uv: vec2<f32>,
-    @location(2) vertex_tile_uv: vec2<f32>,
-    @location(3) vertex_color: vec4<f32>,
+    @location(0) instance_position: vec3<f32>,
+    @location(1) instance_size: vec2<f32>,
+    @location(2) instance_uv_rect: vec4<f32>,
+    @location(3) instance_color: vec4<f32>,
+    @location(4) instance_flags: u32,
 ) -> VertexOutput {
     var out: VertexOutput;
 
-    out.uv = vertex_uv;
-    out.tile_uv = vertex_tile_uv;
-    out.position = view.view_proj * tilemap.transform * vec4<f32>(vertex_position, 1.0);
-    out.color = vertex_color;
+    // Corners of the quad's two triangles, from -0.5 to 0.5
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+    );
+
+    // Corners of the triangle shapes, each having its right angle in a different corner.
+    // Must match the order of TileShape.
+    var triangles = array<vec2<f32>, 12>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(-0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+    );
+
+    let shape = (instance_flags >> SHAPE_SHIFT) & SHAPE_MASK;
+    var corner = corners[vertex_index];
+
+    if (shape >= 1u && shape <= 4u) {
+        // Triangles only use the first three vertices. The rest collapse onto
+        // the first vertex, forming a degenerate triangle that is not drawn.
+        corner = triangles[(shape - 1u) * 3u + select(0u, vertex_index, vertex_index < 3u)];
+    } else if (shape == 5u) {
+        corner.y = corner.y * 0.5 - 0.25;
+    } else if (shape == 6u) {
+        corner.y = corner.y * 0.5 + 0.25;
+    } else if (shape == 7u) {
+        corner.x = corner.x * 0.5 - 0.25;
+    } else if (shape == 8u) {
+        corner.x = corner.x * 0.5 + 0.25;
+    }
+
+    // UV within the tile, with V pointing down. The shape is in sprite space,
+    // so flipping mirrors the quad rather than the UV, to flip the shape along with the sprite.
+    let tile_uv = vec2<f32>(corner.x + 0.5, 0.5 - corner.y);
+
+    out.normal_flip = vec2<f32>(1.0, 1.0);
+
+    if ((instance_flags & FLIP_X) != 0u) {
+        corner.x = -corner.x;
+        out.normal_flip.x = -1.0;
+    }
+
+    if ((instance_flags & FLIP_Y) != 0u) {
+        corner.y = -corner.y;
+        out.normal_flip.y = -1.0;
+    }
+
+    out.uv = mix(instance_uv_rect.xy, instance_uv_rect.zw, tile_uv);
+    out.tile_uv = tile_uv;
+    out.uv_rect = instance_uv_rect;
+
+    let vertex_position = vec3<f32>(instance_position.xy + corner * instance_size, instance_position.z);
+
+    // The tilemap transform is relative to the view translation, so only the
+    // rotation and scale of the view are applied here.
+    let relative_position = tilemap.transform * vec4<f32>(vertex_position, 1.0);
+    let view_position = (view.view_from_world * vec4<f32>(relative_position.xyz, 0.0)).xyz;
+    out.position = view.clip_from_view * vec4<f32>(view_position, 1.0);
+    out.world_position = relative_position.xy + view.world_from_view[3].xy;
+    out.color = instance_color;
+    out.array_layer = instance_flags >> ARRAY_LAYER_SHIFT;
+    out.emissive = select(0.0, 1.0, (instance_flags & EMISSIVE) != 0u);
 
     return out;
 }
 
+#ifdef TEXTURE_ARRAY
+@group(1) @binding(0)
+var sprite_texture: texture_2d_array<f32>;
+#else
 @group(1) @binding(0)
 var sprite_texture: texture_2d<f32>;
+#endif
 @group(1) @binding(1)
 var sprite_sampler: sampler;
 
+#ifdef NORMAL_MAP
+@group(1) @binding(2)
+var normal_map_texture: texture_2d<f32>;
+#endif
+
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    let half_texture_pixel_size_u = 0.5 / tilemap.texture_size.x;
-    let half_texture_pixel_size_v = 0.5 / tilemap.texture_size.y;
-    let half_tile_pixel_size_u = 0.5 / tilemap.tile_size.x;
-    let half_tile_pixel_size_v = 0.5 / tilemap.tile_size.y;
-
-    // Offset the UV 1/2 pixel from the sides of the tile, so that the sampler doesn't bleed onto
-    // adjacent tiles at the edges.
-    var uv_offset = vec2<f32>(0.0, 0.0);
-
-    if (in.tile_uv.x < half_tile_pixel_size_u) {
-        uv_offset.x = half_texture_pixel_size_u;
-    } else if (in.tile_uv.x > (1.0 - half_tile_pixel_size_u)) {
-        uv_offset.x = -half_texture_pixel_size_u;
+    // Clamp the UV to the interior of the sprite's rect, so that the sampler doesn't bleed onto adjacent sprites.
+    // The inset is limited to half the rect, so that the bounds never cross over.
+    let inset = min(tilemap.uv_inset / tilemap.texture_size, (in.uv_rect.zw - in.uv_rect.xy) * 0.5);
+    let uv = clamp(in.uv, in.uv_rect.xy + inset, in.uv_rect.zw - inset);
+
+    // Derivatives of the unclamped UV, so that mip level selection is unaffected by the clamping
+    let uv_ddx = dpdx(in.uv);
+    let uv_ddy = dpdy(in.uv);
+
+#ifdef TEXTURE_ARRAY
+    var color = in.color * textureSampleGrad(sprite_texture, sprite_sampler, uv, in.array_layer, uv_ddx, uv_ddy);
+#else
+    var color = in.color * textureSampleGrad(sprite_texture, sprite_sampler, uv, uv_ddx, uv_ddy);
+#endif
+
+#ifdef NORMAL_MAP
+    var normal = textureSampleGrad(normal_map_texture, sprite_sampler, uv, uv_ddx, uv_ddy).xyz * 2.0 - 1.0;
+    normal = normalize(vec3<f32>(normal.xy * in.normal_flip, normal.z));
+
+    // Ambient light, plus N-dot-L lighting from point lights
+    var light = tilemap.ambient.rgb;
+
+    for (var i = 0u; i < min(lights.count, MAX_LIGHTS); i++) {
+        let point_light = lights.lights[i];
+        let to_light = vec3<f32>(point_light.position.xy - in.world_position, point_light.position.z);
+        let distance = max(length(to_light), 0.0001);
+        let attenuation = clamp(1.0 - distance / point_light.range, 0.0, 1.0);
+
+        light += point_light.color.rgb * max(dot(normal, to_light / distance), 0.0) * attenuation * attenuation;
+    }
+#else
+    let light = tilemap.ambient.rgb;
+#endif
+
+    // Apply lighting, except to emissive tiles, and the tilemap tint
+    color = vec4<f32>(color.rgb * mix(light, vec3<f32>(1.0), in.emissive), color.a) * tilemap.color;
+
+#ifdef ALPHA_MASK
+    if (color.a < tilemap.alpha_cutoff) {
+        discard;
     }
 
-    if (in.tile_uv.y < half_tile_pixel_size_v) {
-        uv_offset.y = half_texture_pixel_size_v;
-    } else if (in.tile_uv.y > (1.0 - half_tile_pixel_size_v)) {
-        uv_offset.y = -half_texture_pixel_size_v;
+    color.a = 1.0;
+#endif
+
+#ifdef OPAQUE
+    color.a = 1.0;
+#endif
+
+#ifdef DEPTH_WRITE
+    if (color.a < tilemap.alpha_cutoff) {
+        discard;
     }
+#endif
 
-    var color = in.color * textureSample(sprite_texture, sprite_sampler, in.uv + uv_offset);
+#ifdef BLEND_MULTIPLY
+    color = vec4<f32>(color.rgb * color.a, color.a);
+#endif
 
     return color;
 }
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    culling::world_chunk_positions, plugin::TileMapSystem, provider::ChunkProvider, unloading::ChunkUnloadEvent, Chunk,
+    TileMap,
+};
+
+/// Optional plugin for streaming chunks of infinite tilemaps in and out around cameras and other entities.
+///
+/// Add a [`TileMapStreaming`] component to a tilemap entity to stream it. Missing chunks near an anchor
+/// are generated by a [`ChunkProvider`] on background tasks, and chunks far from every anchor are unloaded.
+/// Anchors are 2D cameras (unless disabled in [`TileMapStreamingSettings`]) and entities with a [`StreamingAnchor`].
+///
+/// Requires [`SimpleTileMapPlugin`](crate::plugin::SimpleTileMapPlugin).
+#[derive(Default)]
+pub struct TileMapStreamingPlugin;
+
+impl Plugin for TileMapStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StreamingAnchor>()
+            .register_type::<TileMapStreamingSettings>()
+            .init_resource::<TileMapStreamingSettings>()
+            .add_systems(Update, stream_chunks_system.in_set(TileMapSystem::ProvideChunks));
+    }
+}
+
+/// Global settings for [`TileMapStreamingPlugin`]
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource, Debug, Default)]
+pub struct TileMapStreamingSettings {
+    /// Stream chunks around 2D cameras, in addition to [`StreamingAnchor`]s
+    pub follow_cameras: bool,
+}
+
+impl Default for TileMapStreamingSettings {
+    fn default() -> Self {
+        Self { follow_cameras: true }
+    }
+}
+
+/// Marks an entity, such as the player, that chunks of streamed tilemaps are loaded around
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component, Debug, Default)]
+pub struct StreamingAnchor;
+
+/// Stream chunks of a tilemap in and out around anchors, using a [`ChunkProvider`].
+///
+/// Unloaded chunks are sent as [`ChunkUnloadEvent`]s before being removed, so they can be saved.
+/// This replaces [`TileMapChunkProvider`](crate::provider::TileMapChunkProvider) and
+/// [`ChunkUnloading`](crate::unloading::ChunkUnloading), and should not be combined with them.
+#[derive(Component)]
+pub struct TileMapStreaming {
+    provider: Arc<dyn ChunkProvider>,
+    /// Layers to stream
+    pub layers: Vec<i32>,
+    /// Number of chunks around each anchor to load, in each direction
+    pub load_radius: u32,
+    /// Chunks further than this many chunks from every anchor are unloaded.
+    /// Keep this larger than `load_radius`, so that chunks at the edge don't load and unload repeatedly.
+    pub unload_radius: u32,
+    /// Maximum number of chunks being generated at the same time
+    pub max_pending: usize,
+    /// Chunks currently being generated
+    pending: HashMap<IVec3, Task<Option<Chunk>>>,
+    /// Chunk positions for which the provider returned no chunk, so they are not requested again
+    /// until they have been out of range.
+    empty: HashSet<IVec3>,
+}
+
+impl TileMapStreaming {
+    pub fn new(provider: impl ChunkProvider, layers: impl Into<Vec<i32>>) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            layers: layers.into(),
+            load_radius: 2,
+            unload_radius: 4,
+            max_pending: 8,
+            pending: HashMap::default(),
+            empty: HashSet::default(),
+        }
+    }
+
+    pub fn with_load_radius(mut self, load_radius: u32) -> Self {
+        self.load_radius = load_radius;
+        self
+    }
+
+    pub fn with_unload_radius(mut self, unload_radius: u32) -> Self {
+        self.unload_radius = unload_radius;
+        self
+    }
+
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Number of chunks currently being generated
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Unload distant chunks, insert generated chunks and start generating missing chunks near anchors
+fn stream_chunks_system(
+    mut tilemap_query: Query<(Entity, &mut TileMap, &mut TileMapStreaming, &GlobalTransform)>,
+    camera_query: Query<&GlobalTransform, With<Camera2d>>,
+    anchor_query: Query<&GlobalTransform, With<StreamingAnchor>>,
+    settings: Res<TileMapStreamingSettings>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+    mut unload_events: EventWriter<ChunkUnloadEvent>,
+) {
+    let camera_positions = camera_query
+        .iter()
+        .filter(|_| settings.follow_cameras)
+        .map(GlobalTransform::translation);
+
+    let anchor_positions: Vec<Vec3> = anchor_query
+        .iter()
+        .map(GlobalTransform::translation)
+        .chain(camera_positions)
+        .collect();
+
+    for (entity, mut tilemap, mut streaming, transform) in tilemap_query.iter_mut() {
+        let Some(anchor_chunks) =
+            world_chunk_positions(&tilemap, transform, anchor_positions.iter().copied(), &texture_atlases)
+        else {
+            continue;
+        };
+
+        // Without any anchors, there is nothing to measure distance from
+        if anchor_chunks.is_empty() {
+            continue;
+        }
+
+        let within = |chunk_pos: IVec3, radius: u32| {
+            anchor_chunks
+                .iter()
+                .any(|anchor_chunk| (chunk_pos.truncate() - *anchor_chunk).abs().max_element() <= radius as i32)
+        };
+
+        let streaming = &mut *streaming;
+        let load_radius = streaming.load_radius;
+        let unload_radius = streaming.unload_radius.max(load_radius);
+
+        // Unload distant chunks of the streamed layers
+        let distant_chunks: Vec<IVec3> = tilemap
+            .chunks
+            .keys()
+            .filter(|chunk_pos| streaming.layers.contains(&chunk_pos.z) && !within(**chunk_pos, unload_radius))
+            .copied()
+            .collect();
+
+        for chunk_pos in distant_chunks {
+            unload_events.send(ChunkUnloadEvent {
+                tilemap: entity,
+                chunk_pos,
+                chunk: tilemap.chunks[&chunk_pos].clone(),
+            });
+
+            tilemap.remove_chunk(chunk_pos);
+        }
+
+        // Cancel generation of chunks that are no longer needed, and forget empty chunks that have gone out of range
+        streaming
+            .pending
+            .retain(|chunk_pos, _| within(*chunk_pos, unload_radius));
+        streaming.empty.retain(|chunk_pos| within(*chunk_pos, load_radius));
+
+        // Insert chunks that have finished generating
+        let finished: Vec<IVec3> = streaming
+            .pending
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(chunk_pos, _)| *chunk_pos)
+            .collect();
+
+        for chunk_pos in finished {
+            let Some(task) = streaming.pending.remove(&chunk_pos) else {
+                continue;
+            };
+
+            match block_on(future::poll_once(task)).flatten() {
+                Some(chunk) => tilemap.insert_chunk(chunk),
+                None => {
+                    streaming.empty.insert(chunk_pos);
+                }
+            }
+        }
+
+        let available = streaming.max_pending.saturating_sub(streaming.pending.len());
+        if available == 0 {
+            continue;
+        }
+
+        // Missing chunks within the load radius of any anchor, nearest first
+        let radius = load_radius as i32;
+
+        let mut missing: Vec<(i32, IVec3)> = Vec::new();
+        let mut seen: HashSet<IVec3> = HashSet::default();
+
+        for anchor_chunk in anchor_chunks.iter() {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    for layer in streaming.layers.iter() {
+                        let chunk_pos = (*anchor_chunk + IVec2::new(x, y)).extend(*layer);
+
+                        // Chunks finished this frame are only queued for insertion until the tilemap updates
+                        if tilemap.chunks.contains_key(&chunk_pos)
+                            || tilemap.is_chunk_queued(chunk_pos)
+                            || streaming.pending.contains_key(&chunk_pos)
+                            || streaming.empty.contains(&chunk_pos)
+                            || !seen.insert(chunk_pos)
+                        {
+                            continue;
+                        }
+
+                        missing.push((x.abs().max(y.abs()), chunk_pos));
+                    }
+                }
+            }
+        }
+
+        missing.sort_by_key(|(distance, _)| *distance);
+
+        let task_pool = AsyncComputeTaskPool::get();
+
+        for (_, chunk_pos) in missing.into_iter().take(available) {
+            let provider = streaming.provider.clone();
+
+            let task = task_pool.spawn(async move { provider.generate(chunk_pos) });
+
+            streaming.pending.insert(chunk_pos, task);
+        }
+    }
+}
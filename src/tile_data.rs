@@ -0,0 +1,141 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::tilemap::{calc_chunk_origin, calc_chunk_pos, row_major_index, row_major_pos, TILES_PER_CHUNK};
+
+/// Per-tile user data, stored in the same chunked layout as the tiles of a [`TileMap`](crate::TileMap).
+///
+/// This is intended for gameplay data such as terrain type, durability or ownership,
+/// and is typically added as a component on the same entity as the tilemap.
+/// Unlike tiles, data is applied immediately rather than queued.
+#[derive(Component, Clone, Debug)]
+pub struct TileData<D: Send + Sync + 'static> {
+    chunks: HashMap<IVec3, DataChunk<D>>,
+}
+
+#[derive(Clone, Debug)]
+struct DataChunk<D> {
+    origin: IVec3,
+    data: Vec<Option<D>>,
+    count: usize,
+}
+
+impl<D: Send + Sync + 'static> Default for TileData<D> {
+    fn default() -> Self {
+        Self {
+            chunks: Default::default(),
+        }
+    }
+}
+
+impl<D> DataChunk<D> {
+    fn new(origin: IVec3) -> Self {
+        let mut data = Vec::with_capacity(TILES_PER_CHUNK);
+        data.resize_with(TILES_PER_CHUNK, || None);
+
+        Self { origin, data, count: 0 }
+    }
+
+    #[inline]
+    fn index(&self, pos: IVec3) -> usize {
+        let pos = pos - self.origin;
+        row_major_index(IVec2::new(pos.x, pos.y))
+    }
+}
+
+impl<D: Send + Sync + 'static> TileData<D> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get data for the tile at the specified position
+    pub fn get(&self, pos: IVec3) -> Option<&D> {
+        let chunk = self.chunks.get(&calc_chunk_pos(pos))?;
+
+        chunk.data[chunk.index(pos)].as_ref()
+    }
+
+    /// Get mutable data for the tile at the specified position
+    pub fn get_mut(&mut self, pos: IVec3) -> Option<&mut D> {
+        let chunk = self.chunks.get_mut(&calc_chunk_pos(pos))?;
+        let index = chunk.index(pos);
+
+        chunk.data[index].as_mut()
+    }
+
+    /// Set or remove data for the tile at the specified position, returning the previous data
+    pub fn set(&mut self, pos: IVec3, data: Option<D>) -> Option<D> {
+        let Some(data) = data else {
+            return self.remove(pos);
+        };
+
+        let chunk_pos = calc_chunk_pos(pos);
+        let chunk = self
+            .chunks
+            .entry(chunk_pos)
+            .or_insert_with(|| DataChunk::new(calc_chunk_origin(chunk_pos)));
+
+        let index = chunk.index(pos);
+        let previous = chunk.data[index].replace(data);
+
+        if previous.is_none() {
+            chunk.count += 1;
+        }
+
+        previous
+    }
+
+    /// Remove data for the tile at the specified position, returning it
+    pub fn remove(&mut self, pos: IVec3) -> Option<D> {
+        let chunk_pos = calc_chunk_pos(pos);
+        let chunk = self.chunks.get_mut(&chunk_pos)?;
+
+        let index = chunk.index(pos);
+        let previous = chunk.data[index].take();
+
+        if previous.is_some() {
+            chunk.count -= 1;
+
+            // Free chunks that no longer contain any data
+            if chunk.count == 0 {
+                self.chunks.remove(&chunk_pos);
+            }
+        }
+
+        previous
+    }
+
+    /// Remove all data
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+
+    /// Remove all data on the specified layer
+    pub fn clear_layer(&mut self, layer: i32) {
+        self.chunks.retain(|pos, _| pos.z != layer);
+    }
+
+    /// Iterate over all tile positions that have data
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &D)> {
+        self.chunks.values().flat_map(|chunk| {
+            chunk.data.iter().enumerate().filter_map(|(i, data)| {
+                data.as_ref()
+                    .map(|data| (chunk.origin + row_major_pos(i).extend(0), data))
+            })
+        })
+    }
+
+    /// Iterate over the tile positions that have data within the chunk at the specified chunk position
+    pub(crate) fn iter_chunk(&self, chunk_pos: IVec3) -> impl Iterator<Item = (IVec3, &D)> {
+        self.chunks.get(&chunk_pos).into_iter().flat_map(|chunk| {
+            chunk.data.iter().enumerate().filter_map(|(i, data)| {
+                data.as_ref()
+                    .map(|data| (chunk.origin + row_major_pos(i).extend(0), data))
+            })
+        })
+    }
+
+    /// Iterate over the positions of chunks that contain data
+    pub(crate) fn chunk_positions(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.chunks.keys().copied()
+    }
+}
@@ -0,0 +1,607 @@
+use std::{
+    fmt,
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    render::view::RenderLayers,
+    tasks::block_on,
+    utils::{HashMap, Instant},
+};
+use tiled::{
+    ChunkData, FiniteTileLayer, InfiniteTileLayer, LayerTile, LayerType, ObjectShape, Orientation, PropertyValue,
+    TileLayer,
+};
+
+use crate::{
+    collision::{TileCollision, TileCollisionMap, TileCollisionShape, TileCollisionShapes},
+    projection::TileMapProjection,
+    tilemap::calc_chunk_pos,
+    Chunk, Tile, TileAnimation, TileFlags, TileMap, TileMapLayer, TileMapLayerProperty,
+};
+
+/// Map loaded from a Tiled `.tmx` file, along with the `.tsx` tilesets it references.
+///
+/// Each tile layer becomes a layer of the tilemap, numbered from 0 in the order they appear in Tiled.
+/// Tiled's y axis points down, so the tile at (x, y) in Tiled is placed at (x, -y), and the top left of the map
+/// is at the origin. With isometric maps it is placed at (-y, -x), which keeps the same layout.
+///
+/// Flip flags, tile animations, and layer names, properties, offsets, parallax, opacity, tint and visibility
+/// are carried over. Orthogonal and isometric (diamond) maps are supported, with tilesets based on
+/// a single image. Each layer must only use tiles from one tileset. Diagonal flips and object layers are ignored.
+/// Layers of infinite maps are converted chunk by chunk, so large maps don't need a dense grid of tiles.
+///
+/// Rectangles and polygons drawn in Tiled's tile collision editor become [`TileCollisionShapes`], and tiles with them
+/// are made solid in a [`TileCollisionMap`]. Rotated collision objects and other shapes are ignored.
+///
+/// Spawn a [`TiledMapRoot`] to instantiate the map.
+#[derive(Asset, TypePath, Debug)]
+pub struct TiledMap {
+    /// Tilesets by their index in the map. Tilesets that are not supported are `None`.
+    pub tilesets: Vec<Option<TiledTileset>>,
+    pub layers: Vec<TiledLayer>,
+    pub projection: TileMapProjection,
+}
+
+/// Tileset of a [`TiledMap`]
+#[derive(Clone, Debug)]
+pub struct TiledTileset {
+    pub image: Handle<Image>,
+    pub texture_atlas_layout: Handle<TextureAtlasLayout>,
+    /// Collision shapes of tiles by their id in the tileset
+    pub collision_shapes: HashMap<u32, Vec<TileCollisionShape>>,
+}
+
+/// Tile layer of a [`TiledMap`]
+#[derive(Clone, Debug)]
+pub struct TiledLayer {
+    /// Tilemap layer the tiles are placed on
+    pub z: i32,
+    /// Index of the tileset used by the layer's tiles, if it has any
+    pub tileset: Option<usize>,
+    pub settings: TileMapLayer,
+    /// Tiles of the layer, by chunk position
+    pub chunks: HashMap<IVec3, Chunk>,
+    pub animations: Vec<(IVec2, TileAnimation)>,
+}
+
+impl TiledMap {
+    /// Build the tilemap from the map, queuing its tiles.
+    /// Existing tiles, layer settings and tile animations are replaced.
+    fn build(&self, tilemap: &mut TileMap) {
+        // The tilemap uses the first tileset, and layers using other tilesets override it
+        let base_tileset = self.tilesets.iter().position(Option::is_some);
+
+        if let Some(tileset) = base_tileset.and_then(|index| self.tilesets[index].as_ref()) {
+            tilemap.image = tileset.image.clone();
+            tilemap.texture_atlas_layout = tileset.texture_atlas_layout.clone();
+        }
+
+        tilemap.clear();
+        tilemap.projection = self.projection;
+        tilemap.tile_animations.clear();
+        tilemap.layers.clear();
+        tilemap.layers_changed_at = Instant::now();
+
+        for layer in self.layers.iter() {
+            let mut settings = layer.settings.clone();
+
+            if layer.tileset != base_tileset {
+                if let Some(tileset) = layer.tileset.and_then(|index| self.tilesets[index].as_ref()) {
+                    settings.image = Some(tileset.image.clone());
+                    settings.texture_atlas_layout = Some(tileset.texture_atlas_layout.clone());
+                }
+            }
+
+            tilemap.set_layer(layer.z, settings);
+
+            for chunk in layer.chunks.values() {
+                tilemap.insert_chunk(chunk.clone());
+            }
+
+            for (pos, animation) in layer.animations.iter() {
+                tilemap.set_tile_animation(pos.extend(layer.z), Some(animation.clone()));
+            }
+        }
+    }
+
+    /// Build collision data from the collision shapes of the tilesets, where tiles with shapes are solid.
+    /// Returns `None` if no tileset has collision shapes.
+    fn build_collision(&self) -> Option<(TileCollisionMap, TileCollisionShapes)> {
+        if self
+            .tilesets
+            .iter()
+            .flatten()
+            .all(|tileset| tileset.collision_shapes.is_empty())
+        {
+            return None;
+        }
+
+        let base_tileset = self.tilesets.iter().position(Option::is_some);
+
+        let mut collision_map = TileCollisionMap::new();
+        let mut shapes = TileCollisionShapes::new();
+
+        if let Some(tileset) = base_tileset.and_then(|index| self.tilesets[index].as_ref()) {
+            for (id, tile_shapes) in tileset.collision_shapes.iter() {
+                shapes.insert(*id, tile_shapes.iter().cloned());
+            }
+        }
+
+        for layer in self.layers.iter() {
+            let Some(tileset) = layer.tileset.and_then(|index| self.tilesets[index].as_ref()) else {
+                continue;
+            };
+
+            if layer.tileset != base_tileset {
+                for (id, tile_shapes) in tileset.collision_shapes.iter() {
+                    shapes.insert_layer(layer.z, *id, tile_shapes.iter().cloned());
+                }
+            }
+
+            for chunk in layer.chunks.values() {
+                for (pos, tile) in chunk.iter_tiles() {
+                    if tileset.collision_shapes.contains_key(&tile.sprite_index) {
+                        collision_map.set(pos, Some(TileCollision::SOLID));
+                    }
+                }
+            }
+        }
+
+        Some((collision_map, shapes))
+    }
+}
+
+/// Error loading a [`TiledMap`]
+#[derive(Debug)]
+pub enum TiledMapLoaderError {
+    Io(io::Error),
+    Tiled(tiled::Error),
+    /// Only orthogonal and isometric maps are supported
+    UnsupportedOrientation(Orientation),
+}
+
+impl fmt::Display for TiledMapLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read map: {err}"),
+            Self::Tiled(err) => write!(f, "could not parse map: {err}"),
+            Self::UnsupportedOrientation(orientation) => write!(f, "unsupported map orientation {orientation}"),
+        }
+    }
+}
+
+impl std::error::Error for TiledMapLoaderError {}
+
+impl From<io::Error> for TiledMapLoaderError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<tiled::Error> for TiledMapLoaderError {
+    fn from(err: tiled::Error) -> Self {
+        Self::Tiled(err)
+    }
+}
+
+/// Reads the map from the bytes being loaded, and the files it references, such as tilesets, as assets.
+/// This makes them dependencies of the map, so it is reloaded when they change.
+struct AssetResourceReader<'a, 'ctx> {
+    map_path: PathBuf,
+    map_bytes: Vec<u8>,
+    load_context: &'a mut LoadContext<'ctx>,
+}
+
+impl tiled::ResourceReader for AssetResourceReader<'_, '_> {
+    type Resource = Cursor<Vec<u8>>;
+    type Error = io::Error;
+
+    fn read_from(&mut self, path: &Path) -> Result<Self::Resource, Self::Error> {
+        if path == self.map_path {
+            return Ok(Cursor::new(self.map_bytes.clone()));
+        }
+
+        let bytes = block_on(self.load_context.read_asset_bytes(path.to_path_buf())).map_err(io::Error::other)?;
+
+        Ok(Cursor::new(bytes))
+    }
+}
+
+/// Loads [`TiledMap`]s from `.tmx` files
+#[derive(Default)]
+pub struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    type Asset = TiledMap;
+    type Settings = ();
+    type Error = TiledMapLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut map_bytes = Vec::new();
+        reader.read_to_end(&mut map_bytes).await?;
+
+        let map_path = load_context.path().to_path_buf();
+
+        let map = tiled::Loader::with_reader(AssetResourceReader {
+            map_path: map_path.clone(),
+            map_bytes,
+            load_context,
+        })
+        .load_tmx_map(&map_path)?;
+
+        let projection = match map.orientation {
+            Orientation::Orthogonal => TileMapProjection::Orthogonal,
+            Orientation::Isometric => TileMapProjection::Isometric {
+                tile_size: Vec2::new(map.tile_width as f32, map.tile_height as f32),
+            },
+            orientation => return Err(TiledMapLoaderError::UnsupportedOrientation(orientation)),
+        };
+
+        let tilesets: Vec<Option<TiledTileset>> = map
+            .tilesets()
+            .iter()
+            .enumerate()
+            .map(|(index, tileset)| {
+                // Tilesets made from a collection of images are not supported
+                let image = tileset.image.as_ref()?;
+
+                let columns = tileset.columns.max(1);
+                let rows = tileset.tilecount.div_ceil(columns);
+
+                let texture_atlas_layout = TextureAtlasLayout::from_grid(
+                    UVec2::new(tileset.tile_width, tileset.tile_height),
+                    columns,
+                    rows,
+                    Some(UVec2::splat(tileset.spacing)),
+                    Some(UVec2::splat(tileset.margin)),
+                );
+
+                let tile_size = Vec2::new(tileset.tile_width as f32, tileset.tile_height as f32);
+
+                let collision_shapes = tileset
+                    .tiles()
+                    .filter_map(|(id, tile)| {
+                        let tile_shapes: Vec<TileCollisionShape> = tile
+                            .collision
+                            .as_ref()?
+                            .object_data()
+                            .iter()
+                            .filter_map(|object| convert_collision_object(object, tile_size))
+                            .collect();
+
+                        (!tile_shapes.is_empty()).then_some((id, tile_shapes))
+                    })
+                    .collect();
+
+                Some(TiledTileset {
+                    image: load_context.load(image.source.clone()),
+                    texture_atlas_layout: load_context
+                        .add_labeled_asset(format!("tileset{index}"), texture_atlas_layout),
+                    collision_shapes,
+                })
+            })
+            .collect();
+
+        let mut layers: Vec<TiledLayer> = Vec::new();
+        collect_layers(map.layers(), &LayerContext::default(), projection, &mut layers);
+
+        Ok(TiledMap {
+            tilesets,
+            layers,
+            projection,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx"]
+    }
+}
+
+/// Settings inherited from group layers
+#[derive(Clone)]
+struct LayerContext {
+    offset: Vec2,
+    parallax: Vec2,
+    opacity: f32,
+    /// Linear RGBA tint
+    tint: Vec4,
+    visible: bool,
+}
+
+impl Default for LayerContext {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            parallax: Vec2::ONE,
+            opacity: 1.0,
+            tint: Vec4::ONE,
+            visible: true,
+        }
+    }
+}
+
+/// Convert the tile layers, including those within group layers, to [`TiledLayer`]s
+fn collect_layers<'map>(
+    tiled_layers: impl Iterator<Item = tiled::Layer<'map>>,
+    parent: &LayerContext,
+    projection: TileMapProjection,
+    layers: &mut Vec<TiledLayer>,
+) {
+    for tiled_layer in tiled_layers {
+        let tint = tiled_layer
+            .tint_color
+            .map(|color| {
+                Color::srgba_u8(color.red, color.green, color.blue, color.alpha)
+                    .to_linear()
+                    .to_vec4()
+            })
+            .unwrap_or(Vec4::ONE);
+
+        let context = LayerContext {
+            // Tiled's y axis points down
+            offset: parent.offset + Vec2::new(tiled_layer.offset_x, -tiled_layer.offset_y),
+            parallax: parent.parallax * Vec2::new(tiled_layer.parallax_x, tiled_layer.parallax_y),
+            opacity: parent.opacity * tiled_layer.opacity,
+            tint: parent.tint * tint,
+            visible: parent.visible && tiled_layer.visible,
+        };
+
+        let tile_layer = match tiled_layer.layer_type() {
+            LayerType::Tiles(tile_layer) => tile_layer,
+            LayerType::Group(group) => {
+                collect_layers(group.layers(), &context, projection, layers);
+                continue;
+            }
+            _ => continue,
+        };
+
+        let settings = TileMapLayer {
+            name: Some(tiled_layer.name.clone()),
+            properties: tiled_layer
+                .properties
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), convert_property(value)?)))
+                .collect(),
+            parallax: context.parallax,
+            offset: context.offset,
+            render_layers: (!context.visible).then(RenderLayers::none),
+            ..default()
+        };
+
+        let mut layer = TiledLayer {
+            z: layers.len() as i32,
+            tileset: None,
+            settings,
+            chunks: HashMap::default(),
+            animations: Vec::new(),
+        };
+
+        match tile_layer {
+            TileLayer::Finite(tile_layer) => collect_finite_tiles(&tile_layer, &context, projection, &mut layer),
+            TileLayer::Infinite(tile_layer) => collect_infinite_tiles(&tile_layer, &context, projection, &mut layer),
+        }
+
+        layers.push(layer);
+    }
+}
+
+/// Convert the tiles of a finite layer
+fn collect_finite_tiles(
+    tile_layer: &FiniteTileLayer,
+    context: &LayerContext,
+    projection: TileMapProjection,
+    layer: &mut TiledLayer,
+) {
+    let mut pending = PendingTiles::default();
+
+    for y in 0..tile_layer.height() as i32 {
+        for x in 0..tile_layer.width() as i32 {
+            if let Some(tiled_tile) = tile_layer.get_tile(x, y) {
+                add_tile(IVec2::new(x, y), tiled_tile, context, projection, layer, &mut pending);
+            }
+        }
+    }
+
+    pending.flush(layer);
+}
+
+/// Convert the tiles of an infinite layer, one Tiled chunk at a time.
+/// Tiled chunks are written into the tilemap chunks they overlap, which may have a different size,
+/// so only chunks with tiles are created and no dense grid of the whole layer is allocated.
+fn collect_infinite_tiles(
+    tile_layer: &InfiniteTileLayer,
+    context: &LayerContext,
+    projection: TileMapProjection,
+    layer: &mut TiledLayer,
+) {
+    let chunk_size = IVec2::new(ChunkData::WIDTH as i32, ChunkData::HEIGHT as i32);
+    let mut pending = PendingTiles::default();
+
+    for ((chunk_x, chunk_y), chunk) in tile_layer.chunks() {
+        let chunk_origin = IVec2::new(chunk_x, chunk_y) * chunk_size;
+
+        for y in 0..chunk_size.y {
+            for x in 0..chunk_size.x {
+                if let Some(tiled_tile) = chunk.get_tile(x, y) {
+                    let pos = chunk_origin + IVec2::new(x, y);
+                    add_tile(pos, tiled_tile, context, projection, layer, &mut pending);
+                }
+            }
+        }
+
+        pending.flush(layer);
+    }
+}
+
+/// Converted tiles waiting to be written to their chunk, by chunk position
+#[derive(Default)]
+struct PendingTiles(HashMap<IVec3, Vec<(IVec3, Option<Tile>)>>);
+
+impl PendingTiles {
+    /// Write the pending tiles to the layer's chunks, creating them as needed
+    fn flush(&mut self, layer: &mut TiledLayer) {
+        for (chunk_pos, tiles) in self.0.drain() {
+            layer
+                .chunks
+                .entry(chunk_pos)
+                .or_insert_with(|| Chunk::from_chunk_pos(chunk_pos))
+                .set_tiles(tiles);
+        }
+    }
+}
+
+/// Convert a tile at a position in Tiled's coordinates
+fn add_tile(
+    tiled_pos: IVec2,
+    tiled_tile: LayerTile,
+    context: &LayerContext,
+    projection: TileMapProjection,
+    layer: &mut TiledLayer,
+    pending: &mut PendingTiles,
+) {
+    let tileset_index = tiled_tile.tileset_index();
+
+    // Tiles of other tilesets than the layer's first can't be drawn by the layer
+    if *layer.tileset.get_or_insert(tileset_index) != tileset_index {
+        warn!(
+            "Skipping tile at {tiled_pos} of layer {}, as it uses a different tileset than the layer",
+            layer.z
+        );
+        return;
+    }
+
+    let pos = match projection {
+        TileMapProjection::Isometric { .. } => IVec3::new(-tiled_pos.y, -tiled_pos.x, layer.z),
+        _ => IVec3::new(tiled_pos.x, -tiled_pos.y, layer.z),
+    };
+
+    let mut flags = TileFlags::empty();
+    flags.set(TileFlags::FLIP_X, tiled_tile.flip_h);
+    flags.set(TileFlags::FLIP_Y, tiled_tile.flip_v);
+
+    let tile = Tile {
+        sprite_index: tiled_tile.id(),
+        color: Color::from(LinearRgba::from_vec4(
+            context.tint * Vec4::new(1.0, 1.0, 1.0, context.opacity),
+        )),
+        flags,
+        ..default()
+    };
+
+    pending
+        .0
+        .entry(calc_chunk_pos(pos))
+        .or_default()
+        .push((pos, Some(tile)));
+
+    let animation = tiled_tile
+        .get_tile()
+        .and_then(|tile_data| tile_data.animation.clone())
+        .filter(|frames| !frames.is_empty());
+
+    if let Some(frames) = animation {
+        // Frames are shown for the duration of the first frame, as animations have a fixed frame duration
+        layer.animations.push((
+            pos.truncate(),
+            TileAnimation::new(
+                frames.iter().map(|frame| frame.tile_id).collect::<Vec<u32>>(),
+                Duration::from_millis(frames[0].duration as u64),
+            ),
+        ));
+    }
+}
+
+/// Convert an object of a tile's collision shapes, in pixels from the top left of the tile, to a collision shape
+fn convert_collision_object(object: &tiled::ObjectData, tile_size: Vec2) -> Option<TileCollisionShape> {
+    if object.rotation != 0.0 {
+        return None;
+    }
+
+    // Tiled's y axis points down
+    let convert = |x: f32, y: f32| Vec2::new(x / tile_size.x - 0.5, 0.5 - y / tile_size.y);
+
+    match &object.shape {
+        ObjectShape::Rect { width, height } => Some(TileCollisionShape::Rect(Rect::from_corners(
+            convert(object.x, object.y),
+            convert(object.x + width, object.y + height),
+        ))),
+        ObjectShape::Polygon { points } => Some(TileCollisionShape::Polygon(
+            points
+                .iter()
+                .map(|(x, y)| convert(object.x + x, object.y + y))
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+fn convert_property(value: &PropertyValue) -> Option<TileMapLayerProperty> {
+    Some(match value {
+        PropertyValue::BoolValue(value) => TileMapLayerProperty::Bool(*value),
+        PropertyValue::IntValue(value) => TileMapLayerProperty::Int(*value as i64),
+        PropertyValue::FloatValue(value) => TileMapLayerProperty::Float(*value as f64),
+        PropertyValue::StringValue(value) | PropertyValue::FileValue(value) => {
+            TileMapLayerProperty::String(value.clone())
+        }
+        PropertyValue::ColorValue(color) => {
+            TileMapLayerProperty::Color(Color::srgba_u8(color.red, color.green, color.blue, color.alpha))
+        }
+        _ => return None,
+    })
+}
+
+/// Instantiates a [`TiledMap`] as a [`TileMap`] on the same entity, once it is loaded.
+/// The tilemap and its collision data are rebuilt when the map or its tilesets change,
+/// discarding any changes made to them.
+#[derive(Component, Clone, Debug, Deref)]
+pub struct TiledMapRoot(pub Handle<TiledMap>);
+
+/// Build the tilemaps of [`TiledMapRoot`]s when their map is loaded or changes
+pub(crate) fn instantiate_tiled_maps_system(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<TiledMap>>,
+    tiled_maps: Res<Assets<TiledMap>>,
+    mut root_query: Query<(Entity, Ref<TiledMapRoot>, Option<&mut TileMap>)>,
+) {
+    let changed_maps: Vec<AssetId<TiledMap>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, root, tilemap) in root_query.iter_mut() {
+        if !root.is_changed() && !changed_maps.contains(&root.id()) {
+            continue;
+        }
+
+        // Not loaded yet. The tilemap is built once it is.
+        let Some(tiled_map) = tiled_maps.get(&root.0) else {
+            continue;
+        };
+
+        match tilemap {
+            Some(mut tilemap) => tiled_map.build(&mut tilemap),
+            None => {
+                let mut tilemap = TileMap::new(Handle::default(), Handle::default());
+                tiled_map.build(&mut tilemap);
+                commands.entity(entity).insert(tilemap);
+            }
+        }
+
+        if let Some(collision) = tiled_map.build_collision() {
+            commands.entity(entity).insert(collision);
+        }
+    }
+}
@@ -1,90 +1,910 @@
 use bitflags::bitflags;
 
+use std::time::Duration;
+
 use bevy::{
     prelude::*,
-    render::sync_world::SyncToRenderWorld,
+    reflect::ReflectRef,
+    render::{primitives::Aabb, sync_world::SyncToRenderWorld},
+    sprite::AlphaMode2d,
     utils::{HashMap, HashSet, Instant},
 };
 
-pub(crate) const CHUNK_WIDTH: u32 = 64;
-pub(crate) const CHUNK_HEIGHT: u32 = 64;
+use crate::animation::{TileAnimation, TileFade};
+use crate::culling::TileMapCulling;
+use crate::dirty::{DirtyCause, DirtyChunks};
+use crate::extension::{ChunkLifecycle, ChunkLifecycleEvent};
+use crate::layer::TileMapLayer;
+use crate::projection::TileMapProjection;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of tiles along each axis of a chunk, selected at compile time by the `chunk-32` and `chunk-128` features.
+/// Being a constant allows the compiler to unroll and vectorize loops over the tiles of a chunk.
+/// If both features are enabled, such as with `--all-features`, the larger size wins.
+#[cfg(all(feature = "chunk-32", not(feature = "chunk-128")))]
+const CHUNK_EDGE: u32 = 32;
+#[cfg(feature = "chunk-128")]
+const CHUNK_EDGE: u32 = 128;
+#[cfg(not(any(feature = "chunk-32", feature = "chunk-128")))]
+const CHUNK_EDGE: u32 = 64;
+
+pub(crate) const CHUNK_WIDTH: u32 = CHUNK_EDGE;
+pub(crate) const CHUNK_HEIGHT: u32 = CHUNK_EDGE;
 const CHUNK_WIDTH_I32: i32 = CHUNK_WIDTH as i32;
 const CHUNK_HEIGHT_I32: i32 = CHUNK_HEIGHT as i32;
 const CHUNK_WIDTH_USIZE: usize = CHUNK_WIDTH as usize;
 
-const TILES_PER_CHUNK: usize = (CHUNK_WIDTH * CHUNK_HEIGHT) as usize;
+pub(crate) const CHUNK_SIZE: UVec2 = UVec2::new(CHUNK_WIDTH, CHUNK_HEIGHT);
+
+pub(crate) const TILES_PER_CHUNK: usize = (CHUNK_WIDTH * CHUNK_HEIGHT) as usize;
+const OCCUPANCY_WORDS: usize = TILES_PER_CHUNK / 64;
+
+/// Store the tiles of chunks with few occupied tiles in a map, instead of an array of every tile in the chunk
+const SPARSE_CHUNKS: bool = cfg!(feature = "sparse-chunks");
 
-#[derive(Clone, Debug)]
+/// Sparse chunks with more tiles than this switch to dense storage.
+/// Dense chunks switch back to sparse storage once they have half as many tiles or fewer,
+/// so that chunks near the threshold don't switch back and forth.
+const SPARSE_MAX_TILES: usize = TILES_PER_CHUNK / 4;
+
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug, from_reflect = false)]
+#[cfg_attr(feature = "serde", derive(Serialize), reflect(Serialize, Deserialize))]
 pub struct Chunk {
     pub origin: IVec3,
-    pub tiles: Vec<Option<Tile>>,
+    tiles: ChunkTiles,
+    #[reflect(ignore)]
+    #[reflect(default = "Instant::now")]
+    #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
     pub last_change_at: Instant,
+    /// Bounds of the occupied tiles in the chunk, in tile coordinates (inclusive)
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bounds: Option<IRect>,
+    /// Bitset of occupied tiles, indexed by row major index
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    occupancy: Vec<u64>,
+}
+
+/// Only the origin and tiles of a chunk are reflected.
+/// The occupancy and bounds are rebuilt from the tiles, like when deserializing.
+impl FromReflect for Chunk {
+    fn from_reflect(reflect: &dyn PartialReflect) -> Option<Self> {
+        let ReflectRef::Struct(chunk) = reflect.reflect_ref() else {
+            return None;
+        };
+
+        let origin = IVec3::from_reflect(chunk.field("origin")?)?;
+        let tiles = ChunkTiles::from_reflect(chunk.field("tiles")?)?;
+
+        Some(Self::with_tiles(origin, tiles))
+    }
+}
+
+/// Tiles of a chunk, indexed by row major index.
+///
+/// Reflected as an opaque value, which can only be serialized with the `serde` feature.
+/// Serialized as runs of occupied tiles, so that chunks in scenes don't list every empty tile.
+#[derive(Clone, Debug, Reflect)]
+#[reflect(opaque, Debug)]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
+enum ChunkTiles {
+    /// Only occupied tiles are stored, which saves memory for chunks with few tiles
+    Sparse(HashMap<u16, Tile>),
+    /// Every tile in the chunk is stored, which is faster to access and smaller for chunks with many tiles
+    Dense(Vec<Option<Tile>>),
+}
+
+impl ChunkTiles {
+    fn new() -> Self {
+        if SPARSE_CHUNKS {
+            Self::Sparse(HashMap::default())
+        } else {
+            Self::Dense(vec![None; TILES_PER_CHUNK])
+        }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> Option<&Tile> {
+        match self {
+            Self::Sparse(tiles) => tiles.get(&(index as u16)),
+            Self::Dense(tiles) => tiles[index].as_ref(),
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, index: usize, tile: Option<Tile>) {
+        match self {
+            Self::Sparse(tiles) => match tile {
+                Some(tile) => {
+                    tiles.insert(index as u16, tile);
+                }
+                None => {
+                    tiles.remove(&(index as u16));
+                }
+            },
+            Self::Dense(tiles) => tiles[index] = tile,
+        }
+    }
+
+    fn make_dense(&mut self) {
+        if let Self::Sparse(sparse_tiles) = self {
+            let mut tiles = vec![None; TILES_PER_CHUNK];
+
+            for (index, tile) in sparse_tiles.drain() {
+                tiles[index as usize] = Some(tile);
+            }
+
+            *self = Self::Dense(tiles);
+        }
+    }
+
+    fn make_sparse(&mut self) {
+        if let Self::Dense(dense_tiles) = self {
+            let tiles = dense_tiles
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(index, tile)| Some((index as u16, tile.take()?)))
+                .collect();
+
+            *self = Self::Sparse(tiles);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ChunkTiles {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tiles: Vec<(usize, &Tile)> = match self {
+            Self::Sparse(tiles) => tiles.iter().map(|(index, tile)| (*index as usize, tile)).collect(),
+            Self::Dense(tiles) => tiles
+                .iter()
+                .enumerate()
+                .filter_map(|(index, tile)| Some((index, tile.as_ref()?)))
+                .collect(),
+        };
+
+        tiles.sort_unstable_by_key(|(index, _)| *index);
+
+        // Runs of consecutive tiles, by the index of their first tile
+        let mut runs: Vec<(u16, Vec<&Tile>)> = Vec::new();
+
+        for (index, tile) in tiles {
+            match runs.last_mut() {
+                Some((start, run)) if *start as usize + run.len() == index => run.push(tile),
+                _ => runs.push((index as u16, vec![tile])),
+            }
+        }
+
+        runs.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ChunkTiles {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let runs: Vec<(u16, Vec<Tile>)> = Vec::deserialize(deserializer)?;
+
+        let mut tiles = Self::new();
+
+        for (start, run) in runs {
+            for (index, tile) in (start as usize..).zip(run) {
+                if index >= TILES_PER_CHUNK {
+                    return Err(serde::de::Error::custom(format!("invalid tile index {index}")));
+                }
+
+                tiles.set(index, Some(tile));
+            }
+        }
+
+        Ok(tiles)
+    }
+}
+
+/// Only the origin and tiles of a chunk are deserialized.
+/// The occupancy and bounds are rebuilt from the tiles, rather than trusted from the input.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct ChunkData {
+            origin: IVec3,
+            tiles: ChunkTiles,
+        }
+
+        let data = ChunkData::deserialize(deserializer)?;
+
+        Ok(Chunk::with_tiles(data.origin, data.tiles))
+    }
 }
 
 bitflags! {
-    #[derive(Clone, Copy, Debug, Default)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+    #[reflect(opaque, Debug, Default, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
     pub struct TileFlags: u32 {
         const FLIP_X = 1 << 0;
         const FLIP_Y = 1 << 1;
+        /// The tile is not affected by ambient light or lighting, so its color is used as-is.
+        /// Combined with color values above 1.0 and an HDR camera, this lets tiles glow with bloom.
+        const EMISSIVE = 1 << 2;
+        /// The tile blocks light. See [`TileMapOccluders`](crate::occluders::TileMapOccluders).
+        const OCCLUDER = 1 << 3;
+        /// Bits used to store the shape of the tile. Use [`TileFlags::with_shape`] to set it.
+        const SHAPE = 0xf << 8;
+        /// Bits used to store the depth bias. Use [`TileFlags::with_depth_bias`] to set it.
+        const DEPTH_BIAS = 0xff << 24;
     }
 }
 
-#[derive(Clone, Debug, Default)]
+impl TileFlags {
+    const SHAPE_SHIFT: u32 = 8;
+    const DEPTH_BIAS_SHIFT: u32 = 24;
+
+    /// Amount of z added to the tile per step of depth bias, in units of [`TileMap::layer_z_scale`].
+    /// This keeps the maximum bias within half a layer.
+    pub const DEPTH_BIAS_STEP: f32 = 1.0 / 256.0;
+
+    /// Return flags with the depth bias set to the specified value.
+    ///
+    /// Tiles with a higher depth bias are drawn in front of tiles with a lower one on the same layer,
+    /// without needing to be moved to another layer.
+    #[inline]
+    pub const fn with_depth_bias(self, bias: i8) -> Self {
+        let bits = self.bits() & !Self::DEPTH_BIAS.bits();
+
+        Self::from_bits_retain(bits | ((bias as u8 as u32) << Self::DEPTH_BIAS_SHIFT))
+    }
+
+    /// Get the depth bias
+    #[inline]
+    pub const fn depth_bias(&self) -> i8 {
+        ((self.bits() & Self::DEPTH_BIAS.bits()) >> Self::DEPTH_BIAS_SHIFT) as u8 as i8
+    }
+
+    /// Return flags with the shape set to the specified shape
+    #[inline]
+    pub const fn with_shape(self, shape: TileShape) -> Self {
+        let bits = self.bits() & !Self::SHAPE.bits();
+
+        Self::from_bits_retain(bits | ((shape as u32) << Self::SHAPE_SHIFT))
+    }
+
+    /// Get the shape
+    #[inline]
+    pub const fn shape(&self) -> TileShape {
+        TileShape::from_bits((self.bits() & Self::SHAPE.bits()) >> Self::SHAPE_SHIFT)
+    }
+}
+
+/// Shape of the quad a tile is rendered with.
+///
+/// Parts of the sprite outside the shape are not drawn, which gives slope and half tiles correct silhouettes
+/// without relying on transparent pixels. The shape is flipped along with the sprite.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Default, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+#[repr(u32)]
+pub enum TileShape {
+    /// The whole tile
+    #[default]
+    Full = 0,
+    /// Triangle with its right angle in the bottom left corner, such as a slope going down to the right
+    TriangleBottomLeft = 1,
+    /// Triangle with its right angle in the bottom right corner, such as a slope going up to the right
+    TriangleBottomRight = 2,
+    /// Triangle with its right angle in the top left corner
+    TriangleTopLeft = 3,
+    /// Triangle with its right angle in the top right corner
+    TriangleTopRight = 4,
+    /// Bottom half of the tile
+    HalfBottom = 5,
+    /// Top half of the tile
+    HalfTop = 6,
+    /// Left half of the tile
+    HalfLeft = 7,
+    /// Right half of the tile
+    HalfRight = 8,
+}
+
+impl TileShape {
+    /// Get the shape stored in the shape bits of [`TileFlags`]. Unknown values are treated as [`TileShape::Full`].
+    #[inline]
+    const fn from_bits(bits: u32) -> Self {
+        match bits {
+            1 => Self::TriangleBottomLeft,
+            2 => Self::TriangleBottomRight,
+            3 => Self::TriangleTopLeft,
+            4 => Self::TriangleTopRight,
+            5 => Self::HalfBottom,
+            6 => Self::HalfTop,
+            7 => Self::HalfLeft,
+            8 => Self::HalfRight,
+            _ => Self::Full,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Reflect)]
+#[reflect(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
 pub struct Tile {
     pub sprite_index: u32,
     pub color: Color,
     pub flags: TileFlags,
+    /// Size to render the tile at, in pixels, instead of the size of the sprite.
+    /// The quad is centered on the tile's grid cell, so it will extend into neighboring cells if larger.
+    pub custom_size: Option<Vec2>,
+}
+
+/// Determines how tile colors are passed to the shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), reflect(Serialize, Deserialize))]
+pub enum TileColorMode {
+    /// Colors are converted to linear RGBA before being multiplied with the texture,
+    /// which is the same as Bevy does for sprites. Tinted tiles will look identical to
+    /// identically tinted sprites.
+    #[default]
+    Linear,
+    /// The sRGB components of colors are passed to the shader as-is, without any conversion.
+    /// This will result in tinted tiles looking different from identically tinted sprites,
+    /// and is only provided for compatibility with maps authored for this behavior.
+    LegacySrgb,
 }
 
-#[derive(Component, Debug)]
+/// A tilemap, storing tiles in chunks of 64x64 tiles per layer.
+///
+/// The chunk size can be changed to 32x32 or 128x128 with the `chunk-32` or `chunk-128` feature,
+/// with `chunk-128` taking precedence if both are enabled.
+/// Smaller chunks are remeshed faster when tiles change,
+/// while larger chunks reduce per-chunk overhead for big static maps.
+///
+/// # Coordinate limits
+///
+/// Tile positions may use the full range of `i32` on every axis.
+/// Neighbor lookups that would step outside of this range are skipped rather than wrapping around.
+///
+/// Vertices are generated relative to their chunk, and the chunk's offset is combined with the tilemap's
+/// transform and made relative to the camera in double precision. This avoids jitter and cracks between tiles
+/// far from the world origin, as long as the `f32` transforms of the tilemap and camera themselves remain accurate.
+///
+/// With the `serde` feature, tilemaps can be saved in and spawned from `DynamicScene`s,
+/// with chunks stored as runs of occupied tiles.
+/// Handles are not serialized, so the image and texture atlas layout of tilemaps loaded from a scene file
+/// must be set afterwards, such as with `TileMap::resolve_handles`.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component, Debug)]
+#[cfg_attr(feature = "serde", reflect(Serialize, Deserialize))]
 #[require(TileMapCache, Transform, Visibility, SyncToRenderWorld)]
 pub struct TileMap {
+    #[reflect(skip_serializing)]
     pub image: Handle<Image>,
+    #[reflect(skip_serializing)]
     pub texture_atlas_layout: Handle<TextureAtlasLayout>,
 
+    /// How tile colors are interpreted when rendering
+    pub color_mode: TileColorMode,
+
+    /// How the alpha channel of tiles is handled when rendering.
+    ///
+    /// [`AlphaMode2d::Mask`] discards fragments with an alpha below the threshold and enables depth writes,
+    /// which avoids sorting artifacts between overlapping layers for tilesets with hard-edged transparency.
+    pub alpha_mode: AlphaMode2d,
+    /// Alpha cutoff for writing depth with [`AlphaMode2d::Blend`].
+    ///
+    /// If set, fragments with an alpha below the cutoff are discarded, and the rest are blended as usual
+    /// and write depth. This lets tiles occlude and be occluded by sprites at intermediate z values,
+    /// such as for y-sorting in 2.5D setups.
+    pub depth_write_cutoff: Option<f32>,
+    /// Whether tiles are depth tested against the view's depth attachment.
+    ///
+    /// Disable this only to render into custom passes without a depth attachment. Tiles are then drawn
+    /// in the [`Transparent2d`] phase of views of other cameras only, sorted by z, as the built-in 2D passes
+    /// of [`Camera2d`]s always have a depth attachment. Tilemaps with depth testing are only drawn by `Camera2d`s.
+    /// See the `no_depth` example.
+    ///
+    /// [`Transparent2d`]: bevy::core_pipeline::core_2d::Transparent2d
+    pub depth_test: bool,
+
+    /// How tile positions are laid out, such as orthogonal or isometric
+    pub projection: TileMapProjection,
+
+    /// Distance in texels to inset samples from the edges of each sprite's atlas rect.
+    ///
+    /// With linear filtering, non-integer zoom levels or MSAA, sampling at the edge of a sprite blends in
+    /// texels of the neighboring sprite, which shows up as seams between tiles. The shader clamps samples
+    /// to the sprite's rect shrunk by this inset, so the default of half a texel keeps filtering within
+    /// the sprite regardless of the atlas' padding. Set to 0 to sample up to the edges of the rect.
+    pub uv_inset: f32,
+
+    /// Whether chunks are culled against the cameras
+    pub culling: TileMapCulling,
+    /// Distance in tiles to expand the bounds of chunks by when culling them against cameras.
+    ///
+    /// Increase this if tiles are drawn outside their cell, such as with large custom sizes or offsets,
+    /// or if cameras shake after chunks are culled, to avoid chunks popping in late at the screen edges.
+    pub cull_margin: f32,
+
+    /// Distance in local z between consecutive layers.
+    /// Layer `n` is placed at `layer_z_offset + n * layer_z_scale`.
+    pub layer_z_scale: f32,
+    /// Local z of layer 0
+    pub layer_z_offset: f32,
+
+    /// Chunks by chunk position.
+    /// Change tiles through the tilemap rather than the chunks directly,
+    /// so that changes are recorded in [`DirtyChunks`].
     pub chunks: HashMap<IVec3, Chunk>,
 
-    tile_changes: Vec<(IVec3, Option<Tile>)>,
+    /// Per-layer settings
+    pub(crate) layers: HashMap<i32, TileMapLayer>,
+    /// When layer settings were last accessed mutably, used to detect chunks that need remeshing
+    #[reflect(ignore)]
+    #[reflect(default = "Instant::now")]
+    pub(crate) layers_changed_at: Instant,
+
+    /// Animations applied to all tiles with a given sprite index
+    pub(crate) sprite_animations: HashMap<u32, TileAnimation>,
+    /// Animations applied to tiles at specific positions. These take precedence over sprite animations.
+    pub(crate) tile_animations: HashMap<IVec3, TileAnimation>,
+    pub(crate) animation_time: Duration,
+    #[reflect(ignore)]
+    pub(crate) tile_fades: Vec<TileFade>,
+
+    /// World-space bounding boxes of chunks, updated after transform propagation
+    #[reflect(ignore)]
+    pub(crate) chunk_aabbs: HashMap<IVec3, Aabb>,
+
+    #[reflect(ignore)]
+    pub(crate) tile_changes: Vec<(IVec3, Option<Tile>)>,
+    #[reflect(ignore)]
     clear_all: bool,
+    #[reflect(ignore)]
     clear_layers: HashSet<i32>,
+    #[reflect(ignore)]
+    remove_chunks: HashSet<IVec3>,
+    #[reflect(ignore)]
+    insert_chunks: Vec<Chunk>,
+    #[reflect(ignore)]
+    fill_rects: Vec<(IRect, i32, Option<Tile>)>,
+    /// Asset paths of the handles the tilemap was serialized with, until they are resolved
+    #[cfg(feature = "serde")]
+    #[reflect(ignore)]
+    pub(crate) asset_paths: Option<crate::tilemap_serde::TileMapAssetPaths>,
 }
 
 #[derive(Component, Default)]
 pub struct TileMapCache {
     tile_changes_by_chunk: HashMap<IVec3, Vec<(IVec3, Option<Tile>)>>,
+    chunk_pool: ChunkPool,
+}
+
+/// Recycles the allocations of removed chunks for new chunks,
+/// to reduce allocator pressure when chunks are frequently removed and created, such as when streaming.
+#[derive(Default)]
+struct ChunkPool {
+    chunks: Vec<Chunk>,
+}
+
+impl ChunkPool {
+    /// Maximum number of chunks kept for reuse
+    const MAX_CHUNKS: usize = 16;
+
+    /// Take an empty chunk from the pool, or create a new one if the pool is empty
+    fn take(&mut self, origin: IVec3) -> Chunk {
+        match self.chunks.pop() {
+            Some(mut chunk) => {
+                chunk.origin = origin;
+                chunk.last_change_at = Instant::now();
+                chunk
+            }
+            None => Chunk::new(origin),
+        }
+    }
+
+    /// Return a removed chunk to the pool
+    fn recycle(&mut self, mut chunk: Chunk) {
+        if self.chunks.len() >= Self::MAX_CHUNKS {
+            return;
+        }
+
+        chunk.clear();
+        self.chunks.push(chunk);
+    }
 }
 
 /// Alias for use with [`bevy_render::view::VisibleEntities`].
 pub type WithTileMap = With<TileMap>;
 
 impl Chunk {
+    /// Create an empty chunk at the specified chunk position (as opposed to tile position)
+    pub fn from_chunk_pos(chunk_pos: IVec3) -> Self {
+        Self::new(calc_chunk_origin(chunk_pos))
+    }
+
     pub fn new(origin: IVec3) -> Self {
         Self {
             origin,
-            tiles: vec![None; (CHUNK_WIDTH * CHUNK_HEIGHT) as usize],
+            tiles: ChunkTiles::new(),
             last_change_at: Instant::now(),
+            bounds: None,
+            occupancy: vec![0; OCCUPANCY_WORDS],
         }
     }
 
+    /// Create a chunk with the specified tiles, rebuilding its occupancy and bounds from them
+    fn with_tiles(origin: IVec3, tiles: ChunkTiles) -> Self {
+        let mut chunk = Self {
+            tiles,
+            ..Self::new(origin)
+        };
+
+        chunk.rebuild_occupancy();
+        chunk
+    }
+
+    /// Bounds of the occupied tiles in the chunk, in tile coordinates (inclusive)
+    pub fn bounds(&self) -> Option<IRect> {
+        self.bounds
+    }
+
+    /// Returns true if the chunk contains no tiles
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.occupancy.iter().all(|word| *word == 0)
+    }
+
+    /// Number of tiles in the chunk
+    pub fn tile_count(&self) -> usize {
+        self.occupancy.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns true if the chunk's tiles are stored sparsely, which only happens with the `sparse-chunks` feature
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.tiles, ChunkTiles::Sparse(_))
+    }
+
+    /// Get the tile at the specified position, in tile coordinates.
+    /// Returns `None` if there is no tile there, or the position is outside the chunk.
+    pub fn get_tile(&self, pos: IVec3) -> Option<&Tile> {
+        let pos = pos.truncate().saturating_sub(self.origin.truncate());
+
+        if pos.cmplt(IVec2::ZERO).any() || pos.cmpge(CHUNK_SIZE.as_ivec2()).any() {
+            return None;
+        }
+
+        self.tiles.get(row_major_index(pos))
+    }
+
+    /// Get the tile at the specified row major index within the chunk
+    #[inline]
+    pub(crate) fn tile_at_index(&self, index: usize) -> Option<&Tile> {
+        self.tiles.get(index)
+    }
+
+    /// Iterate over the indices of occupied tiles, using the occupancy bitset to skip empty ones
+    pub fn occupied_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.occupancy.iter().enumerate().flat_map(|(word_index, word)| {
+            let mut word = *word;
+
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+
+                Some(word_index * 64 + bit)
+            })
+        })
+    }
+
+    /// Iterate over all tiles in the chunk, along with their positions
+    pub fn iter_tiles(&self) -> impl Iterator<Item = (IVec3, &Tile)> + '_ {
+        self.occupied_indices().filter_map(move |i| {
+            let tile = self.tiles.get(i)?;
+
+            Some((self.origin + row_major_pos(i).extend(0), tile))
+        })
+    }
+
     fn clear(&mut self) {
-        for tile in self.tiles.iter_mut() {
-            *tile = None;
+        match &mut self.tiles {
+            ChunkTiles::Sparse(tiles) => {
+                tiles.clear();
+                self.occupancy.fill(0);
+            }
+            ChunkTiles::Dense(tiles) => {
+                // Only reset occupied tiles, so that mostly empty chunks are cleared without touching every tile
+                for (word_index, word) in self.occupancy.iter_mut().enumerate() {
+                    let tiles = &mut tiles[word_index * 64..(word_index + 1) * 64];
+
+                    if *word == u64::MAX {
+                        tiles.fill(None);
+                    } else {
+                        while *word != 0 {
+                            tiles[word.trailing_zeros() as usize] = None;
+                            *word &= *word - 1;
+                        }
+                    }
+
+                    *word = 0;
+                }
+            }
+        }
+
+        self.bounds = None;
+        self.last_change_at = Instant::now();
+        self.update_storage();
+    }
+
+    /// Switch between sparse and dense storage, depending on the number of tiles
+    fn update_storage(&mut self) {
+        if !SPARSE_CHUNKS {
+            return;
+        }
+
+        let tile_count = self.tile_count();
+
+        match self.tiles {
+            ChunkTiles::Sparse(_) if tile_count > SPARSE_MAX_TILES => self.tiles.make_dense(),
+            ChunkTiles::Dense(_) if tile_count <= SPARSE_MAX_TILES / 2 => self.tiles.make_sparse(),
+            _ => {}
+        }
+    }
+
+    /// Fill a rectangle (inclusive, in tile coordinates) with the same tile, or remove all tiles in it if `None`.
+    /// The part of the rectangle outside of the chunk is ignored.
+    ///
+    /// Each row is written as a contiguous slice, which is much faster than setting tiles individually.
+    pub fn fill_rect(&mut self, rect: IRect, tile: Option<Tile>) {
+        let chunk_origin = self.origin.truncate();
+
+        // Clip the rectangle to the chunk, in chunk-local coordinates.
+        // Subtraction saturates, as rectangles may extend far outside the chunk.
+        let min = rect.min.saturating_sub(chunk_origin).max(IVec2::ZERO);
+        let max = rect
+            .max
+            .saturating_sub(chunk_origin)
+            .min(CHUNK_SIZE.as_ivec2() - IVec2::ONE);
+
+        if min.cmpgt(max).any() {
+            return;
+        }
+
+        // Switch to dense storage up front if the fill would make a sparse chunk too large
+        let area = ((max - min + IVec2::ONE).element_product()) as usize;
+        if tile.is_some() && self.is_sparse() && self.tile_count() + area > SPARSE_MAX_TILES {
+            self.tiles.make_dense();
+        }
+
+        for y in min.y..=max.y {
+            let start = row_major_index(IVec2::new(min.x, y));
+            let end = row_major_index(IVec2::new(max.x, y)) + 1;
+
+            if let ChunkTiles::Dense(tiles) = &mut self.tiles {
+                tiles[start..end].fill(tile.clone());
+            } else {
+                for index in start..end {
+                    self.tiles.set(index, tile.clone());
+                }
+            }
+
+            self.set_occupancy_range(start, end, tile.is_some());
+        }
+
+        if tile.is_some() {
+            let rect = IRect::from_corners(chunk_origin + min, chunk_origin + max);
+            self.bounds = Some(self.bounds.map_or(rect, |bounds| bounds.union(rect)));
+        } else {
+            self.update_bounds();
         }
 
         self.last_change_at = Instant::now();
+        self.update_storage();
     }
 
-    fn set_tiles(&mut self, tiles: impl IntoIterator<Item = (IVec3, Option<Tile>)>) {
+    /// Set or unset the occupancy bits of a range of tile indices, a whole word at a time where possible
+    fn set_occupancy_range(&mut self, start: usize, end: usize, occupied: bool) {
+        let mut index = start;
+
+        while index < end {
+            let word_index = index / 64;
+            let bit = index % 64;
+            let count = (64 - bit).min(end - index);
+
+            let mask = if count == 64 {
+                u64::MAX
+            } else {
+                ((1u64 << count) - 1) << bit
+            };
+
+            if occupied {
+                self.occupancy[word_index] |= mask;
+            } else {
+                self.occupancy[word_index] &= !mask;
+            }
+
+            index += count;
+        }
+    }
+
+    /// Rebuild the occupancy bitset and bounds from the tiles, such as after deserializing a chunk
+    pub(crate) fn rebuild_occupancy(&mut self) {
+        self.occupancy = vec![0; OCCUPANCY_WORDS];
+
+        match &mut self.tiles {
+            ChunkTiles::Sparse(tiles) => {
+                tiles.retain(|index, _| (*index as usize) < TILES_PER_CHUNK);
+
+                for index in tiles.keys() {
+                    self.occupancy[*index as usize / 64] |= 1u64 << (*index % 64);
+                }
+            }
+            ChunkTiles::Dense(tiles) => {
+                tiles.resize(TILES_PER_CHUNK, None);
+
+                for (index, tile) in tiles.iter().enumerate() {
+                    if tile.is_some() {
+                        self.occupancy[index / 64] |= 1u64 << (index % 64);
+                    }
+                }
+            }
+        }
+
+        self.update_bounds();
+        self.update_storage();
+    }
+
+    fn update_bounds(&mut self) {
+        let chunk_origin = self.origin.truncate();
+
+        self.bounds = self
+            .occupied_indices()
+            .map(|i| chunk_origin + row_major_pos(i))
+            .fold(None, |bounds: Option<IRect>, pos| {
+                Some(bounds.map_or(IRect::from_corners(pos, pos), |b| b.union_point(pos)))
+            });
+    }
+
+    /// Set tiles within the chunk, such as when generating a chunk to pass to [`TileMap::insert_chunk`].
+    /// Positions are in tile coordinates, and must be within the chunk.
+    pub fn set_tiles(&mut self, tiles: impl IntoIterator<Item = (IVec3, Option<Tile>)>) {
         let chunk_origin = self.origin;
 
         for (pos, tile) in tiles {
             let pos = pos - chunk_origin;
             let index = row_major_index(IVec2::new(pos.x, pos.y));
 
-            self.tiles[index] = tile;
+            let (word, bit) = (index / 64, 1u64 << (index % 64));
+            if tile.is_some() {
+                self.occupancy[word] |= bit;
+            } else {
+                self.occupancy[word] &= !bit;
+            }
+
+            self.tiles.set(index, tile);
         }
 
+        self.update_bounds();
         self.last_change_at = Instant::now();
+        self.update_storage();
+    }
+}
+
+impl Default for Tile {
+    fn default() -> Self {
+        Self {
+            sprite_index: 0,
+            color: Color::WHITE,
+            flags: TileFlags::empty(),
+            custom_size: None,
+        }
+    }
+}
+
+impl Tile {
+    /// Create a tile with the specified sprite index, no tint and no flags
+    pub fn new(sprite_index: u32) -> Self {
+        Self {
+            sprite_index,
+            ..Default::default()
+        }
+    }
+
+    /// Set the color the tile is tinted with
+    pub fn with_color(mut self, color: impl Into<Color>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Set the flags of the tile
+    pub fn with_flags(mut self, flags: TileFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the depth bias of the tile, used to resolve overlap with other tiles on the same layer.
+    /// See [`TileFlags::with_depth_bias`].
+    pub fn with_depth_bias(mut self, bias: i8) -> Self {
+        self.flags = self.flags.with_depth_bias(bias);
+        self
+    }
+
+    /// Set the shape the tile is rendered with. See [`TileShape`].
+    pub fn with_shape(mut self, shape: TileShape) -> Self {
+        self.flags = self.flags.with_shape(shape);
+        self
+    }
+
+    /// Make the tile block light. See [`TileFlags::OCCLUDER`].
+    pub fn with_occluder(mut self) -> Self {
+        self.flags |= TileFlags::OCCLUDER;
+        self
+    }
+
+    /// Make the tile emissive, multiplying its color by the specified strength.
+    /// See [`TileFlags::EMISSIVE`].
+    pub fn with_emissive(mut self, strength: f32) -> Self {
+        let color = self.color.to_linear();
+
+        self.color = Color::LinearRgba(LinearRgba::new(
+            color.red * strength,
+            color.green * strength,
+            color.blue * strength,
+            color.alpha,
+        ));
+        self.flags |= TileFlags::EMISSIVE;
+        self
+    }
+
+    /// Set the size the tile is rendered at
+    pub fn with_custom_size(mut self, custom_size: Vec2) -> Self {
+        self.custom_size = Some(custom_size);
+        self
+    }
+
+    /// Get the tile color in non-linear sRGB color space
+    pub fn srgba(&self) -> Srgba {
+        self.color.to_srgba()
+    }
+
+    /// Get the tile color in linear RGB color space
+    pub fn linear_rgba(&self) -> LinearRgba {
+        self.color.to_linear()
+    }
+
+    /// Set the tile color from a non-linear sRGB color
+    pub fn set_srgba(&mut self, color: Srgba) {
+        self.color = Color::Srgba(color);
+    }
+
+    /// Set the tile color from a linear RGB color
+    pub fn set_linear_rgba(&mut self, color: LinearRgba) {
+        self.color = Color::LinearRgba(color);
+    }
+
+    /// Get the color to pass to the shader, according to the specified color mode
+    #[inline]
+    pub(crate) fn render_color(&self, color_mode: TileColorMode) -> LinearRgba {
+        match color_mode {
+            TileColorMode::Linear => self.color.to_linear(),
+            TileColorMode::LegacySrgb => {
+                let Srgba {
+                    red,
+                    green,
+                    blue,
+                    alpha,
+                } = self.color.to_srgba();
+
+                LinearRgba::new(red, green, blue, alpha)
+            }
+        }
     }
 }
 
@@ -94,16 +914,41 @@ impl TileMap {
             image,
             texture_atlas_layout,
 
+            color_mode: TileColorMode::default(),
+            alpha_mode: AlphaMode2d::Blend,
+            depth_write_cutoff: None,
+            depth_test: true,
+            projection: TileMapProjection::Orthogonal,
+            uv_inset: 0.5,
+            culling: TileMapCulling::Enabled,
+            cull_margin: 0.0,
+            layer_z_scale: 1.0,
+            layer_z_offset: 0.0,
+
             chunks: Default::default(),
+            layers: Default::default(),
+            layers_changed_at: Instant::now(),
+            sprite_animations: Default::default(),
+            tile_animations: Default::default(),
+            animation_time: Duration::ZERO,
+            tile_fades: Default::default(),
+            chunk_aabbs: Default::default(),
             tile_changes: Default::default(),
             clear_all: false,
             clear_layers: Default::default(),
+            remove_chunks: Default::default(),
+            insert_chunks: Default::default(),
+            fill_rects: Default::default(),
+            #[cfg(feature = "serde")]
+            asset_paths: None,
         }
     }
 
     pub fn clear(&mut self) {
         // Clear change queue
         self.tile_changes.clear();
+        self.insert_chunks.clear();
+        self.fill_rects.clear();
 
         // Clear layer clear requests, since we're clearing everything anyway
         self.clear_layers.clear();
@@ -115,23 +960,353 @@ impl TileMap {
     pub fn clear_layer(&mut self, layer: i32) {
         // Remove queued tile changes for the cleared layer
         self.tile_changes.retain(|(pos, _)| pos.z != layer);
+        self.insert_chunks.retain(|chunk| chunk.origin.z != layer);
+        self.fill_rects.retain(|(_, z, _)| *z != layer);
 
         // Request clear layer
         self.clear_layers.insert(layer);
     }
 
+    /// Iterate over all tiles in the tilemap, along with their positions
+    pub fn iter_tiles(&self) -> impl Iterator<Item = (IVec3, &Tile)> + '_ {
+        self.chunks
+            .values()
+            .filter(|chunk| !chunk.is_empty())
+            .flat_map(|chunk| chunk.iter_tiles())
+    }
+
+    /// Find the positions of all tiles matching the predicate.
+    /// Empty chunks are skipped entirely, and only occupied tiles are passed to the predicate.
+    pub fn find<'a>(
+        &'a self,
+        mut predicate: impl FnMut(IVec3, &Tile) -> bool + 'a,
+    ) -> impl Iterator<Item = IVec3> + 'a {
+        self.iter_tiles()
+            .filter(move |(pos, tile)| predicate(*pos, tile))
+            .map(|(pos, _)| pos)
+    }
+
+    /// Find the position of the first tile matching the predicate.
+    /// Note that the order in which chunks are searched is unspecified.
+    pub fn find_first(&self, mut predicate: impl FnMut(IVec3, &Tile) -> bool) -> Option<IVec3> {
+        self.iter_tiles()
+            .find(|(pos, tile)| predicate(*pos, tile))
+            .map(|(pos, _)| pos)
+    }
+
+    /// Get the world-space bounding boxes of all chunks, keyed by chunk position.
+    ///
+    /// These are updated in [`PostUpdate`] after transform propagation, in [`TileMapSystem::UpdateChunkAabbs`](crate::TileMapSystem::UpdateChunkAabbs),
+    /// and can be used by external systems such as audio or effects that need to know where regions of the map are.
+    pub fn chunk_aabbs(&self) -> impl Iterator<Item = (IVec3, &Aabb)> + '_ {
+        self.chunk_aabbs.iter().map(|(chunk_pos, aabb)| (*chunk_pos, aabb))
+    }
+
+    /// Get the world-space bounding box of the chunk containing the specified tile position
+    pub fn chunk_aabb_at(&self, tile_pos: IVec3) -> Option<&Aabb> {
+        self.chunk_aabbs.get(&calc_chunk_pos(tile_pos))
+    }
+
+    /// Get settings for the specified layer, if any have been set
+    pub fn layer(&self, layer: i32) -> Option<&TileMapLayer> {
+        self.layers.get(&layer)
+    }
+
+    /// Get mutable settings for the specified layer, creating default settings if none exist
+    pub fn layer_mut(&mut self, layer: i32) -> &mut TileMapLayer {
+        self.layers_changed_at = Instant::now();
+        self.layers.entry(layer).or_default()
+    }
+
+    /// Set settings for the specified layer
+    pub fn set_layer(&mut self, layer: i32, settings: TileMapLayer) {
+        self.layers_changed_at = Instant::now();
+        self.layers.insert(layer, settings);
+    }
+
+    /// Name a layer, so that it can be looked up with [`TileMap::layer_by_name`].
+    /// If another layer already has this name, it is removed from that layer.
+    pub fn add_layer(&mut self, name: impl Into<String>, layer: i32) -> &mut TileMapLayer {
+        let name = name.into();
+
+        for (_, settings) in self.layers.iter_mut().filter(|(z, _)| **z != layer) {
+            if settings.name.as_ref() == Some(&name) {
+                settings.name = None;
+            }
+        }
+
+        let settings = self.layer_mut(layer);
+        settings.name = Some(name);
+        settings
+    }
+
+    /// Find a layer by name, returning its layer index and settings
+    pub fn layer_by_name(&self, name: &str) -> Option<(i32, &TileMapLayer)> {
+        self.layers()
+            .find(|(_, settings)| settings.name.as_deref() == Some(name))
+    }
+
+    /// Get the local z coordinate the specified layer is rendered at
+    #[inline]
+    pub fn layer_z(&self, layer: i32) -> f32 {
+        self.layer_z_offset + layer as f32 * self.layer_z_scale
+    }
+
+    /// Get the image and texture atlas layout used for the specified layer
+    pub fn layer_tileset(&self, layer: i32) -> (&Handle<Image>, &Handle<TextureAtlasLayout>) {
+        self.layers
+            .get(&layer)
+            .and_then(|settings| settings.tileset())
+            .unwrap_or((&self.image, &self.texture_atlas_layout))
+    }
+
+    /// Iterate over all layers that have settings
+    pub fn layers(&self) -> impl Iterator<Item = (i32, &TileMapLayer)> {
+        self.layers.iter().map(|(layer, settings)| (*layer, settings))
+    }
+
+    /// Iterate over the 8 neighbors of a tile position on the same layer, along with their tiles, if any.
+    /// Neighbors outside the range of `i32` are skipped.
+    pub fn neighbors(&self, pos: IVec3) -> impl Iterator<Item = (IVec3, Option<&Tile>)> + '_ {
+        const NEIGHBOR_OFFSETS: [IVec2; 8] = [
+            IVec2::new(-1, -1),
+            IVec2::new(0, -1),
+            IVec2::new(1, -1),
+            IVec2::new(-1, 0),
+            IVec2::new(1, 0),
+            IVec2::new(-1, 1),
+            IVec2::new(0, 1),
+            IVec2::new(1, 1),
+        ];
+
+        NEIGHBOR_OFFSETS.into_iter().filter_map(move |offset| {
+            let pos = checked_offset(pos, offset)?;
+
+            Some((pos, self.get_tile(pos)))
+        })
+    }
+
+    /// Get the bounds of all tiles in the tilemap across all layers, in tile coordinates (inclusive).
+    /// Returns `None` if the tilemap contains no tiles.
+    pub fn bounds(&self) -> Option<IRect> {
+        self.chunks
+            .values()
+            .filter_map(|chunk| chunk.bounds())
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Get the size of a tile in pixels, as determined by the first sprite in the texture atlas.
+    /// Returns `None` if the texture atlas is not loaded.
+    pub fn tile_size(&self, texture_atlases: &Assets<TextureAtlasLayout>) -> Option<UVec2> {
+        let texture_atlas = texture_atlases.get(&self.texture_atlas_layout)?;
+        let tile0_tex = texture_atlas.textures.first()?;
+
+        Some(tile0_tex.size())
+    }
+
+    /// Get the size of a tile on the specified layer in pixels, as determined by the first sprite
+    /// in the layer's texture atlas. Returns `None` if the texture atlas is not loaded.
+    pub fn layer_tile_size(&self, layer: i32, texture_atlases: &Assets<TextureAtlasLayout>) -> Option<UVec2> {
+        let (_, texture_atlas_layout) = self.layer_tileset(layer);
+        let tile0_tex = texture_atlases.get(texture_atlas_layout)?.textures.first()?;
+
+        Some(tile0_tex.size())
+    }
+
+    /// Get the world position of the center of a tile, given the tilemap's transform.
+    ///
+    /// The projection, the layer's z and the layer's offset are taken into account, but not its parallax,
+    /// which depends on the camera. Returns `None` if the layer's texture atlas is not loaded.
+    pub fn tile_to_world(
+        &self,
+        tile_pos: IVec3,
+        transform: &GlobalTransform,
+        texture_atlases: &Assets<TextureAtlasLayout>,
+    ) -> Option<Vec3> {
+        let tile_size = self.layer_tile_size(tile_pos.z, texture_atlases)?.as_vec2();
+        let local_pos = self.projection.tile_to_local(tile_pos.truncate(), tile_size);
+        let offset = self
+            .layer(tile_pos.z)
+            .map(|settings| settings.offset)
+            .unwrap_or_default();
+
+        Some(transform.transform_point(local_pos.extend(self.layer_z(tile_pos.z))) + offset.extend(0.0))
+    }
+
+    /// Get the position of the tile on the specified layer containing a world position,
+    /// given the tilemap's transform.
+    ///
+    /// Tiles are centered on their position, so positions are rounded to the nearest tile rather than truncated,
+    /// which also holds at negative coordinates. The layer's offset is taken into account, but not its parallax.
+    /// Returns `None` if the layer's texture atlas is not loaded.
+    pub fn world_to_tile(
+        &self,
+        world_pos: Vec2,
+        layer: i32,
+        transform: &GlobalTransform,
+        texture_atlases: &Assets<TextureAtlasLayout>,
+    ) -> Option<IVec3> {
+        let tile_size = self.layer_tile_size(layer, texture_atlases)?.as_vec2();
+        let offset = self.layer(layer).map(|settings| settings.offset).unwrap_or_default();
+
+        let local_pos = transform
+            .affine()
+            .inverse()
+            .transform_point3((world_pos - offset).extend(0.0));
+
+        Some(
+            self.projection
+                .local_to_tile(local_pos.truncate(), tile_size)
+                .extend(layer),
+        )
+    }
+
+    /// Find the topmost tile at a world position, given the tilemap's transform,
+    /// such as for selecting or targeting tiles. Layers are ordered by their world z, as they are drawn.
+    ///
+    /// Only the specified layers are considered, or all layers if `None`.
+    /// Layers whose texture atlas is not loaded are skipped.
+    pub fn pick(
+        &self,
+        world_pos: Vec2,
+        layers: Option<&[i32]>,
+        transform: &GlobalTransform,
+        texture_atlases: &Assets<TextureAtlasLayout>,
+    ) -> Option<(IVec3, &Tile)> {
+        let mut tile_layers: Vec<i32> = self
+            .chunks
+            .keys()
+            .map(|chunk_pos| chunk_pos.z)
+            .filter(|layer| layers.is_none_or(|layers| layers.contains(layer)))
+            .collect();
+
+        tile_layers.sort_unstable();
+        tile_layers.dedup();
+
+        // Topmost first, in the order layers are drawn in
+        let world_z = |layer: i32| transform.transform_point(Vec3::Z * self.layer_z(layer)).z;
+        tile_layers.sort_by(|a, b| world_z(*b).total_cmp(&world_z(*a)).then(b.cmp(a)));
+
+        tile_layers.into_iter().find_map(|layer| {
+            let tile_pos = self.world_to_tile(world_pos, layer, transform, texture_atlases)?;
+
+            Some((tile_pos, self.get_tile(tile_pos)?))
+        })
+    }
+
+    /// Get the tile at the specified position.
+    ///
+    /// Note that queued tile changes are not applied until the next [`TileMapSystem::UpdateChunks`](crate::TileMapSystem::UpdateChunks),
+    /// and will not be reflected here until then.
+    pub fn get_tile(&self, pos: IVec3) -> Option<&Tile> {
+        self.chunks.get(&calc_chunk_pos(pos))?.get_tile(pos)
+    }
+
+    /// Remove an entire chunk, freeing its memory.
+    /// Queued tile changes within the chunk are discarded.
+    pub fn remove_chunk(&mut self, chunk_pos: IVec3) {
+        self.tile_changes.retain(|(pos, _)| calc_chunk_pos(*pos) != chunk_pos);
+        self.insert_chunks
+            .retain(|chunk| calc_chunk_pos(chunk.origin) != chunk_pos);
+        self.remove_chunks.insert(chunk_pos);
+    }
+
+    /// Insert an entire chunk, replacing any existing chunk at the same position.
+    /// The chunk's origin is aligned to the chunk containing it.
+    ///
+    /// This is much faster than setting each tile individually, such as when loading or generating chunks.
+    pub fn insert_chunk(&mut self, chunk: Chunk) {
+        self.remove_chunks.remove(&calc_chunk_pos(chunk.origin));
+        self.insert_chunks.push(chunk);
+    }
+
+    /// Whether a chunk is queued to be inserted at the specified chunk position by [`TileMap::insert_chunk`],
+    /// but not yet in [`TileMap::chunks`]
+    pub fn is_chunk_queued(&self, chunk_pos: IVec3) -> bool {
+        self.insert_chunks
+            .iter()
+            .any(|chunk| calc_chunk_pos(chunk.origin) == chunk_pos)
+    }
+
     pub fn set_tile(&mut self, pos: IVec3, tile: Option<Tile>) {
+        // Setting a tile cancels any ongoing fade-out at that position
+        if !self.tile_fades.is_empty() {
+            self.tile_fades.retain(|fade| fade.pos != pos);
+        }
+
         self.tile_changes.push((pos, tile));
     }
 
+    /// Fade the tile at the specified position out over the specified duration, and then remove it
+    pub fn remove_tile_animated(&mut self, pos: IVec3, duration: Duration) {
+        self.tile_fades.retain(|fade| fade.pos != pos);
+        self.tile_fades.push(TileFade::new(pos, duration));
+    }
+
     pub fn set_tiles(&mut self, tiles: impl IntoIterator<Item = (IVec3, Option<Tile>)>) {
         self.tile_changes.extend(tiles);
     }
+
+    /// Fill a rectangle (inclusive) on a layer with the same tile, or remove all tiles in it if `None`.
+    ///
+    /// This is much faster than setting each tile individually, such as when loading levels.
+    /// Fills are applied after chunk insertions, and before tile changes queued after the fill.
+    pub fn fill_rect(&mut self, rect: IRect, layer: i32, tile: Option<Tile>) {
+        let in_rect = |pos: &IVec3| pos.z == layer && rect.contains(pos.truncate());
+
+        // Earlier changes within the rectangle would otherwise be applied after the fill
+        self.tile_changes.retain(|(pos, _)| !in_rect(pos));
+
+        if !self.tile_fades.is_empty() {
+            self.tile_fades.retain(|fade| !in_rect(&fade.pos));
+        }
+
+        self.fill_rects.push((rect, layer, tile));
+    }
+
+    /// Animate all tiles with the specified sprite index.
+    /// Passing `None` removes the animation.
+    pub fn set_sprite_animation(&mut self, sprite_index: u32, animation: Option<TileAnimation>) {
+        if let Some(animation) = animation {
+            self.sprite_animations.insert(sprite_index, animation);
+        } else {
+            self.sprite_animations.remove(&sprite_index);
+        }
+    }
+
+    /// Animate the tile at the specified position, regardless of its sprite index.
+    /// Passing `None` removes the animation.
+    pub fn set_tile_animation(&mut self, pos: IVec3, animation: Option<TileAnimation>) {
+        if let Some(animation) = animation {
+            self.tile_animations.insert(pos, animation);
+        } else {
+            self.tile_animations.remove(&pos);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn has_animations(&self) -> bool {
+        !self.sprite_animations.is_empty() || !self.tile_animations.is_empty()
+    }
+
+    /// Get the sprite index to render for a tile, taking animations into account
+    #[inline]
+    pub(crate) fn animated_sprite_index(&self, pos: IVec3, sprite_index: u32) -> u32 {
+        if !self.has_animations() {
+            return sprite_index;
+        }
+
+        self.tile_animations
+            .get(&pos)
+            .or_else(|| self.sprite_animations.get(&sprite_index))
+            .and_then(|animation| animation.frame_at(self.animation_time))
+            .unwrap_or(sprite_index)
+    }
 }
 
 /// Calculate chunk position based on tile position
 #[inline]
-fn calc_chunk_pos(tile_pos: IVec3) -> IVec3 {
+pub(crate) fn calc_chunk_pos(tile_pos: IVec3) -> IVec3 {
     IVec3::new(
         tile_pos.x.div_euclid(CHUNK_WIDTH_I32),
         tile_pos.y.div_euclid(CHUNK_HEIGHT_I32),
@@ -141,7 +1316,7 @@ fn calc_chunk_pos(tile_pos: IVec3) -> IVec3 {
 
 /// Calculate chunk origin (bottom left corner of chunk) in tile coordinates
 #[inline]
-fn calc_chunk_origin(chunk_pos: IVec3) -> IVec3 {
+pub(crate) fn calc_chunk_origin(chunk_pos: IVec3) -> IVec3 {
     IVec3::new(
         chunk_pos.x * CHUNK_WIDTH_I32,
         chunk_pos.y * CHUNK_HEIGHT_I32,
@@ -149,10 +1324,20 @@ fn calc_chunk_origin(chunk_pos: IVec3) -> IVec3 {
     )
 }
 
+/// Offset a tile position on the same layer, returning `None` if the result is outside the range of `i32`
+#[inline]
+pub(crate) fn checked_offset(pos: IVec3, offset: IVec2) -> Option<IVec3> {
+    Some(IVec3::new(
+        pos.x.checked_add(offset.x)?,
+        pos.y.checked_add(offset.y)?,
+        pos.z,
+    ))
+}
+
 /// Calculate row major index of tile position
 #[inline]
-fn row_major_index(pos: IVec2) -> usize {
-    (pos.x + pos.y * CHUNK_HEIGHT_I32) as usize
+pub(crate) fn row_major_index(pos: IVec2) -> usize {
+    (pos.x + pos.y * CHUNK_WIDTH_I32) as usize
 }
 
 /// Calculate row major position from index
@@ -164,15 +1349,55 @@ pub fn row_major_pos(index: usize) -> IVec2 {
 }
 
 /// Update and mark chunks for remeshing, based on queued tile changes
-pub(crate) fn update_chunks_system(mut tilemap_query: Query<(&mut TileMap, &mut TileMapCache)>) {
-    for (mut tilemap, mut tilemap_cache) in tilemap_query.iter_mut() {
+pub(crate) fn update_chunks_system(
+    mut tilemap_query: Query<(Entity, &mut TileMap, &mut TileMapCache)>,
+    mut chunk_events: EventWriter<ChunkLifecycleEvent>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+) {
+    dirty_chunks.clear();
+
+    for (entity, mut tilemap, mut tilemap_cache) in tilemap_query.iter_mut() {
         // Temporary storage for tile changes grouped by chunk
-        let changes_by_chunk = &mut tilemap_cache.tile_changes_by_chunk;
+        let TileMapCache {
+            tile_changes_by_chunk: changes_by_chunk,
+            chunk_pool,
+        } = &mut *tilemap_cache;
+
+        // Record a changed chunk, and send a lifecycle event if it was created, cleared or removed
+        let mut mark_dirty = |chunk_pos: IVec3, cause: DirtyCause| {
+            dirty_chunks.mark(entity, chunk_pos, cause);
+
+            let kind = match cause {
+                DirtyCause::Created => ChunkLifecycle::Created,
+                DirtyCause::Cleared => ChunkLifecycle::Cleared,
+                DirtyCause::Removed => ChunkLifecycle::Evicted,
+                DirtyCause::Modified => return,
+            };
+
+            chunk_events.send(ChunkLifecycleEvent {
+                tilemap: entity,
+                chunk_pos,
+                kind,
+            });
+        };
+
+        // Remove chunks that were requested to be removed
+        if !tilemap.remove_chunks.is_empty() {
+            let remove_chunks: Vec<IVec3> = tilemap.remove_chunks.drain().collect();
+
+            for chunk_pos in remove_chunks {
+                if let Some(chunk) = tilemap.chunks.remove(&chunk_pos) {
+                    chunk_pool.recycle(chunk);
+                    mark_dirty(chunk_pos, DirtyCause::Removed);
+                }
+            }
+        }
 
         // A full clear was requested. Clear all chunks.
         if tilemap.clear_all {
-            for chunk in tilemap.chunks.values_mut() {
+            for (chunk_pos, chunk) in tilemap.chunks.iter_mut() {
                 chunk.clear();
+                mark_dirty(*chunk_pos, DirtyCause::Cleared);
             }
 
             tilemap.clear_all = false;
@@ -188,8 +1413,57 @@ pub(crate) fn update_chunks_system(mut tilemap_query: Query<(&mut TileMap, &mut
 
             // Process clear layer requests
             for layer in clear_layers.into_iter() {
-                for (_, chunk) in tilemap.chunks.iter_mut().filter(|(pos, _)| pos.z == layer) {
+                for (chunk_pos, chunk) in tilemap.chunks.iter_mut().filter(|(pos, _)| pos.z == layer) {
                     chunk.clear();
+                    mark_dirty(*chunk_pos, DirtyCause::Cleared);
+                }
+            }
+        }
+
+        // Insert whole chunks. This happens after clears, so chunks inserted after a clear are kept.
+        if !tilemap.insert_chunks.is_empty() {
+            let insert_chunks = std::mem::take(&mut tilemap.insert_chunks);
+
+            for mut chunk in insert_chunks {
+                let chunk_pos = calc_chunk_pos(chunk.origin);
+
+                chunk.origin = calc_chunk_origin(chunk_pos);
+                chunk.rebuild_occupancy();
+                chunk.last_change_at = Instant::now();
+
+                if let Some(replaced_chunk) = tilemap.chunks.insert(chunk_pos, chunk) {
+                    chunk_pool.recycle(replaced_chunk);
+                    mark_dirty(chunk_pos, DirtyCause::Removed);
+                }
+
+                mark_dirty(chunk_pos, DirtyCause::Created);
+            }
+        }
+
+        // Fill rectangles, creating chunks as needed
+        if !tilemap.fill_rects.is_empty() {
+            let fill_rects = std::mem::take(&mut tilemap.fill_rects);
+
+            for (rect, layer, tile) in fill_rects {
+                let min_chunk_pos = calc_chunk_pos(rect.min.extend(layer));
+                let max_chunk_pos = calc_chunk_pos(rect.max.extend(layer));
+
+                for y in min_chunk_pos.y..=max_chunk_pos.y {
+                    for x in min_chunk_pos.x..=max_chunk_pos.x {
+                        let chunk_pos = IVec3::new(x, y, layer);
+
+                        if let Some(chunk) = tilemap.chunks.get_mut(&chunk_pos) {
+                            chunk.fill_rect(rect, tile.clone());
+                            mark_dirty(chunk_pos, DirtyCause::Modified);
+                        } else if tile.is_some() {
+                            let mut chunk = chunk_pool.take(calc_chunk_origin(chunk_pos));
+                            chunk.fill_rect(rect, tile.clone());
+
+                            tilemap.chunks.insert(chunk_pos, chunk);
+
+                            mark_dirty(chunk_pos, DirtyCause::Created);
+                        }
+                    }
                 }
             }
         }
@@ -214,19 +1488,160 @@ pub(crate) fn update_chunks_system(mut tilemap_query: Query<(&mut TileMap, &mut
 
                 // Set tiles in chunk
                 chunk.set_tiles(tiles.drain(..));
+
+                mark_dirty(*chunk_pos, DirtyCause::Modified);
             } else {
                 // Chunk does not exist yet, and needs to be spawned...
 
                 let chunk_origin = calc_chunk_origin(*chunk_pos);
 
-                let mut chunk = Chunk::new(chunk_origin);
+                let mut chunk = chunk_pool.take(chunk_origin);
 
                 // Set tiles in chunk
                 chunk.set_tiles(tiles.drain(..));
 
                 // Store chunk entity in the tilemap
                 tilemap.chunks.insert(*chunk_pos, chunk);
+
+                mark_dirty(*chunk_pos, DirtyCause::Created);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::I64Vec2;
+
+    use super::*;
+
+    fn tile(sprite_index: u32) -> Tile {
+        Tile {
+            sprite_index,
+            ..default()
+        }
+    }
+
+    /// Rectangle covered by a chunk, in tile coordinates (inclusive)
+    fn chunk_rect(chunk: &Chunk) -> IRect {
+        let origin = chunk.origin.truncate();
+        IRect::from_corners(origin, origin + CHUNK_SIZE.as_ivec2() - IVec2::ONE)
+    }
+
+    #[test]
+    fn chunk_pos_of_negative_and_extreme_positions() {
+        for (x, y) in [
+            (0, 0),
+            (-1, -1),
+            (CHUNK_WIDTH_I32, -CHUNK_HEIGHT_I32),
+            (1_000_000_000, -1_000_000_000),
+            (-1_000_000_000, 1_000_000_000),
+            (i32::MIN, i32::MAX),
+            (i32::MAX, i32::MIN),
+        ] {
+            let pos = IVec3::new(x, y, 3);
+            let chunk_pos = calc_chunk_pos(pos);
+            let origin = calc_chunk_origin(chunk_pos);
+
+            assert_eq!(chunk_pos.z, 3);
+            assert_eq!(origin.z, 3);
+
+            // The position is within the chunk whose origin is at or below it
+            let local = pos.truncate().as_i64vec2() - origin.truncate().as_i64vec2();
+            assert!(local.cmpge(I64Vec2::ZERO).all(), "{pos} is below {origin}");
+            assert!(
+                local.cmplt(CHUNK_SIZE.as_i64vec2()).all(),
+                "{pos} is beyond the chunk at {origin}"
+            );
+        }
+
+        assert_eq!(calc_chunk_pos(IVec3::new(-1, -1, 0)), IVec3::new(-1, -1, 0));
+        assert_eq!(
+            calc_chunk_origin(calc_chunk_pos(IVec3::new(i32::MIN, i32::MIN, 0))).x,
+            i32::MIN
+        );
+    }
+
+    #[test]
+    fn fill_rect_across_chunk_boundaries() {
+        let rect = IRect::new(-2, -2, 1, 1);
+        let mut total = 0;
+
+        for chunk_pos in [
+            IVec2::new(-1, -1),
+            IVec2::new(0, -1),
+            IVec2::new(-1, 0),
+            IVec2::new(0, 0),
+        ] {
+            let mut chunk = Chunk::from_chunk_pos(chunk_pos.extend(0));
+            chunk.fill_rect(rect, Some(tile(1)));
+
+            assert_eq!(chunk.tile_count(), 4);
+            assert_eq!(chunk.bounds(), Some(rect.intersect(chunk_rect(&chunk))));
+            total += chunk.tile_count();
+        }
+
+        assert_eq!(total, 16);
+
+        let mut chunk = Chunk::from_chunk_pos(IVec3::ZERO);
+        chunk.fill_rect(rect, Some(tile(1)));
+        chunk.fill_rect(IRect::new(0, 0, 0, 0), None);
+
+        assert!(chunk.get_tile(IVec3::ZERO).is_none());
+        assert_eq!(
+            chunk.get_tile(IVec3::new(1, 1, 0)).map(|tile| tile.sprite_index),
+            Some(1)
+        );
+        assert_eq!(chunk.bounds(), Some(IRect::new(0, 0, 1, 1)));
+    }
+
+    #[test]
+    fn fill_rect_at_i32_limits() {
+        let full = IRect::new(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+
+        for pos in [IVec3::new(i32::MIN, i32::MIN, 0), IVec3::new(i32::MAX, i32::MAX, 0)] {
+            let mut chunk = Chunk::new(calc_chunk_origin(calc_chunk_pos(pos)));
+
+            chunk.fill_rect(full, Some(tile(2)));
+            assert_eq!(chunk.tile_count(), TILES_PER_CHUNK);
+            assert_eq!(chunk.get_tile(pos).map(|tile| tile.sprite_index), Some(2));
+
+            chunk.fill_rect(IRect::from_corners(pos.truncate(), pos.truncate()), None);
+            assert!(chunk.get_tile(pos).is_none());
+            assert_eq!(chunk.tile_count(), TILES_PER_CHUNK - 1);
+        }
+
+        // Rectangles entirely outside of the chunk, on the far side of the range of i32
+        let mut chunk = Chunk::new(calc_chunk_origin(calc_chunk_pos(IVec3::new(i32::MAX, i32::MAX, 0))));
+        chunk.fill_rect(
+            IRect::new(i32::MIN, i32::MIN, i32::MIN + 1, i32::MIN + 1),
+            Some(tile(3)),
+        );
+        assert!(chunk.is_empty());
+        assert!(chunk.get_tile(IVec3::new(i32::MIN, i32::MIN, 0)).is_none());
+
+        let mut chunk = Chunk::new(calc_chunk_origin(calc_chunk_pos(IVec3::new(i32::MIN, i32::MIN, 0))));
+        chunk.fill_rect(
+            IRect::new(i32::MAX - 1, i32::MAX - 1, i32::MAX, i32::MAX),
+            Some(tile(3)),
+        );
+        assert!(chunk.is_empty());
+        assert!(chunk.get_tile(IVec3::new(i32::MAX, i32::MAX, 0)).is_none());
+    }
+
+    #[test]
+    fn checked_offset_at_i32_limits() {
+        assert_eq!(
+            checked_offset(IVec3::new(-1, 2, 5), IVec2::new(1, -3)),
+            Some(IVec3::new(0, -1, 5))
+        );
+        assert_eq!(
+            checked_offset(IVec3::new(i32::MAX - 1, i32::MIN + 1, 0), IVec2::new(1, -1)),
+            Some(IVec3::new(i32::MAX, i32::MIN, 0))
+        );
+        assert_eq!(checked_offset(IVec3::new(i32::MAX, 0, 0), IVec2::X), None);
+        assert_eq!(checked_offset(IVec3::new(0, i32::MAX, 0), IVec2::Y), None);
+        assert_eq!(checked_offset(IVec3::new(i32::MIN, 0, 0), IVec2::NEG_X), None);
+        assert_eq!(checked_offset(IVec3::new(0, i32::MIN, 0), IVec2::NEG_Y), None);
+    }
+}
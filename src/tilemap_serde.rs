@@ -0,0 +1,195 @@
+use bevy::{prelude::*, sprite::AlphaMode2d, utils::HashMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    culling::TileMapCulling,
+    projection::TileMapProjection,
+    tilemap::{calc_chunk_origin, calc_chunk_pos},
+    Chunk, TileAnimation, TileColorMode, TileMap, TileMapLayer,
+};
+
+/// Asset paths of the handles of a tilemap and its layers, as of when it was serialized.
+/// Handles that were not loaded from a path, such as texture atlas layouts created in code, have no path.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct TileMapAssetPaths {
+    image: Option<String>,
+    texture_atlas_layout: Option<String>,
+    /// Image and texture atlas layout paths of layers with their own tileset
+    layers: HashMap<i32, (Option<String>, Option<String>)>,
+}
+
+/// Mirror of [`AlphaMode2d`] for serialization
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "AlphaMode2d")]
+enum AlphaMode2dDef {
+    Opaque,
+    Mask(f32),
+    Blend,
+}
+
+/// Serialized form of a [`TileMap`], borrowing from it
+#[derive(Serialize)]
+struct TileMapRef<'a> {
+    asset_paths: TileMapAssetPaths,
+    color_mode: TileColorMode,
+    #[serde(with = "AlphaMode2dDef")]
+    alpha_mode: AlphaMode2d,
+    depth_write_cutoff: Option<f32>,
+    depth_test: bool,
+    projection: TileMapProjection,
+    uv_inset: f32,
+    culling: TileMapCulling,
+    cull_margin: f32,
+    layer_z_scale: f32,
+    layer_z_offset: f32,
+    layers: &'a HashMap<i32, TileMapLayer>,
+    sprite_animations: &'a HashMap<u32, TileAnimation>,
+    tile_animations: Vec<(IVec3, &'a TileAnimation)>,
+    chunks: Vec<&'a Chunk>,
+}
+
+/// Serialized form of a [`TileMap`]. Must have the same fields as [`TileMapRef`].
+#[derive(Deserialize)]
+struct TileMapData {
+    asset_paths: TileMapAssetPaths,
+    color_mode: TileColorMode,
+    #[serde(with = "AlphaMode2dDef")]
+    alpha_mode: AlphaMode2d,
+    depth_write_cutoff: Option<f32>,
+    depth_test: bool,
+    projection: TileMapProjection,
+    uv_inset: f32,
+    culling: TileMapCulling,
+    cull_margin: f32,
+    layer_z_scale: f32,
+    layer_z_offset: f32,
+    layers: HashMap<i32, TileMapLayer>,
+    sprite_animations: HashMap<u32, TileAnimation>,
+    tile_animations: Vec<(IVec3, TileAnimation)>,
+    chunks: Vec<Chunk>,
+}
+
+/// Tiles, layers, animations and settings are serialized. Pending tile changes are not,
+/// so changes made since the tilemap was last updated are lost.
+///
+/// Handles are stored as the asset path they were loaded from, if any,
+/// and must be re-bound with [`TileMap::resolve_handles`] after deserializing.
+impl Serialize for TileMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let asset_paths = TileMapAssetPaths {
+            image: self.image.path().map(ToString::to_string),
+            texture_atlas_layout: self.texture_atlas_layout.path().map(ToString::to_string),
+            layers: self
+                .layers
+                .iter()
+                .filter(|(_, settings)| settings.image.is_some() || settings.texture_atlas_layout.is_some())
+                .map(|(layer, settings)| {
+                    (
+                        *layer,
+                        (
+                            settings.image.as_ref().and_then(Handle::path).map(ToString::to_string),
+                            settings
+                                .texture_atlas_layout
+                                .as_ref()
+                                .and_then(Handle::path)
+                                .map(ToString::to_string),
+                        ),
+                    )
+                })
+                .collect(),
+        };
+
+        // Chunks are stored by their origin, which determines their position in the map
+        let mut chunks: Vec<&Chunk> = self.chunks.values().collect();
+        chunks.sort_unstable_by_key(|chunk| (chunk.origin.z, chunk.origin.y, chunk.origin.x));
+
+        TileMapRef {
+            asset_paths,
+            color_mode: self.color_mode,
+            alpha_mode: self.alpha_mode,
+            depth_write_cutoff: self.depth_write_cutoff,
+            depth_test: self.depth_test,
+            projection: self.projection,
+            uv_inset: self.uv_inset,
+            culling: self.culling,
+            cull_margin: self.cull_margin,
+            layer_z_scale: self.layer_z_scale,
+            layer_z_offset: self.layer_z_offset,
+            layers: &self.layers,
+            sprite_animations: &self.sprite_animations,
+            tile_animations: self.tile_animations.iter().map(|(pos, anim)| (*pos, anim)).collect(),
+            chunks,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TileMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TileMapData::deserialize(deserializer)?;
+
+        let mut tilemap = TileMap::new(Handle::default(), Handle::default());
+
+        tilemap.color_mode = data.color_mode;
+        tilemap.alpha_mode = data.alpha_mode;
+        tilemap.depth_write_cutoff = data.depth_write_cutoff;
+        tilemap.depth_test = data.depth_test;
+        tilemap.projection = data.projection;
+        tilemap.uv_inset = data.uv_inset;
+        tilemap.culling = data.culling;
+        tilemap.cull_margin = data.cull_margin;
+        tilemap.layer_z_scale = data.layer_z_scale;
+        tilemap.layer_z_offset = data.layer_z_offset;
+        tilemap.layers = data.layers;
+        tilemap.sprite_animations = data.sprite_animations;
+        tilemap.tile_animations = data.tile_animations.into_iter().collect();
+        tilemap.chunks = data
+            .chunks
+            .into_iter()
+            .map(|mut chunk| {
+                // Align the origin with the chunk position, as chunks are looked up by position,
+                // and rebuild the bounds relative to it
+                let chunk_pos = calc_chunk_pos(chunk.origin);
+                chunk.origin = calc_chunk_origin(chunk_pos);
+                chunk.rebuild_occupancy();
+
+                (chunk_pos, chunk)
+            })
+            .collect();
+        tilemap.asset_paths = Some(data.asset_paths);
+
+        Ok(tilemap)
+    }
+}
+
+impl TileMap {
+    /// Re-bind the handles of a deserialized tilemap, as handles are not serialized.
+    ///
+    /// The resolvers are called for the tilemap itself with a layer of `None`, and for each layer that had its own
+    /// tileset. They are given the asset path of the handle when the tilemap was serialized, if it had one.
+    ///
+    /// ```ignore
+    /// tilemap.resolve_handles(
+    ///     |_, path| path.map(|path| asset_server.load(path)).unwrap_or_default(),
+    ///     |_, _| texture_atlas_layout.clone(),
+    /// );
+    /// ```
+    pub fn resolve_handles(
+        &mut self,
+        mut image: impl FnMut(Option<i32>, Option<&str>) -> Handle<Image>,
+        mut texture_atlas_layout: impl FnMut(Option<i32>, Option<&str>) -> Handle<TextureAtlasLayout>,
+    ) {
+        let asset_paths = self.asset_paths.take().unwrap_or_default();
+
+        self.image = image(None, asset_paths.image.as_deref());
+        self.texture_atlas_layout = texture_atlas_layout(None, asset_paths.texture_atlas_layout.as_deref());
+
+        for (layer, (image_path, texture_atlas_layout_path)) in asset_paths.layers {
+            let settings = self.layer_mut(layer);
+
+            settings.image = Some(image(Some(layer), image_path.as_deref()));
+            settings.texture_atlas_layout =
+                Some(texture_atlas_layout(Some(layer), texture_atlas_layout_path.as_deref()));
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureViewDescriptor, TextureViewDimension},
+};
+
+use crate::TileMap;
+
+/// Combine several tileset images into a single array texture, so that one tilemap can mix tiles from all of them.
+///
+/// Add this to a tilemap entity. Once all the images have loaded, they are packed into an array texture,
+/// which replaces the tilemap's image. All images must have the same size and format,
+/// and share the tilemap's texture atlas layout.
+///
+/// The tileset of a tile is encoded in its sprite index. Use [`TilesetArray::sprite_index`] to calculate it.
+#[derive(Component, Clone, Debug)]
+pub struct TilesetArray {
+    /// Tileset images, in array layer order
+    pub images: Vec<Handle<Image>>,
+    /// The array texture, once it has been built
+    array: Option<Handle<Image>>,
+    /// Whether building the array texture has been attempted
+    built: bool,
+}
+
+impl TilesetArray {
+    pub fn new(images: impl Into<Vec<Handle<Image>>>) -> Self {
+        Self {
+            images: images.into(),
+            array: None,
+            built: false,
+        }
+    }
+
+    /// The array texture, once all images have loaded
+    pub fn array(&self) -> Option<&Handle<Image>> {
+        self.array.as_ref()
+    }
+
+    /// Calculate the sprite index of a sprite within the specified tileset,
+    /// for tilemaps using a tileset array with the specified texture atlas layout
+    pub fn sprite_index(tileset: u32, sprite_index: u32, texture_atlas_layout: &TextureAtlasLayout) -> u32 {
+        tileset * texture_atlas_layout.len() as u32 + sprite_index
+    }
+}
+
+/// Pack the images of tileset arrays into array textures once they have loaded
+pub(crate) fn build_tileset_arrays_system(
+    mut tilemap_query: Query<(&mut TilesetArray, &mut TileMap)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (mut tileset_array, mut tilemap) in tilemap_query.iter_mut() {
+        if tileset_array.built {
+            continue;
+        }
+
+        let Some(sources) = tileset_array
+            .images
+            .iter()
+            .map(|image| images.get(image))
+            .collect::<Option<Vec<&Image>>>()
+        else {
+            continue;
+        };
+
+        tileset_array.built = true;
+
+        let Some(first) = sources.first() else {
+            continue;
+        };
+
+        let descriptor = &first.texture_descriptor;
+
+        if sources.iter().any(|image| {
+            image.texture_descriptor.size != descriptor.size
+                || image.texture_descriptor.format != descriptor.format
+                || image.texture_descriptor.mip_level_count != 1
+        }) {
+            error!("Tileset array images must all have the same size and format, and no mipmaps");
+            continue;
+        }
+
+        let layer_count = sources.len() as u32;
+        let data: Vec<u8> = sources.iter().flat_map(|image| image.data.iter().copied()).collect();
+
+        // Stack the images vertically, then reinterpret them as layers
+        let mut array = Image::new(
+            Extent3d {
+                width: descriptor.size.width,
+                height: descriptor.size.height * layer_count,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            descriptor.format,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+
+        array.reinterpret_stacked_2d_as_array(layer_count);
+        array.sampler = first.sampler.clone();
+        array.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..default()
+        });
+
+        let array = images.add(array);
+
+        tilemap.image = array.clone();
+        tileset_array.array = Some(array);
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::{culling::camera_chunk_positions, Chunk, TileMap};
+
+/// Unload chunks of a tilemap that are far away from every 2D camera.
+///
+/// Add this component to a tilemap entity to enable unloading. A square ring of chunks within
+/// `distance` chunks of each camera is kept loaded, and chunks outside of it are removed from
+/// the tilemap after sending a [`ChunkUnloadEvent`] containing their tiles, so they can be saved
+/// and restored later.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Debug)]
+pub struct ChunkUnloading {
+    /// Number of chunks around each camera to keep loaded, in each direction
+    pub distance: u32,
+}
+
+impl Default for ChunkUnloading {
+    fn default() -> Self {
+        Self { distance: 4 }
+    }
+}
+
+/// Sent when a chunk is unloaded by [`ChunkUnloading`], before it is removed from the tilemap
+#[derive(Event, Clone, Debug)]
+pub struct ChunkUnloadEvent {
+    /// Tilemap entity the chunk belonged to
+    pub tilemap: Entity,
+    pub chunk_pos: IVec3,
+    /// The chunk's tiles at the time it was unloaded
+    pub chunk: Chunk,
+}
+
+/// Unload chunks that are too far away from all cameras
+pub(crate) fn unload_distant_chunks_system(
+    mut tilemap_query: Query<(Entity, &mut TileMap, &ChunkUnloading, &GlobalTransform)>,
+    camera_query: Query<&GlobalTransform, With<Camera2d>>,
+    texture_atlases: Res<Assets<TextureAtlasLayout>>,
+    mut unload_events: EventWriter<ChunkUnloadEvent>,
+) {
+    for (entity, mut tilemap, unloading, transform) in tilemap_query.iter_mut() {
+        let Some(camera_chunks) = camera_chunk_positions(&tilemap, transform, &camera_query, &texture_atlases) else {
+            continue;
+        };
+
+        // Without any cameras, there is nothing to measure distance from
+        if camera_chunks.is_empty() {
+            continue;
+        }
+
+        let distance = unloading.distance as i32;
+
+        let distant_chunks: Vec<IVec3> = tilemap
+            .chunks
+            .keys()
+            .filter(|chunk_pos| {
+                camera_chunks.iter().all(|camera_chunk| {
+                    let offset = (chunk_pos.truncate() - *camera_chunk).abs();
+
+                    offset.max_element() > distance
+                })
+            })
+            .copied()
+            .collect();
+
+        if distant_chunks.is_empty() {
+            continue;
+        }
+
+        for chunk_pos in distant_chunks {
+            unload_events.send(ChunkUnloadEvent {
+                tilemap: entity,
+                chunk_pos,
+                chunk: tilemap.chunks[&chunk_pos].clone(),
+            });
+
+            tilemap.remove_chunk(chunk_pos);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{plugin::TileMapSystem, tilemap::checked_offset, Tile, TileMap};
+
+/// Plugin maintaining procedural [`WeatherOverlay`] layers
+#[derive(Default)]
+pub struct WeatherOverlayPlugin;
+
+impl Plugin for WeatherOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_weather_overlays_system.before(TileMapSystem::UpdateChunks),
+        );
+    }
+}
+
+/// Effect generated by a [`WeatherOverlay`]
+#[derive(Clone, Debug)]
+pub enum WeatherEffect {
+    /// Place `tile` over every source tile whose top side is exposed, meaning there is no tile
+    /// directly above it on the source layer.
+    Snow { tile: Tile },
+    /// Place `tile` over every source tile within `radius` tiles of a water tile.
+    /// Water tiles are identified by their sprite index.
+    Wet {
+        tile: Tile,
+        water: HashSet<u32>,
+        radius: i32,
+    },
+}
+
+/// Procedurally maintained overlay layer, derived from the tiles of a source layer.
+///
+/// The overlay is updated incrementally through the tilemap's normal change queue, examining at most
+/// `budget` tiles per frame, so keeping large maps up to date does not cause frame spikes.
+#[derive(Component, Clone, Debug)]
+pub struct WeatherOverlay {
+    /// Layer whose tiles determine where the overlay is placed
+    pub source_layer: i32,
+    /// Layer the overlay tiles are written to
+    pub overlay_layer: i32,
+    pub effect: WeatherEffect,
+    /// Maximum number of tiles examined per frame
+    pub budget: usize,
+    /// Positions remaining to be examined in the current pass
+    pending: Vec<IVec2>,
+}
+
+impl WeatherOverlay {
+    pub fn new(source_layer: i32, overlay_layer: i32, effect: WeatherEffect) -> Self {
+        Self {
+            source_layer,
+            overlay_layer,
+            effect,
+            budget: 1024,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Determine which tile, if any, the overlay should have at the specified position
+    fn overlay_tile(&self, tilemap: &TileMap, pos: IVec2) -> Option<Tile> {
+        let source_pos = pos.extend(self.source_layer);
+
+        tilemap.get_tile(source_pos)?;
+
+        match &self.effect {
+            WeatherEffect::Snow { tile } => {
+                let exposed = checked_offset(source_pos, IVec2::Y).is_none_or(|pos| tilemap.get_tile(pos).is_none());
+
+                exposed.then(|| tile.clone())
+            }
+            WeatherEffect::Wet { tile, water, radius } => {
+                let near_water = (-radius..=*radius).any(|y| {
+                    (-radius..=*radius).any(|x| {
+                        checked_offset(source_pos, IVec2::new(x, y))
+                            .and_then(|pos| tilemap.get_tile(pos))
+                            .is_some_and(|t| water.contains(&t.sprite_index))
+                    })
+                });
+
+                near_water.then(|| tile.clone())
+            }
+        }
+    }
+}
+
+/// Incrementally update weather overlays
+fn update_weather_overlays_system(mut query: Query<(&mut TileMap, &mut WeatherOverlay)>) {
+    for (mut tilemap, mut overlay) in query.iter_mut() {
+        if overlay.pending.is_empty() {
+            // Start a new pass, covering both the source tiles and any existing overlay tiles,
+            // so that overlay tiles whose source has been removed are cleaned up.
+            let (source_layer, overlay_layer) = (overlay.source_layer, overlay.overlay_layer);
+
+            let positions: HashSet<IVec2> = tilemap
+                .iter_tiles()
+                .filter(|(pos, _)| pos.z == source_layer || pos.z == overlay_layer)
+                .map(|(pos, _)| pos.truncate())
+                .collect();
+
+            overlay.pending = positions.into_iter().collect();
+        }
+
+        let budget = overlay.budget.min(overlay.pending.len());
+        let start = overlay.pending.len() - budget;
+        let batch: Vec<IVec2> = overlay.pending.drain(start..).collect();
+
+        for pos in batch {
+            let overlay_pos = pos.extend(overlay.overlay_layer);
+
+            let desired = overlay.overlay_tile(&tilemap, pos);
+            let current = tilemap.get_tile(overlay_pos);
+
+            // Only queue changes where the overlay actually differs
+            if desired.is_some() != current.is_some() {
+                tilemap.set_tile(overlay_pos, desired);
+            }
+        }
+    }
+}